@@ -0,0 +1,72 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+// Rows kept; older ones are dropped as new ones arrive
+const MAX_ENTRIES: i64 = 10_000;
+
+// Messages the receiver refused to apply, with the reason
+pub fn create_table(connection: &Connection) -> rusqlite::Result<()> {
+	connection.execute_batch(
+		"CREATE TABLE IF NOT EXISTS receiver_audit (
+			id INTEGER PRIMARY KEY AUTOINCREMENT,
+			at INTEGER NOT NULL,
+			project TEXT NOT NULL,
+			subject TEXT NOT NULL,
+			signer TEXT,
+			reason TEXT NOT NULL
+		);",
+	)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+	pub id: i64,
+	/// Unix time in milliseconds
+	pub at: i64,
+	pub project: String,
+	/// Topic or path the rejected message referred to
+	pub subject: String,
+	pub signer: Option<String>,
+	pub reason: String,
+}
+
+pub fn record(
+	connection: &Connection,
+	project: &str,
+	subject: &str,
+	signer: Option<&str>,
+	reason: &str,
+) -> rusqlite::Result<()> {
+	connection.execute(
+		"INSERT INTO receiver_audit (at, project, subject, signer, reason) VALUES (?1, ?2, ?3, ?4, ?5)",
+		params![
+			chrono::Utc::now().timestamp_millis(),
+			project,
+			subject,
+			signer,
+			reason
+		],
+	)?;
+	connection.execute(
+		"DELETE FROM receiver_audit WHERE id <= last_insert_rowid() - ?1",
+		params![MAX_ENTRIES],
+	)?;
+	Ok(())
+}
+
+pub fn recent(connection: &Connection, limit: u32) -> rusqlite::Result<Vec<AuditEntry>> {
+	let mut statement = connection.prepare(
+		"SELECT id, at, project, subject, signer, reason FROM receiver_audit ORDER BY id DESC LIMIT ?1",
+	)?;
+	let rows = statement.query_map(params![limit], |row| {
+		Ok(AuditEntry {
+			id: row.get(0)?,
+			at: row.get(1)?,
+			project: row.get(2)?,
+			subject: row.get(3)?,
+			signer: row.get(4)?,
+			reason: row.get(5)?,
+		})
+	})?;
+	rows.collect()
+}
@@ -0,0 +1,47 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+// Projects whose MQTT traffic is encrypted. Their keys are kept in the OS credential store,
+// since the webview can open this database
+pub fn create_table(connection: &Connection) -> rusqlite::Result<()> {
+	connection.execute_batch(
+		"CREATE TABLE IF NOT EXISTS encrypted_projects (
+			project TEXT PRIMARY KEY,
+			created_at INTEGER NOT NULL
+		);",
+	)
+}
+
+pub fn contains(connection: &Connection, project: &str) -> rusqlite::Result<bool> {
+	connection
+		.query_row(
+			"SELECT 1 FROM encrypted_projects WHERE project = ?1",
+			params![project],
+			|_| Ok(()),
+		)
+		.optional()
+		.map(|found| found.is_some())
+}
+
+pub fn add(connection: &Connection, project: &str) -> rusqlite::Result<()> {
+	connection.execute(
+		"INSERT INTO encrypted_projects (project, created_at) VALUES (?1, ?2)
+		ON CONFLICT(project) DO UPDATE SET created_at = excluded.created_at",
+		params![project, chrono::Utc::now().timestamp_millis()],
+	)?;
+	Ok(())
+}
+
+pub fn remove(connection: &Connection, project: &str) -> rusqlite::Result<bool> {
+	let removed = connection.execute(
+		"DELETE FROM encrypted_projects WHERE project = ?1",
+		params![project],
+	)?;
+	Ok(removed > 0)
+}
+
+pub fn projects(connection: &Connection) -> rusqlite::Result<Vec<String>> {
+	let mut statement =
+		connection.prepare("SELECT project FROM encrypted_projects ORDER BY project")?;
+	let rows = statement.query_map([], |row| row.get(0))?;
+	rows.collect()
+}
@@ -1,4 +1,11 @@
+pub mod audit;
 pub mod db;
+pub mod keys;
+pub mod outbox;
+pub mod projects;
+pub mod sequences;
+pub mod store;
+pub mod trust;
 pub mod migrations {
 	pub mod users;
 }
@@ -0,0 +1,74 @@
+use rusqlite::{params, Connection};
+
+// Outgoing MQTT messages waiting for the broker's acknowledgement, in emission order
+pub struct OutboxEntry {
+	pub seq: i64,
+	pub topic: String,
+	pub payload: Vec<u8>,
+}
+
+pub fn create_table(connection: &Connection) -> rusqlite::Result<()> {
+	connection.execute_batch(
+		"CREATE TABLE IF NOT EXISTS mqtt_outbox (
+			seq INTEGER PRIMARY KEY AUTOINCREMENT,
+			topic TEXT NOT NULL,
+			payload BLOB NOT NULL,
+			created_at INTEGER NOT NULL
+		);",
+	)
+}
+
+pub fn enqueue(connection: &Connection, topic: &str, payload: &[u8]) -> rusqlite::Result<i64> {
+	connection.execute(
+		"INSERT INTO mqtt_outbox (topic, payload, created_at) VALUES (?1, ?2, ?3)",
+		params![topic, payload, chrono::Utc::now().timestamp_millis()],
+	)?;
+	Ok(connection.last_insert_rowid())
+}
+
+pub fn oldest(connection: &Connection, limit: usize) -> rusqlite::Result<Vec<OutboxEntry>> {
+	let mut statement =
+		connection.prepare("SELECT seq, topic, payload FROM mqtt_outbox ORDER BY seq LIMIT ?1")?;
+	let rows = statement.query_map(params![limit as i64], |row| {
+		Ok(OutboxEntry {
+			seq: row.get(0)?,
+			topic: row.get(1)?,
+			payload: row.get(2)?,
+		})
+	})?;
+	rows.collect()
+}
+
+pub fn remove(connection: &Connection, seq: i64) -> rusqlite::Result<()> {
+	connection.execute("DELETE FROM mqtt_outbox WHERE seq = ?1", params![seq])?;
+	Ok(())
+}
+
+pub fn count(connection: &Connection) -> rusqlite::Result<u64> {
+	connection.query_row("SELECT COUNT(*) FROM mqtt_outbox", [], |row| row.get(0))
+}
+
+/// Drops entries older than `max_age_secs` and the oldest ones beyond `max_messages`.
+/// Returns how many entries were discarded.
+pub fn prune(
+	connection: &Connection,
+	max_messages: Option<u64>,
+	max_age_secs: Option<u64>,
+) -> rusqlite::Result<usize> {
+	let mut removed = 0;
+	if let Some(max_age) = max_age_secs {
+		let cutoff = chrono::Utc::now().timestamp_millis() - (max_age as i64) * 1000;
+		removed += connection.execute(
+			"DELETE FROM mqtt_outbox WHERE created_at < ?1",
+			params![cutoff],
+		)?;
+	}
+	if let Some(max) = max_messages {
+		removed += connection.execute(
+			"DELETE FROM mqtt_outbox WHERE seq NOT IN
+				(SELECT seq FROM mqtt_outbox ORDER BY seq DESC LIMIT ?1)",
+			params![max as i64],
+		)?;
+	}
+	Ok(removed)
+}
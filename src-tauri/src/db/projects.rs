@@ -0,0 +1,130 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+// The users and proyects tables belong to the frontend (src/api/db.ts) and are created with
+// the same definitions; the Rust side only adds the per-project settings columns it needs
+pub fn create_table(connection: &Connection) -> rusqlite::Result<()> {
+	connection.execute_batch(
+		"CREATE TABLE IF NOT EXISTS users (
+			id INTEGER PRIMARY KEY AUTOINCREMENT,
+			name TEXT NOT NULL
+		);
+		CREATE TABLE IF NOT EXISTS proyects (
+			id INTEGER PRIMARY KEY AUTOINCREMENT,
+			name TEXT NOT NULL,
+			path TEXT NOT NULL,
+			separator TEXT NOT NULL,
+			user_id INTEGER,
+			last_selected INTEGER,
+			FOREIGN KEY (user_id) REFERENCES users(id)
+		);",
+	)?;
+	add_column(connection, "delete_policy", "TEXT")?;
+	add_column(connection, "file_rules", "TEXT")?;
+	add_column(connection, "encoding", "TEXT")?;
+	add_column(connection, "log_formats", "TEXT")?;
+	add_column(connection, "log_format", "TEXT")?;
+	add_column(connection, "timestamps", "TEXT")?;
+	add_column(connection, "trust_policy", "TEXT")?;
+	Ok(())
+}
+
+fn add_column(connection: &Connection, column: &str, definition: &str) -> rusqlite::Result<()> {
+	let exists = connection
+		.prepare("SELECT 1 FROM pragma_table_info('proyects') WHERE name = ?1")?
+		.exists(params![column])?;
+	if !exists {
+		connection.execute_batch(&format!(
+			"ALTER TABLE proyects ADD COLUMN {} {};",
+			column, definition
+		))?;
+	}
+	Ok(())
+}
+
+// Projects are matched by name the same way the frontend does
+const NAME_MATCHES: &str = "TRIM(LOWER(name)) = TRIM(LOWER(?1))";
+
+/// Reads one settings column; `None` when the project does not exist.
+pub fn setting(
+	connection: &Connection,
+	project: &str,
+	column: &str,
+) -> rusqlite::Result<Option<Option<String>>> {
+	connection
+		.query_row(
+			&format!(
+				"SELECT {} FROM proyects WHERE {} LIMIT 1",
+				column, NAME_MATCHES
+			),
+			params![project],
+			|row| row.get(0),
+		)
+		.optional()
+}
+
+/// Returns false when the project does not exist.
+pub fn set_setting(
+	connection: &Connection,
+	project: &str,
+	column: &str,
+	value: Option<&str>,
+) -> rusqlite::Result<bool> {
+	let updated = connection.execute(
+		&format!("UPDATE proyects SET {} = ?2 WHERE {}", column, NAME_MATCHES),
+		params![project, value],
+	)?;
+	Ok(updated > 0)
+}
+
+/// Local folder of the project, matched by name like the frontend's `getProyect`.
+pub fn find_path(connection: &Connection, project: &str) -> rusqlite::Result<Option<String>> {
+	connection
+		.query_row(
+			&format!("SELECT path FROM proyects WHERE {} LIMIT 1", NAME_MATCHES),
+			params![project],
+			|row| row.get(0),
+		)
+		.optional()
+}
+
+pub fn insert(
+	connection: &Connection,
+	project: &str,
+	path: &str,
+	separator: &str,
+) -> rusqlite::Result<()> {
+	connection.execute(
+		"INSERT INTO proyects (name, path, separator, last_selected) VALUES (?1, ?2, ?3, 0)",
+		params![project, path, separator],
+	)?;
+	Ok(())
+}
+
+/// Name of the project registered with `path`, the folder the emitter watches.
+pub fn find_name(connection: &Connection, path: &str) -> rusqlite::Result<Option<String>> {
+	connection
+		.query_row(
+			"SELECT name FROM proyects WHERE path = ?1 LIMIT 1",
+			params![path],
+			|row| row.get(0),
+		)
+		.optional()
+}
+
+/// Name of the project whose folder holds `file` (or is `file`), the innermost one if several do.
+pub fn find_containing(connection: &Connection, file: &Path) -> rusqlite::Result<Option<String>> {
+	let mut statement = connection.prepare("SELECT name, path FROM proyects")?;
+	let rows = statement.query_map([], |row| {
+		Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+	})?;
+	let mut best: Option<(String, usize)> = None;
+	for row in rows {
+		let (name, path) = row?;
+		let depth = Path::new(&path).components().count();
+		if file.starts_with(&path) && best.as_ref().is_none_or(|(_, best)| depth > *best) {
+			best = Some((name, depth));
+		}
+	}
+	Ok(best.map(|(name, _)| name))
+}
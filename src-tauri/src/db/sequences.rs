@@ -0,0 +1,41 @@
+use rusqlite::{params, Connection};
+
+// Last sequence number the emitter assigned to each stream ("<project>/<path>"), and the
+// epoch those numbers belong to
+pub fn create_table(connection: &Connection) -> rusqlite::Result<()> {
+	connection.execute_batch(
+		"CREATE TABLE IF NOT EXISTS emitter_sequences (
+			stream TEXT PRIMARY KEY,
+			last_seq INTEGER NOT NULL,
+			epoch TEXT
+		);",
+	)?;
+	// Tables created before epochs existed
+	let has_epoch = connection
+		.prepare("SELECT 1 FROM pragma_table_info('emitter_sequences') WHERE name = 'epoch'")?
+		.exists([])?;
+	if !has_epoch {
+		connection.execute_batch("ALTER TABLE emitter_sequences ADD COLUMN epoch TEXT;")?;
+	}
+	Ok(())
+}
+
+/// Reserves `count` consecutive sequence numbers of `stream` and returns the first, with the
+/// stream's epoch. Numbers start at 1; `new_epoch` becomes the epoch of a stream that has
+/// none yet, so a stream numbered again from 1 (its row lost with the database) always gets a
+/// different one.
+pub fn reserve(
+	connection: &Connection,
+	stream: &str,
+	count: u64,
+	new_epoch: &str,
+) -> rusqlite::Result<(u64, String)> {
+	let (last, epoch) = connection.query_row(
+		"INSERT INTO emitter_sequences (stream, last_seq, epoch) VALUES (?1, ?2, ?3)
+		ON CONFLICT(stream) DO UPDATE SET last_seq = last_seq + ?2, epoch = COALESCE(epoch, ?3)
+		RETURNING last_seq, epoch",
+		params![stream, count as i64, new_epoch],
+		|row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, String>(1)?)),
+	)?;
+	Ok((last + 1 - count, epoch))
+}
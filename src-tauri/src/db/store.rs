@@ -0,0 +1,58 @@
+use rusqlite::Connection;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::db::{audit, keys, outbox, projects, sequences, trust};
+use crate::settings;
+
+// Same file the frontend opens through tauri-plugin-sql ("sqlite:database.sqlite")
+const DATABASE_FILE: &str = "database.sqlite";
+
+// Rust-side connection to the app database
+pub struct Database(pub Mutex<Connection>);
+
+impl Database {
+	/// Opens the app database, falling back to an in-memory one so the app can still start.
+	pub fn open(app_handle: &AppHandle) -> Self {
+		let connection = match open_file(app_handle) {
+			Ok(connection) => connection,
+			Err(e) => {
+				eprintln!("Database: {}. Using an in-memory database instead.", e);
+				let connection = Connection::open_in_memory().expect("in-memory sqlite");
+				if let Err(e) = create_tables(&connection) {
+					eprintln!("Database: Failed to create tables: {}", e);
+				}
+				connection
+			}
+		};
+		Database(Mutex::new(connection))
+	}
+}
+
+fn open_file(app_handle: &AppHandle) -> Result<Connection, String> {
+	let path = settings::config_file_path(app_handle, DATABASE_FILE)?;
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+	}
+
+	let connection =
+		Connection::open(&path).map_err(|e| format!("Error abriendo {}: {}", path.display(), e))?;
+	// The webview keeps its own connection to the same file
+	connection
+		.busy_timeout(Duration::from_secs(5))
+		.map_err(|e| e.to_string())?;
+	create_tables(&connection).map_err(|e| format!("Error creando tablas: {}", e))?;
+	Ok(connection)
+}
+
+fn create_tables(connection: &Connection) -> rusqlite::Result<()> {
+	outbox::create_table(connection)?;
+	sequences::create_table(connection)?;
+	keys::create_table(connection)?;
+	trust::create_tables(connection)?;
+	audit::create_table(connection)?;
+	projects::create_table(connection)?;
+	Ok(())
+}
@@ -0,0 +1,151 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+// Per project, the public keys a receiver accepts log messages from and, apart, the keys an
+// emitter answers snapshot requests from. This installation's Ed25519 secret is kept in the
+// OS credential store, since the webview can open this database
+pub fn create_tables(connection: &Connection) -> rusqlite::Result<()> {
+	connection.execute_batch(
+		"CREATE TABLE IF NOT EXISTS trusted_emitters (
+			project TEXT NOT NULL,
+			public_key TEXT NOT NULL,
+			label TEXT,
+			added_at INTEGER NOT NULL,
+			first_use INTEGER NOT NULL DEFAULT 0,
+			PRIMARY KEY (project, public_key)
+		);
+		CREATE TABLE IF NOT EXISTS trusted_requesters (
+			project TEXT NOT NULL,
+			public_key TEXT NOT NULL,
+			label TEXT,
+			added_at INTEGER NOT NULL,
+			first_use INTEGER NOT NULL DEFAULT 0,
+			PRIMARY KEY (project, public_key)
+		);",
+	)
+}
+
+/// Which of a project's trust lists a key belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrustList {
+	/// Emitters whose log messages the receiver applies
+	Emitters,
+	/// Receivers whose snapshot requests the emitter answers
+	Requesters,
+}
+
+impl TrustList {
+	fn table(self) -> &'static str {
+		match self {
+			TrustList::Emitters => "trusted_emitters",
+			TrustList::Requesters => "trusted_requesters",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustedEmitter {
+	pub public_key: String,
+	pub label: Option<String>,
+	pub added_at: i64,
+	/// Pinned by the first-use policy rather than added by the user
+	pub first_use: bool,
+}
+
+/// Trusts `public_key` on the user's behalf; a key pinned on first use becomes explicit.
+pub fn trust(
+	connection: &Connection,
+	list: TrustList,
+	project: &str,
+	public_key: &str,
+	label: Option<&str>,
+) -> rusqlite::Result<()> {
+	connection.execute(
+		&format!(
+			"INSERT INTO {} (project, public_key, label, added_at) VALUES (?1, ?2, ?3, ?4)
+			ON CONFLICT(project, public_key) DO UPDATE SET label = excluded.label, first_use = 0",
+			list.table()
+		),
+		params![
+			project,
+			public_key,
+			label,
+			chrono::Utc::now().timestamp_millis()
+		],
+	)?;
+	Ok(())
+}
+
+/// Trusts the emitter `public_key` only if the project trusts nobody yet; returns whether it
+/// did.
+pub fn trust_first(
+	connection: &Connection,
+	project: &str,
+	public_key: &str,
+	label: &str,
+) -> rusqlite::Result<bool> {
+	let added = connection.execute(
+		"INSERT INTO trusted_emitters (project, public_key, label, added_at, first_use)
+		SELECT ?1, ?2, ?3, ?4, 1
+		WHERE NOT EXISTS (SELECT 1 FROM trusted_emitters WHERE project = ?1)",
+		params![
+			project,
+			public_key,
+			label,
+			chrono::Utc::now().timestamp_millis()
+		],
+	)?;
+	Ok(added > 0)
+}
+
+/// Whether the user added the emitter `public_key` to the project, as opposed to a first-use
+/// pin.
+pub fn trusted_explicitly(
+	connection: &Connection,
+	project: &str,
+	public_key: &str,
+) -> rusqlite::Result<bool> {
+	connection
+		.prepare(
+			"SELECT 1 FROM trusted_emitters
+			WHERE project = ?1 AND public_key = ?2 AND first_use = 0",
+		)?
+		.exists(params![project, public_key])
+}
+
+pub fn untrust(
+	connection: &Connection,
+	list: TrustList,
+	project: &str,
+	public_key: &str,
+) -> rusqlite::Result<bool> {
+	let removed = connection.execute(
+		&format!(
+			"DELETE FROM {} WHERE project = ?1 AND public_key = ?2",
+			list.table()
+		),
+		params![project, public_key],
+	)?;
+	Ok(removed > 0)
+}
+
+pub fn trusted(
+	connection: &Connection,
+	list: TrustList,
+	project: &str,
+) -> rusqlite::Result<Vec<TrustedEmitter>> {
+	let mut statement = connection.prepare(&format!(
+		"SELECT public_key, label, added_at, first_use FROM {} WHERE project = ?1
+		ORDER BY added_at",
+		list.table()
+	))?;
+	let rows = statement.query_map(params![project], |row| {
+		Ok(TrustedEmitter {
+			public_key: row.get(0)?,
+			label: row.get(1)?,
+			added_at: row.get(2)?,
+			first_use: row.get(3)?,
+		})
+	})?;
+	rows.collect()
+}
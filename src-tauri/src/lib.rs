@@ -1,6 +1,8 @@
 mod db;
+mod mqtt;
 mod permissions;
 mod process;
+mod settings;
 
 use once_cell::sync::Lazy;
 use std::path::PathBuf;
@@ -9,9 +11,20 @@ use std::time::Instant;
 use tauri::{path::BaseDirectory::AppConfig, Manager};
 use tokio::sync::mpsc;
 
-use crate::process::background::{MqttReceiverListener, MqttReceiverStopper};
+use crate::db::store::Database;
+use crate::mqtt::service::MqttService;
+use crate::process::background::MqttReceiverStopper;
+use crate::process::file_rules::EmitterFileFilter;
+use crate::process::log_sessions::LogSessions;
 use crate::process::mode::{AppMode, AppModeState};
-use crate::process::watch::WatcherStopper;
+use crate::process::outbox::OutboxSignal;
+use crate::process::project_keys::ProjectKeys;
+use crate::process::project_paths::ProjectPaths;
+use crate::process::sequencer::Sequencer;
+use crate::process::snapshot::SnapshotSync;
+use crate::process::subscriptions::ReceiverSubscriptions;
+use crate::process::trust::{SigningIdentity, TrustLists};
+use crate::process::watch::{WatchedRoot, WatcherStopper};
 
 use tauri::{menu::MenuItemBuilder, Listener};
 
@@ -27,8 +40,19 @@ pub fn run() {
 		.manage(ActiveProjectPath(Mutex::new(None)))
 		.manage(AppModeState(Mutex::new(AppMode::None))) // Add this line
 		.manage(WatcherStopper::default()) // Manage WatcherStopper
+		.manage(WatchedRoot::default())
 		.manage(MqttReceiverStopper::default()) // Manage MqttReceiverStopper
-		.manage(MqttReceiverListener::default())
+		.manage(MqttService::default()) // Shared MQTT connection for every mode
+		.manage(OutboxSignal::default())
+		.manage(SnapshotSync::default())
+		.manage(Sequencer::default())
+		.manage(ProjectPaths::default())
+		.manage(ReceiverSubscriptions::default())
+		.manage(EmitterFileFilter::default())
+		.manage(LogSessions::default())
+		.manage(ProjectKeys::default())
+		.manage(SigningIdentity::default())
+		.manage(TrustLists::default())
 		.plugin(
 			tauri_plugin_sql::Builder::default()
 				.add_migrations(
@@ -44,6 +68,9 @@ pub fn run() {
 		.setup(|app| {
 			println!("{:?}", AppConfig);
 
+			// Rust-side connection to the app database (outbox, ...)
+			app.manage(Database::open(app.app_handle()));
+
 			// Prevent tray icon duplication on hot-reloads
 			if app.tray_by_id("main-tray").is_none() {
 				let app_mode_state = app.state::<AppModeState>();
@@ -55,6 +82,7 @@ pub fn run() {
 			// Spawn the async background task
 			let app_handle = app.app_handle().clone();
 			let app_handle_for_bg = app_handle.clone();
+			let app_handle_for_gave_up = app_handle.clone();
 			tauri::async_runtime::spawn(async move {
 				process::r#try::spawn_background_thread(app_handle_for_bg).await;
 			});
@@ -85,38 +113,41 @@ pub fn run() {
 				process::watch::stop_watcher(&app_handle);
 				process::background::stop_receiver(&app_handle); // Stop MQTT receiver
 
-				match current_mode {
-					AppMode::Receiver => {
-						let (tx, rx) = mpsc::channel(1);
-						let receiver_stopper_state = app_handle.state::<MqttReceiverStopper>();
-						*receiver_stopper_state.0.lock().unwrap() = Some(tx); // Store tx for MQTT receiver stopper
-
-						let app_handle_for_receiver = app_handle.clone();
-						tauri::async_runtime::spawn(async move {
-							process::background::init(app_handle_for_receiver, rx).await;
-						});
-					}
-					AppMode::Emitter => {
-						// Create a channel to stop the watcher
-						let (tx, rx) = mpsc::channel(1);
-						// Store the sender in the WatcherStopper state
-						let watcher_stopper_state = app_handle.state::<WatcherStopper>();
-						*watcher_stopper_state.0.lock().unwrap() = Some(tx);
-
-						let app_handle_for_watcher = app_handle.clone();
-						// Spawn the watcher task
-						tauri::async_runtime::spawn(async move {
-							if let Err(e) =
-								process::watch::start_watcher(app_handle_for_watcher, rx).await
-							{
-								eprintln!("Failed to start file watcher: {}", e);
-							}
-						});
-					}
-					AppMode::None => {
-						// No specific action needed, services are already stopped
-					}
+				if current_mode.is_receiver() {
+					let (tx, rx) = mpsc::channel(1);
+					let receiver_stopper_state = app_handle.state::<MqttReceiverStopper>();
+					*receiver_stopper_state.0.lock().unwrap() = Some(tx); // Store tx for MQTT receiver stopper
+
+					let app_handle_for_receiver = app_handle.clone();
+					tauri::async_runtime::spawn(async move {
+						process::background::init(app_handle_for_receiver, rx).await;
+					});
 				}
+				if current_mode.is_emitter() {
+					// Create a channel to stop the watcher
+					let (tx, rx) = mpsc::channel(1);
+					// Store the sender in the WatcherStopper state
+					let watcher_stopper_state = app_handle.state::<WatcherStopper>();
+					*watcher_stopper_state.0.lock().unwrap() = Some(tx);
+
+					let app_handle_for_watcher = app_handle.clone();
+					// Spawn the watcher task
+					tauri::async_runtime::spawn(async move {
+						if let Err(e) =
+							process::watch::start_watcher(app_handle_for_watcher, rx).await
+						{
+							eprintln!("Failed to start file watcher: {}", e);
+						}
+					});
+				}
+				// AppMode::None: no specific action needed, services are already stopped
+			});
+
+			// The shared connection stopped retrying and dropped its owners: stop the modes
+			// so their tasks end; the selected mode is kept for the UI to restart it
+			app.listen("mqtt-gave-up", move |_event| {
+				process::watch::stop_watcher(&app_handle_for_gave_up);
+				process::background::stop_receiver(&app_handle_for_gave_up);
 			});
 
 			MenuItemBuilder::new("Toggle")
@@ -129,7 +160,6 @@ pub fn run() {
 			permissions::write::set_write_permission,
 			permissions::read::set_read_permission,
 			permissions::remove::set_remove_permission,
-			process::logs::process_log_file,
 			process::watch_file::watch_file,
 			process::getpath::open_folder_dialog,
 			process::getpath::get_folder_contents,
@@ -137,7 +167,54 @@ pub fn run() {
 			process::excel::guardar_excel,
 			process::system_info::get_system_parameters,
 			process::system_info::get_system_info_formatted,
-			process::state_sync::set_monitored_project
+			process::state_sync::set_monitored_project,
+			process::mode::get_app_mode,
+			mqtt::profile::get_mqtt_profile,
+			mqtt::profile::update_mqtt_profile,
+			mqtt::connection::test_mqtt_connection,
+			mqtt::status::get_mqtt_status,
+			process::emitter_settings::get_emitter_settings,
+			process::emitter_settings::update_emitter_settings,
+			process::snapshot::request_project_snapshot,
+			process::project_keys::generate_project_key,
+			process::project_keys::import_project_key,
+			process::project_keys::export_project_key,
+			process::project_keys::remove_project_key,
+			process::project_keys::list_encrypted_projects,
+			process::trust::generate_signing_key,
+			process::trust::get_signing_public_key,
+			process::trust::export_signing_key,
+			process::trust::import_signing_key,
+			process::trust::trust_emitter,
+			process::trust::untrust_emitter,
+			process::trust::list_trusted_emitters,
+			process::trust::trust_requester,
+			process::trust::untrust_requester,
+			process::trust::list_trusted_requesters,
+			process::project_settings::get_project_trust_policy,
+			process::project_settings::set_project_trust_policy,
+			process::audit::get_audit_log,
+			process::project_settings::get_project_delete_policy,
+			process::project_settings::set_project_delete_policy,
+			process::project_paths::invalidate_project_paths,
+			process::receiver_settings::get_receiver_settings,
+			process::receiver_settings::update_receiver_settings,
+			process::subscriptions::get_receiver_subscriptions,
+			process::subscriptions::update_receiver_subscriptions,
+			process::project_settings::get_project_file_rules,
+			process::project_settings::set_project_file_rules,
+			process::project_settings::get_project_encoding,
+			process::project_settings::set_project_encoding,
+			process::log_sessions::open_log_session,
+			process::log_sessions::get_log_rows,
+			process::log_sessions::close_log_session,
+			process::project_settings::get_log_formats,
+			process::project_settings::set_project_log_formats,
+			process::project_settings::get_project_log_format,
+			process::project_settings::select_project_log_format,
+			process::project_settings::get_project_timestamps,
+			process::project_settings::set_project_timestamps,
+			process::log_query::query_log
 		])
 		.run(tauri::generate_context!())
 		.expect("error while running tauri application");
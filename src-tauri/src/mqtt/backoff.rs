@@ -0,0 +1,64 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Reconnection policy for the shared MQTT connection, stored with the broker profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+	pub initial_delay_ms: u64,
+	pub max_delay_ms: u64,
+	pub multiplier: f64,
+	/// Fraction of the delay randomly added or removed (0.0 - 1.0)
+	pub jitter: f64,
+	/// Give up after this many consecutive failed attempts; `None` retries forever
+	pub max_retries: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+	fn default() -> Self {
+		ReconnectPolicy {
+			initial_delay_ms: 1_000,
+			max_delay_ms: 60_000,
+			multiplier: 2.0,
+			jitter: 0.2,
+			max_retries: None,
+		}
+	}
+}
+
+impl ReconnectPolicy {
+	pub fn validate(&self) -> Result<(), String> {
+		if self.initial_delay_ms == 0 || self.max_delay_ms < self.initial_delay_ms {
+			return Err("Los tiempos de reconexión no son válidos".to_string());
+		}
+		if self.multiplier.is_nan() || self.multiplier < 1.0 {
+			return Err("El multiplicador de reconexión debe ser mayor o igual a 1".to_string());
+		}
+		if !(0.0..=1.0).contains(&self.jitter) {
+			return Err("El jitter de reconexión debe estar entre 0 y 1".to_string());
+		}
+		Ok(())
+	}
+
+	pub fn is_exhausted(&self, attempt: u32) -> bool {
+		self.max_retries.is_some_and(|max| attempt > max)
+	}
+
+	/// Delay before reconnection attempt number `attempt` (starting at 1).
+	pub fn delay_for(&self, attempt: u32) -> Duration {
+		let exponent = attempt.saturating_sub(1).min(63) as i32;
+		let base = (self.initial_delay_ms as f64 * self.multiplier.powi(exponent))
+			.min(self.max_delay_ms as f64);
+
+		let jitter = self.jitter.clamp(0.0, 1.0);
+		let factor = if jitter > 0.0 {
+			1.0 + rand::thread_rng().gen_range(-jitter..=jitter)
+		} else {
+			1.0
+		};
+
+		let delay_ms = (base * factor).clamp(0.0, self.max_delay_ms as f64);
+		Duration::from_millis(delay_ms as u64)
+	}
+}
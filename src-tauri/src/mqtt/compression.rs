@@ -0,0 +1,45 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+// Upper bound for decompressed content, so a small payload cannot expand without limit
+const MAX_DECOMPRESSED: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+	Gzip,
+	Zstd,
+}
+
+pub fn compress(compression: Compression, data: &[u8]) -> Result<Vec<u8>, String> {
+	match compression {
+		Compression::Gzip => {
+			let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+			encoder
+				.write_all(data)
+				.and_then(|_| encoder.finish())
+				.map_err(|e| format!("Error comprimiendo con gzip: {}", e))
+		}
+		Compression::Zstd => {
+			zstd::encode_all(data, 0).map_err(|e| format!("Error comprimiendo con zstd: {}", e))
+		}
+	}
+}
+
+pub fn decompress(compression: Compression, data: &[u8]) -> Result<Vec<u8>, String> {
+	let mut output = Vec::new();
+	let read = match compression {
+		Compression::Gzip => GzDecoder::new(data)
+			.take(MAX_DECOMPRESSED + 1)
+			.read_to_end(&mut output),
+		Compression::Zstd => zstd::Decoder::new(data)
+			.and_then(|decoder| decoder.take(MAX_DECOMPRESSED + 1).read_to_end(&mut output)),
+	};
+	read.map_err(|e| format!("Contenido comprimido inválido: {}", e))?;
+	if output.len() as u64 > MAX_DECOMPRESSED {
+		return Err("El contenido descomprimido supera el tamaño máximo".to_string());
+	}
+	Ok(output)
+}
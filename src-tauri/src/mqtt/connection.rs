@@ -0,0 +1,150 @@
+use rand::Rng;
+use rumqttc::{Client, Event, MqttOptions, Packet, RecvTimeoutError, Transport};
+use std::fs::File;
+use std::io::BufReader;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle};
+
+use crate::mqtt::profile::{self, MqttProfile};
+
+// Largest MQTT packet sent or accepted
+pub const MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+// Builds the MQTT options for a given role ("emitter", "receiver", ...) from the broker profile
+pub fn build_mqtt_options(profile: &MqttProfile, role: &str) -> Result<MqttOptions, String> {
+	profile.validate()?;
+
+	// Generate a random client ID suffix to prevent collisions
+	let random_suffix: String = rand::thread_rng()
+		.sample_iter(&rand::distributions::Alphanumeric)
+		.take(6)
+		.map(char::from)
+		.collect();
+	let client_id = format!(
+		"{}_{}_{}",
+		profile.client_id_prefix.trim(),
+		role,
+		random_suffix
+	);
+
+	let mut mqtt_options = MqttOptions::new(client_id, profile.host.trim(), profile.port);
+	if let Some(username) = &profile.username {
+		mqtt_options.set_credentials(username, profile.password.clone().unwrap_or_default());
+	}
+	mqtt_options.set_keep_alive(Duration::from_secs(profile.keep_alive_secs.max(5)));
+	// Snapshot chunks and line batches are well above rumqttc's 10 KiB default
+	mqtt_options.set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE);
+
+	if profile.use_tls {
+		let client_config = tls_client_config(profile)?;
+		mqtt_options.set_transport(Transport::tls_with_config(client_config.into()));
+	}
+
+	Ok(mqtt_options)
+}
+
+fn tls_client_config(profile: &MqttProfile) -> Result<rustls::ClientConfig, String> {
+	// Use the system's native certificate store, plus the profile CA bundle if any
+	let mut root_cert_store = rustls::RootCertStore::empty();
+	let native_certs = rustls_native_certs::load_native_certs()
+		.map_err(|e| format!("No se pudieron cargar los certificados del sistema: {}", e))?;
+	for cert in native_certs {
+		// Some platform stores contain certificates rustls cannot parse; skip them
+		let _ = root_cert_store.add(&rustls::Certificate(cert.0));
+	}
+
+	if let Some(ca_path) = &profile.ca_cert_path {
+		for cert in read_pem_certs(ca_path)? {
+			root_cert_store
+				.add(&cert)
+				.map_err(|e| format!("Certificado CA inválido en {}: {}", ca_path, e))?;
+		}
+	}
+
+	let builder = rustls::ClientConfig::builder()
+		.with_safe_defaults()
+		.with_root_certificates(root_cert_store);
+
+	match &profile.client_cert {
+		Some(client_cert) => {
+			let certs = read_pem_certs(&client_cert.cert_path)?;
+			let key = read_pem_private_key(&client_cert.key_path)?;
+			builder
+				.with_client_auth_cert(certs, key)
+				.map_err(|e| format!("Certificado de cliente inválido: {}", e))
+		}
+		None => Ok(builder.with_no_client_auth()),
+	}
+}
+
+fn read_pem_certs(path: &str) -> Result<Vec<rustls::Certificate>, String> {
+	let file = File::open(path).map_err(|e| format!("Error abriendo {}: {}", path, e))?;
+	let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+		.map_err(|e| format!("Error leyendo certificados de {}: {}", path, e))?;
+	if certs.is_empty() {
+		return Err(format!("No se encontraron certificados en {}", path));
+	}
+	Ok(certs.into_iter().map(rustls::Certificate).collect())
+}
+
+fn read_pem_private_key(path: &str) -> Result<rustls::PrivateKey, String> {
+	let file = File::open(path).map_err(|e| format!("Error abriendo {}: {}", path, e))?;
+	let mut reader = BufReader::new(file);
+	loop {
+		match rustls_pemfile::read_one(&mut reader)
+			.map_err(|e| format!("Error leyendo la clave privada de {}: {}", path, e))?
+		{
+			Some(rustls_pemfile::Item::PKCS8Key(key))
+			| Some(rustls_pemfile::Item::RSAKey(key))
+			| Some(rustls_pemfile::Item::ECKey(key)) => return Ok(rustls::PrivateKey(key)),
+			Some(_) => continue,
+			None => return Err(format!("No se encontró una clave privada en {}", path)),
+		}
+	}
+}
+
+/// Opens a short-lived connection with the given profile (or the saved one) and waits for
+/// the broker's ConnAck. `password` is as in `update_mqtt_profile`.
+#[command]
+pub async fn test_mqtt_connection(
+	app_handle: AppHandle,
+	profile: Option<MqttProfile>,
+	password: Option<String>,
+) -> Result<String, String> {
+	let profile = match profile {
+		Some(p) => profile::with_password(p, password)?,
+		None => profile::load_profile(&app_handle)?,
+	};
+	let mqtt_options = build_mqtt_options(&profile, "test")?;
+	let broker = format!("{}:{}", profile.host, profile.port);
+
+	tauri::async_runtime::spawn_blocking(move || {
+		let (mut client, mut connection) = Client::new(mqtt_options, 10);
+		let deadline = Instant::now() + Duration::from_secs(10);
+
+		let result = loop {
+			let remaining = deadline.saturating_duration_since(Instant::now());
+			if remaining.is_zero() {
+				break Err(format!("Tiempo de espera agotado conectando a {}", broker));
+			}
+			match connection.recv_timeout(remaining) {
+				Ok(Ok(Event::Incoming(Packet::ConnAck(_)))) => {
+					break Ok(format!("Conexión exitosa con {}", broker));
+				}
+				Ok(Ok(_)) => continue,
+				Ok(Err(e)) => break Err(format!("Error conectando a {}: {}", broker, e)),
+				Err(RecvTimeoutError::Timeout) => {
+					break Err(format!("Tiempo de espera agotado conectando a {}", broker));
+				}
+				Err(RecvTimeoutError::Disconnected) => {
+					break Err(format!("Conexión cerrada por {}", broker));
+				}
+			}
+		};
+
+		let _ = client.disconnect();
+		result
+	})
+	.await
+	.map_err(|e| e.to_string())?
+}
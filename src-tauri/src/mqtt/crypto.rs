@@ -0,0 +1,57 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+/// Value of the `encryption` field of encrypted payloads
+pub const ALGORITHM: &str = "chacha20poly1305";
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+pub type ProjectKey = [u8; KEY_LEN];
+
+pub fn generate_key() -> ProjectKey {
+	ChaCha20Poly1305::generate_key(&mut OsRng).into()
+}
+
+pub fn key_from_bytes(bytes: &[u8]) -> Result<ProjectKey, String> {
+	bytes
+		.try_into()
+		.map_err(|_| format!("La clave debe tener {} bytes", KEY_LEN))
+}
+
+/// Encrypts `plaintext` with a random nonce, returned in front of the ciphertext. `aad` binds
+/// the ciphertext to the message it travels in, so it cannot be replayed elsewhere.
+pub fn encrypt(key: &ProjectKey, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+	let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+	let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+	let ciphertext = cipher
+		.encrypt(
+			&nonce,
+			Payload {
+				msg: plaintext,
+				aad,
+			},
+		)
+		.expect("ChaCha20-Poly1305 encryption cannot fail for in-memory buffers");
+
+	let mut sealed = nonce.to_vec();
+	sealed.extend_from_slice(&ciphertext);
+	sealed
+}
+
+pub fn decrypt(key: &ProjectKey, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+	if sealed.len() < NONCE_LEN {
+		return Err("El contenido cifrado está incompleto".to_string());
+	}
+	let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+	ChaCha20Poly1305::new(Key::from_slice(key))
+		.decrypt(
+			Nonce::from_slice(nonce),
+			Payload {
+				msg: ciphertext,
+				aad,
+			},
+		)
+		.map_err(|_| {
+			"No se pudo descifrar el mensaje: clave incorrecta o contenido alterado".to_string()
+		})
+}
@@ -0,0 +1,574 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::mqtt::compression::{self, Compression};
+use crate::mqtt::crypto::{self, ProjectKey};
+
+/// Newest version this build reads; newer ones are rejected. Messages are written with the
+/// lowest version that has every field they use, so receivers not yet updated reject a
+/// message with fields they do not know instead of silently dropping those fields.
+pub const SCHEMA_VERSION: u32 = 4;
+// Version that added `encryption`
+const ENCRYPTION_VERSION: u32 = 2;
+// Version that added `compression` and `lines`
+const BATCH_VERSION: u32 = 3;
+// Version that added `epoch`, `newline` (also on batch lines) and `record`
+const LINE_VERSION: u32 = 4;
+
+// Encoding assumed for messages that carry no text (removals) and for older emitters, which
+// always decoded log files as Windows-1252
+pub const DEFAULT_ENCODING: &str = "windows-1252";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+	Created,
+	Modified,
+	Removed,
+}
+
+/// One log event as published on `project/<project>/logs/<path>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEnvelope {
+	pub version: u32,
+	pub message_id: String,
+	/// Stable id of the emitting installation
+	pub host_id: String,
+	pub project: String,
+	/// Path relative to the project root, '/'-separated
+	pub path: String,
+	pub kind: EventKind,
+	/// Per-file counter assigned by the emitter; 0 when unknown (upgraded payloads)
+	pub sequence: u64,
+	/// Numbering `sequence` belongs to; a new epoch means the emitter started counting again
+	/// from 1 (version 4). Absent from older emitters
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub epoch: Option<String>,
+	/// RFC 3339 time at which the emitter read the line
+	pub timestamp: String,
+	/// Encoding of the source file, which receivers write the content back in; `content`
+	/// itself is always UTF-8
+	pub encoding: String,
+	/// Byte offset just past the line in the source file
+	#[serde(default)]
+	pub offset: Option<u64>,
+	#[serde(default)]
+	pub content: String,
+	/// What the line ended with in the source file when it was not "\n"; receivers write it
+	/// back after `content` (version 4)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub newline: Option<String>,
+	/// Set when `content` is one whole record, continuation lines included, from an emitter
+	/// in whole-record mode; a batch sets it for all its lines (version 4)
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub record: bool,
+	/// Set when `content` is base64 of the nonce and ciphertext (version 2)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub encryption: Option<String>,
+	/// Set when `content` is base64 of the compressed text (version 3)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub compression: Option<Compression>,
+	/// Set when `content` is a JSON array of this many lines, numbered from `sequence`
+	/// (version 3)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub lines: Option<u32>,
+}
+
+/// One line of a batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchLine {
+	pub content: String,
+	/// Byte offset just past the line in the source file
+	pub offset: u64,
+	/// Like `LogEnvelope::newline`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub newline: Option<String>,
+}
+
+// Payload sent before envelopes existed: {event_type, path: "<project>/<path>", content}
+#[derive(Deserialize)]
+struct LegacyPayload {
+	event_type: EventKind,
+	path: String,
+	#[serde(default)]
+	content: String,
+	#[serde(default)]
+	offset: Option<u64>,
+}
+
+impl LogEnvelope {
+	pub fn new(
+		host_id: &str,
+		project: &str,
+		path: &str,
+		kind: EventKind,
+		sequence: u64,
+		offset: Option<u64>,
+		content: &str,
+	) -> Self {
+		LogEnvelope {
+			version: 1,
+			message_id: new_message_id(),
+			host_id: host_id.to_string(),
+			project: project.to_string(),
+			path: path.to_string(),
+			kind,
+			sequence,
+			epoch: None,
+			timestamp: chrono::Local::now().to_rfc3339(),
+			encoding: DEFAULT_ENCODING.to_string(),
+			offset,
+			content: content.to_string(),
+			newline: None,
+			record: false,
+			encryption: None,
+			compression: None,
+			lines: None,
+		}
+	}
+
+	/// Several consecutive lines of one file in a single message. `sequence` is the first
+	/// line's number; the following ones take the next numbers.
+	pub fn batch(
+		host_id: &str,
+		project: &str,
+		path: &str,
+		kind: EventKind,
+		sequence: u64,
+		lines: &[BatchLine],
+	) -> Self {
+		let content = serde_json::to_string(lines).unwrap();
+		let mut envelope = LogEnvelope::new(host_id, project, path, kind, sequence, None, &content);
+		envelope.offset = lines.last().map(|line| line.offset);
+		envelope.lines = Some(lines.len() as u32);
+		envelope.version = envelope.version.max(BATCH_VERSION);
+		// Hidden in `content`, which may end up compressed or encrypted
+		if lines.iter().any(|line| line.newline.is_some()) {
+			envelope.version = envelope.version.max(LINE_VERSION);
+		}
+		envelope
+	}
+
+	pub fn topic(&self) -> String {
+		format!("project/{}/logs/{}", self.project, self.path)
+	}
+
+	/// Whether the installation with `host_id` published this envelope.
+	pub fn is_from(&self, host_id: &str) -> bool {
+		self.host_id == host_id
+	}
+
+	/// "<project>/<path>", the key receivers track files by
+	pub fn mqtt_path(&self) -> String {
+		format!("{}/{}", self.project, self.path)
+	}
+
+	/// Validates an incoming payload, upgrading the one sent before envelopes existed.
+	pub fn from_value(value: Value) -> Result<Self, String> {
+		let envelope = match value.get("version") {
+			None => upgrade_legacy(value)?,
+			Some(version) => {
+				let version = version
+					.as_u64()
+					.ok_or_else(|| "La versión del mensaje no es un número".to_string())?;
+				if version > SCHEMA_VERSION as u64 {
+					return Err(format!(
+						"Versión de mensaje {} no soportada (la más reciente conocida es {}); actualice la aplicación receptora",
+						version, SCHEMA_VERSION
+					));
+				}
+				serde_json::from_value(value)
+					.map_err(|e| format!("Mensaje de versión {} inválido: {}", version, e))?
+			}
+		};
+		envelope.validate()?;
+		Ok(envelope)
+	}
+
+	pub fn validate(&self) -> Result<(), String> {
+		if self.version == 0 || self.version > SCHEMA_VERSION {
+			return Err(format!("Versión de mensaje {} no soportada", self.version));
+		}
+		if self.version < self.required_version() {
+			return Err(format!(
+				"El mensaje de versión {} usa campos de la versión {}",
+				self.version,
+				self.required_version()
+			));
+		}
+		if self.message_id.is_empty() {
+			return Err("El mensaje no tiene identificador".to_string());
+		}
+		if self.host_id.is_empty() {
+			return Err("El mensaje no identifica al emisor".to_string());
+		}
+		if self.project.is_empty() || self.project.contains('/') {
+			return Err(format!("Nombre de proyecto inválido: '{}'", self.project));
+		}
+		if self.path.is_empty() {
+			return Err("El mensaje no indica la ruta del archivo".to_string());
+		}
+		if self.encoding.is_empty() {
+			return Err("El mensaje no indica la codificación".to_string());
+		}
+		chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+			.map_err(|e| format!("Marca de tiempo inválida '{}': {}", self.timestamp, e))?;
+		if self.lines == Some(0) {
+			return Err("El lote de líneas está vacío".to_string());
+		}
+		check_newline(self.newline.as_deref())?;
+		match self.encryption.as_deref() {
+			None | Some(crypto::ALGORITHM) => Ok(()),
+			Some(other) => Err(format!("Algoritmo de cifrado no soportado: {}", other)),
+		}
+	}
+
+	/// Raises `version` to the lowest one that has every field set, for fields assigned
+	/// directly.
+	pub fn raise_version(&mut self) {
+		self.version = self.version.max(self.required_version());
+	}
+
+	// Lowest version that has every field set on the envelope
+	fn required_version(&self) -> u32 {
+		if self.epoch.is_some() || self.newline.is_some() || self.record {
+			LINE_VERSION
+		} else if self.compression.is_some() || self.lines.is_some() {
+			BATCH_VERSION
+		} else if self.encryption.is_some() {
+			ENCRYPTION_VERSION
+		} else {
+			1
+		}
+	}
+
+	// Ties the ciphertext to the stream position it was published at
+	fn aad(&self) -> String {
+		format!(
+			"{}|{}|{}|{}|{}",
+			self.host_id, self.project, self.path, self.sequence, self.message_id
+		)
+	}
+
+	pub fn encrypt(&mut self, key: &ProjectKey) {
+		let sealed = crypto::encrypt(key, self.content.as_bytes(), self.aad().as_bytes());
+		self.content = BASE64.encode(sealed);
+		self.encryption = Some(crypto::ALGORITHM.to_string());
+		self.version = self.version.max(ENCRYPTION_VERSION);
+	}
+
+	/// Compresses `content`; done before encryption, which leaves nothing to compress.
+	pub fn compress(&mut self, compression: Compression) -> Result<(), String> {
+		let packed = compression::compress(compression, self.content.as_bytes())?;
+		self.content = BASE64.encode(packed);
+		self.compression = Some(compression);
+		self.version = self.version.max(BATCH_VERSION);
+		Ok(())
+	}
+
+	pub fn decompress(&mut self) -> Result<(), String> {
+		let Some(compression) = self.compression else {
+			return Ok(());
+		};
+		let packed = BASE64
+			.decode(&self.content)
+			.map_err(|e| format!("Contenido comprimido inválido: {}", e))?;
+		let text = compression::decompress(compression, &packed)?;
+		self.content = String::from_utf8(text)
+			.map_err(|_| "El contenido descomprimido no es UTF-8".to_string())?;
+		self.compression = None;
+		Ok(())
+	}
+
+	/// Splits a batch into one envelope per line, in order; other envelopes are returned as
+	/// they are. Expects decrypted and decompressed content.
+	pub fn into_lines(self) -> Result<Vec<LogEnvelope>, String> {
+		let Some(count) = self.lines else {
+			return Ok(vec![self]);
+		};
+		let lines: Vec<BatchLine> = serde_json::from_str(&self.content)
+			.map_err(|e| format!("Lote de líneas inválido: {}", e))?;
+		for line in &lines {
+			check_newline(line.newline.as_deref())?;
+			if line.newline.is_some() && self.version < LINE_VERSION {
+				return Err(format!(
+					"El mensaje de versión {} usa campos de la versión {}",
+					self.version, LINE_VERSION
+				));
+			}
+		}
+		if lines.len() != count as usize {
+			return Err(format!(
+				"El lote anuncia {} líneas y contiene {}",
+				count,
+				lines.len()
+			));
+		}
+
+		Ok(lines
+			.into_iter()
+			.enumerate()
+			.map(|(index, line)| LogEnvelope {
+				message_id: format!("{}-{}", self.message_id, index),
+				sequence: self.sequence + index as u64,
+				offset: Some(line.offset),
+				content: line.content,
+				newline: line.newline,
+				lines: None,
+				..self.clone()
+			})
+			.collect())
+	}
+
+	pub fn decrypt(&mut self, key: &ProjectKey) -> Result<(), String> {
+		if self.encryption.is_none() {
+			return Ok(());
+		}
+		let sealed = BASE64
+			.decode(&self.content)
+			.map_err(|e| format!("Contenido cifrado inválido: {}", e))?;
+		let plaintext = crypto::decrypt(key, &sealed, self.aad().as_bytes())?;
+		self.content = String::from_utf8(plaintext)
+			.map_err(|_| "El contenido descifrado no es UTF-8".to_string())?;
+		self.encryption = None;
+		Ok(())
+	}
+}
+
+// Only the terminators the tailer splits lines on can be written back
+fn check_newline(newline: Option<&str>) -> Result<(), String> {
+	match newline {
+		None | Some("\n") | Some("\r\n") => Ok(()),
+		Some(other) => Err(format!("Fin de línea inválido: {:?}", other)),
+	}
+}
+
+fn upgrade_legacy(value: Value) -> Result<LogEnvelope, String> {
+	let legacy: LegacyPayload = serde_json::from_value(value)
+		.map_err(|e| format!("Mensaje sin versión inválido: {}", e))?;
+	let (project, path) = legacy.path.split_once('/').ok_or_else(|| {
+		format!(
+			"Ruta sin proyecto en mensaje sin versión: '{}'",
+			legacy.path
+		)
+	})?;
+
+	Ok(LogEnvelope::new(
+		"unknown",
+		project,
+		path,
+		legacy.event_type,
+		0,
+		legacy.offset,
+		&legacy.content,
+	))
+}
+
+pub fn new_message_id() -> String {
+	rand::thread_rng()
+		.sample_iter(&rand::distributions::Alphanumeric)
+		.take(16)
+		.map(char::from)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mqtt::crypto;
+
+	fn plain() -> LogEnvelope {
+		LogEnvelope::new(
+			"host",
+			"project",
+			"logs/app.log",
+			EventKind::Modified,
+			1,
+			Some(5),
+			"line",
+		)
+	}
+
+	fn batch() -> LogEnvelope {
+		let lines = [
+			BatchLine {
+				content: "one".to_string(),
+				offset: 4,
+				newline: None,
+			},
+			BatchLine {
+				content: "two".to_string(),
+				offset: 9,
+				newline: Some("\r\n".to_string()),
+			},
+		];
+		LogEnvelope::batch(
+			"host",
+			"project",
+			"logs/app.log",
+			EventKind::Modified,
+			1,
+			&lines,
+		)
+	}
+
+	fn round_trip(envelope: &LogEnvelope) -> Result<LogEnvelope, String> {
+		LogEnvelope::from_value(serde_json::to_value(envelope).unwrap())
+	}
+
+	#[test]
+	fn plain_lines_are_written_as_version_1() {
+		let envelope = plain();
+		assert_eq!(envelope.version, 1);
+		assert_eq!(round_trip(&envelope).unwrap().version, 1);
+	}
+
+	#[test]
+	fn encrypted_lines_are_written_as_version_2() {
+		let key = crypto::generate_key();
+		let mut envelope = plain();
+		envelope.encrypt(&key);
+		assert_eq!(envelope.version, 2);
+
+		let mut received = round_trip(&envelope).unwrap();
+		received.decrypt(&key).unwrap();
+		assert_eq!(received.content, "line");
+	}
+
+	#[test]
+	fn batches_and_compression_are_written_as_version_3() {
+		let line = BatchLine {
+			content: "one".to_string(),
+			offset: 4,
+			newline: None,
+		};
+		let envelope = LogEnvelope::batch(
+			"host",
+			"project",
+			"logs/app.log",
+			EventKind::Modified,
+			1,
+			&[line],
+		);
+		assert_eq!(envelope.version, 3);
+
+		let mut envelope = plain();
+		envelope.compress(Compression::Gzip).unwrap();
+		assert_eq!(envelope.version, 3);
+
+		let mut envelope = batch();
+		envelope.encrypt(&crypto::generate_key());
+		assert_eq!(envelope.version, 4);
+	}
+
+	#[test]
+	fn epochs_terminators_and_records_are_written_as_version_4() {
+		let mut envelope = plain();
+		envelope.epoch = Some("epoch".to_string());
+		envelope.raise_version();
+		assert_eq!(envelope.version, 4);
+
+		let mut envelope = plain();
+		envelope.newline = Some("\r\n".to_string());
+		envelope.raise_version();
+		assert_eq!(envelope.version, 4);
+
+		let mut envelope = plain();
+		envelope.record = true;
+		envelope.raise_version();
+		assert_eq!(envelope.version, 4);
+
+		// A terminator on one line of the batch is enough
+		assert_eq!(batch().version, 4);
+	}
+
+	#[test]
+	fn fields_newer_than_the_version_are_rejected() {
+		let mut envelope = plain();
+		envelope.encrypt(&crypto::generate_key());
+		envelope.version = 1;
+		assert!(round_trip(&envelope).is_err());
+
+		let mut envelope = batch();
+		envelope.version = 2;
+		assert!(round_trip(&envelope).is_err());
+
+		let mut envelope = plain();
+		envelope.newline = Some("\r\n".to_string());
+		envelope.version = 3;
+		assert!(round_trip(&envelope).is_err());
+
+		// Older receivers would write the batch's CRLF lines back with "\n"
+		let mut envelope = batch();
+		envelope.version = 3;
+		assert!(round_trip(&envelope).unwrap().into_lines().is_err());
+	}
+
+	#[test]
+	fn versions_past_the_newest_known_are_rejected() {
+		let mut envelope = plain();
+		envelope.version = SCHEMA_VERSION + 1;
+		assert!(round_trip(&envelope).is_err());
+	}
+
+	#[test]
+	fn batches_split_into_numbered_lines() {
+		let received = round_trip(&batch()).unwrap();
+		let lines = received.into_lines().unwrap();
+		let numbered: Vec<(u64, &str, Option<u64>, Option<&str>)> = lines
+			.iter()
+			.map(|line| {
+				(
+					line.sequence,
+					line.content.as_str(),
+					line.offset,
+					line.newline.as_deref(),
+				)
+			})
+			.collect();
+		assert_eq!(
+			numbered,
+			[(1, "one", Some(4), None), (2, "two", Some(9), Some("\r\n"))]
+		);
+	}
+
+	#[test]
+	fn only_known_line_terminators_are_accepted() {
+		let mut envelope = plain();
+		envelope.newline = Some("\r\n".to_string());
+		envelope.raise_version();
+		assert_eq!(
+			round_trip(&envelope).unwrap().newline.as_deref(),
+			Some("\r\n")
+		);
+
+		envelope.newline = Some("\n/../".to_string());
+		assert!(round_trip(&envelope).is_err());
+	}
+
+	#[test]
+	fn whole_records_are_marked_on_every_line_of_a_batch() {
+		assert!(!round_trip(&plain()).unwrap().record);
+
+		let mut envelope = batch();
+		envelope.record = true;
+		envelope.raise_version();
+		let lines = round_trip(&envelope).unwrap().into_lines().unwrap();
+		assert!(lines.iter().all(|line| line.record));
+	}
+
+	#[test]
+	fn envelopes_are_told_apart_by_their_host() {
+		let envelope = plain();
+		assert!(envelope.is_from("host"));
+		assert!(!envelope.is_from("other-host"));
+
+		// Payloads from before envelopes carry no host and are never taken as this one's
+		let legacy = serde_json::json!({ "event_type": "modified", "path": "project/app.log", "content": "x" });
+		let upgraded = LogEnvelope::from_value(legacy).unwrap();
+		assert!(!upgraded.is_from("host"));
+	}
+}
@@ -0,0 +1,9 @@
+pub mod backoff;
+pub mod compression;
+pub mod connection;
+pub mod crypto;
+pub mod envelope;
+pub mod profile;
+pub mod service;
+pub mod signing;
+pub mod status;
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Emitter, Manager};
+
+use crate::mqtt::backoff::ReconnectPolicy;
+use crate::process::keychain;
+use crate::process::mode::AppModeState;
+use crate::settings;
+
+const PROFILE_FILE: &str = "mqtt_profile.json";
+// Name of the broker password in the OS credential store
+const PASSWORD_NAME: &str = "mqtt-password";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientCertificate {
+	pub cert_path: String,
+	pub key_path: String,
+}
+
+// Broker profile shared by the Emitter and Receiver modes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttProfile {
+	pub host: String,
+	pub port: u16,
+	pub use_tls: bool,
+	pub username: Option<String>,
+	/// Kept in the OS credential store, never in the profile file nor sent to the webview
+	#[serde(skip)]
+	pub password: Option<String>,
+	pub client_cert: Option<ClientCertificate>,
+	/// Extra CA bundle (PEM) for brokers signed by a private authority
+	pub ca_cert_path: Option<String>,
+	pub keep_alive_secs: u64,
+	pub client_id_prefix: String,
+	pub reconnect: ReconnectPolicy,
+}
+
+impl Default for MqttProfile {
+	fn default() -> Self {
+		MqttProfile {
+			// No broker until the user configures one
+			host: String::new(),
+			port: 8883,
+			use_tls: true,
+			username: None,
+			password: None,
+			client_cert: None,
+			ca_cert_path: None,
+			keep_alive_secs: 30,
+			client_id_prefix: "tauri".to_string(),
+			reconnect: ReconnectPolicy::default(),
+		}
+	}
+}
+
+impl MqttProfile {
+	pub fn validate(&self) -> Result<(), String> {
+		if self.host.trim().is_empty() {
+			return Err("No hay un broker MQTT configurado".to_string());
+		}
+		if self.port == 0 {
+			return Err("El puerto del broker no es válido".to_string());
+		}
+		if self.client_id_prefix.trim().is_empty() {
+			return Err("El prefijo del client-id no puede estar vacío".to_string());
+		}
+		if self.password.is_some() && self.username.is_none() {
+			return Err("Se indicó una contraseña sin usuario".to_string());
+		}
+		if self.client_cert.is_some() && !self.use_tls {
+			return Err("El certificado de cliente requiere TLS".to_string());
+		}
+		self.reconnect.validate()
+	}
+}
+
+fn stored_password() -> Result<Option<String>, String> {
+	keychain::get(PASSWORD_NAME)?
+		.map(|secret| {
+			String::from_utf8(secret)
+				.map_err(|_| "La contraseña guardada del broker no es válida".to_string())
+		})
+		.transpose()
+}
+
+/// Sets the profile's password to `password` when the user typed one, else to the stored
+/// one; a profile without user has none.
+pub fn with_password(
+	mut profile: MqttProfile,
+	password: Option<String>,
+) -> Result<MqttProfile, String> {
+	profile.password = match (&profile.username, password) {
+		(None, _) => None,
+		(Some(_), Some(password)) => Some(password),
+		(Some(_), None) => stored_password()?,
+	};
+	Ok(profile)
+}
+
+pub fn load_profile(app_handle: &AppHandle) -> Result<MqttProfile, String> {
+	let profile = settings::load_json(app_handle, PROFILE_FILE)?;
+	with_password(profile, None)
+}
+
+/// The saved profile, without its password.
+#[command]
+pub fn get_mqtt_profile(app_handle: AppHandle) -> Result<MqttProfile, String> {
+	settings::load_json(&app_handle, PROFILE_FILE)
+}
+
+/// Saves the profile. `password` replaces the stored one; `None` keeps it, unless the
+/// profile has no user.
+#[command]
+pub fn update_mqtt_profile(
+	app_handle: AppHandle,
+	profile: MqttProfile,
+	password: Option<String>,
+) -> Result<(), String> {
+	let profile = with_password(profile, password)?;
+	profile.validate()?;
+	settings::save_json(&app_handle, PROFILE_FILE, &profile)?;
+	match &profile.password {
+		Some(password) => keychain::set(PASSWORD_NAME, password.as_bytes())?,
+		None => keychain::delete(PASSWORD_NAME)?,
+	}
+	println!(
+		"MQTT: Broker profile updated ({}:{})",
+		profile.host, profile.port
+	);
+
+	// Restart the running mode so it reconnects with the new profile
+	let current_mode = *app_handle.state::<AppModeState>().0.lock().unwrap();
+	let _ = app_handle.emit("app_mode_changed", current_mode);
+	Ok(())
+}
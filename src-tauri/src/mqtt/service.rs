@@ -0,0 +1,538 @@
+use rumqttc::{Client, ConnectionError, Event, Packet, Publish, QoS};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::mqtt::connection::build_mqtt_options;
+use crate::mqtt::profile::load_profile;
+use crate::mqtt::status::{ConnectionState, MqttStatus};
+
+// Counter-only status changes are emitted at most this often
+const STATUS_EMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+struct ActiveConnection {
+	generation: u64,
+	client: Client,
+	acks: AckTracker,
+	stopping: Arc<AtomicBool>,
+	// Wakes the connection thread while it is waiting to reconnect
+	wake_tx: std_mpsc::Sender<()>,
+}
+
+// Matches broker acknowledgements with confirmed publishes.
+//
+// rumqttc only reports packet ids, assigned when a request leaves the queue, so every queued
+// publish takes a slot in `queued` (in request order) until its `Outgoing::Publish` event
+// reveals the id. Publishes replayed after a reconnection keep their id.
+#[derive(Default)]
+struct AckTracker {
+	queued: VecDeque<Option<oneshot::Sender<()>>>,
+	awaiting: HashMap<u16, oneshot::Sender<()>>,
+}
+
+impl AckTracker {
+	fn on_outgoing_publish(&mut self, pkid: u16) {
+		if pkid != 0 && self.awaiting.contains_key(&pkid) {
+			return; // Replay of a publish we already know
+		}
+		if let Some(Some(waiter)) = self.queued.pop_front() {
+			if pkid == 0 {
+				let _ = waiter.send(()); // QoS 0 is never acknowledged
+			} else {
+				self.awaiting.insert(pkid, waiter);
+			}
+		}
+	}
+
+	fn on_puback(&mut self, pkid: u16) {
+		if let Some(waiter) = self.awaiting.remove(&pkid) {
+			let _ = waiter.send(());
+		}
+	}
+}
+
+// What the blocking connection thread reports to the async processor
+enum ConnectionEvent {
+	Notification(Result<Event, ConnectionError>),
+	RetryScheduled { attempt: u32, delay: Duration },
+	GaveUp { attempts: u32 },
+}
+
+// A mode (emitter, receiver, ...) that receives the publishes matching its filters
+struct Subscriber {
+	filters: HashMap<String, QoS>,
+	tx: Option<mpsc::UnboundedSender<Publish>>,
+}
+
+/// Single MQTT connection shared by every mode running in the app.
+///
+/// Each mode acquires the service under its own name; the connection is opened by the first
+/// one and closed when the last one releases it. Subscriptions are kept in a registry so they
+/// can be replayed after every reconnection.
+#[derive(Default)]
+pub struct MqttService {
+	connection: Mutex<Option<ActiveConnection>>,
+	subscribers: Mutex<HashMap<String, Subscriber>>,
+	next_generation: AtomicU64,
+	status: Mutex<MqttStatus>,
+	last_status_emit: Mutex<Option<Instant>>,
+	// A throttled change is waiting to be emitted
+	trailing_emit: AtomicBool,
+}
+
+impl MqttService {
+	/// Registers `owner` as a user of the connection, connecting if needed.
+	pub fn acquire(&self, app_handle: &AppHandle, owner: &str) -> Result<(), String> {
+		self.subscribers
+			.lock()
+			.unwrap()
+			.entry(owner.to_string())
+			.or_insert_with(|| Subscriber {
+				filters: HashMap::new(),
+				tx: None,
+			});
+
+		let mut connection = self.connection.lock().unwrap();
+		if connection.is_none() {
+			match self.connect(app_handle) {
+				Ok(active) => *connection = Some(active),
+				Err(e) => {
+					// The owner gives up on the connection; do not leave it registered
+					self.subscribers.lock().unwrap().remove(owner);
+					self.update_status(app_handle, |status| {
+						status.state = ConnectionState::Disconnected;
+						status.last_error = Some(e.clone());
+					});
+					return Err(e);
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Drops `owner` and its subscriptions; disconnects when nobody else uses the connection.
+	pub fn release(&self, app_handle: &AppHandle, owner: &str) {
+		let (removed, remaining) = {
+			let mut subscribers = self.subscribers.lock().unwrap();
+			let removed = subscribers.remove(owner);
+			(removed, subscribers.len())
+		};
+
+		let mut connection = self.connection.lock().unwrap();
+		if remaining == 0 {
+			if let Some(mut active) = connection.take() {
+				println!("MQTT Service: No users left, disconnecting.");
+				active.stopping.store(true, Ordering::SeqCst);
+				let _ = active.wake_tx.send(());
+				let _ = active.client.try_disconnect();
+				self.update_status(app_handle, |status| {
+					status.state = ConnectionState::Disconnected;
+				});
+			}
+		} else if let (Some(removed), Some(active)) = (removed, connection.as_mut()) {
+			for filter in removed.filters.keys() {
+				if !self.is_filter_in_use(filter) {
+					let _ = active.client.try_unsubscribe(filter.clone());
+				}
+			}
+		}
+	}
+
+	/// Returns the channel where publishes matching `owner`'s filters are delivered.
+	pub fn incoming(&self, owner: &str) -> mpsc::UnboundedReceiver<Publish> {
+		let (tx, rx) = mpsc::unbounded_channel();
+		let mut subscribers = self.subscribers.lock().unwrap();
+		let subscriber = subscribers
+			.entry(owner.to_string())
+			.or_insert_with(|| Subscriber {
+				filters: HashMap::new(),
+				tx: None,
+			});
+		subscriber.tx = Some(tx);
+		rx
+	}
+
+	pub fn subscribe(&self, owner: &str, filter: &str, qos: QoS) -> Result<(), String> {
+		if !rumqttc::valid_filter(filter) {
+			return Err(format!("Filtro de tópico inválido: {}", filter));
+		}
+
+		self.subscribers
+			.lock()
+			.unwrap()
+			.get_mut(owner)
+			.ok_or_else(|| format!("'{}' no ha adquirido la conexión MQTT", owner))?
+			.filters
+			.insert(filter.to_string(), qos);
+
+		if let Some(active) = self.connection.lock().unwrap().as_mut() {
+			active
+				.client
+				.try_subscribe(filter, qos)
+				.map_err(|e| format!("Error suscribiendo a '{}': {}", filter, e))?;
+		}
+		println!("MQTT Service: '{}' subscribed to '{}'", owner, filter);
+		Ok(())
+	}
+
+	/// Drops one of `owner`'s filters, unsubscribing unless another owner still uses it.
+	pub fn unsubscribe(&self, owner: &str, filter: &str) -> Result<(), String> {
+		let removed = self
+			.subscribers
+			.lock()
+			.unwrap()
+			.get_mut(owner)
+			.ok_or_else(|| format!("'{}' no ha adquirido la conexión MQTT", owner))?
+			.filters
+			.remove(filter)
+			.is_some();
+		if !removed || self.is_filter_in_use(filter) {
+			return Ok(());
+		}
+
+		if let Some(active) = self.connection.lock().unwrap().as_mut() {
+			active
+				.client
+				.try_unsubscribe(filter)
+				.map_err(|e| format!("Error cancelando la suscripción a '{}': {}", filter, e))?;
+		}
+		println!("MQTT Service: '{}' unsubscribed from '{}'", owner, filter);
+		Ok(())
+	}
+
+	/// Queues a publish without waiting for the broker.
+	pub fn publish(&self, topic: &str, payload: Vec<u8>, qos: QoS) -> Result<(), String> {
+		self.queue_publish(topic, payload, qos, None)
+	}
+
+	/// Queues a publish without blocking the caller. The returned receiver resolves once the
+	/// broker acknowledges the message, and is dropped without a value if the connection
+	/// goes away first.
+	pub fn publish_confirmed(
+		&self,
+		topic: &str,
+		payload: Vec<u8>,
+		qos: QoS,
+	) -> Result<oneshot::Receiver<()>, String> {
+		let (tx, rx) = oneshot::channel();
+		self.queue_publish(topic, payload, qos, Some(tx))?;
+		Ok(rx)
+	}
+
+	pub fn is_connected(&self) -> bool {
+		self.status.lock().unwrap().state == ConnectionState::Connected
+	}
+
+	fn queue_publish(
+		&self,
+		topic: &str,
+		payload: Vec<u8>,
+		qos: QoS,
+		waiter: Option<oneshot::Sender<()>>,
+	) -> Result<(), String> {
+		match self.connection.lock().unwrap().as_mut() {
+			Some(active) => {
+				active
+					.client
+					.try_publish(topic, qos, false, payload)
+					.map_err(|e| format!("Error publicando en '{}': {}", topic, e))?;
+				active.acks.queued.push_back(waiter);
+				Ok(())
+			}
+			None => Err("No hay una conexión MQTT activa".to_string()),
+		}
+	}
+
+	fn track_acks(&self, generation: u64, track: impl FnOnce(&mut AckTracker)) {
+		if let Some(active) = self.connection.lock().unwrap().as_mut() {
+			if active.generation == generation {
+				track(&mut active.acks);
+			}
+		}
+	}
+
+	pub fn status(&self) -> MqttStatus {
+		self.status.lock().unwrap().clone()
+	}
+
+	// Applies `update` and notifies the UI and the tray tooltip. State and reconnection
+	// attempt changes are always emitted; other changes at most once per
+	// `STATUS_EMIT_INTERVAL`, the last one being emitted when the interval ends
+	fn update_status(&self, app_handle: &AppHandle, update: impl FnOnce(&mut MqttStatus)) {
+		let snapshot = {
+			let mut status = self.status.lock().unwrap();
+			let previous = (status.state, status.reconnect_attempt);
+			update(&mut status);
+
+			let mut last_emit = self.last_status_emit.lock().unwrap();
+			let since_emit = last_emit.map(|t| t.elapsed());
+			let due = since_emit.is_none_or(|elapsed| elapsed >= STATUS_EMIT_INTERVAL);
+			if (status.state, status.reconnect_attempt) == previous && !due {
+				if !self.trailing_emit.swap(true, Ordering::SeqCst) {
+					let wait = STATUS_EMIT_INTERVAL - since_emit.unwrap_or_default();
+					let app_handle = app_handle.clone();
+					tauri::async_runtime::spawn(async move {
+						tokio::time::sleep(wait).await;
+						app_handle.state::<MqttService>().emit_trailing_status(&app_handle);
+					});
+				}
+				return;
+			}
+			*last_emit = Some(Instant::now());
+			status.clone()
+		};
+		self.emit_status(app_handle, snapshot);
+	}
+
+	// Emits the changes held back by the throttle, unless a later emit already carried them
+	fn emit_trailing_status(&self, app_handle: &AppHandle) {
+		if !self.trailing_emit.swap(false, Ordering::SeqCst) {
+			return;
+		}
+		let snapshot = {
+			let status = self.status.lock().unwrap();
+			*self.last_status_emit.lock().unwrap() = Some(Instant::now());
+			status.clone()
+		};
+		self.emit_status(app_handle, snapshot);
+	}
+
+	fn emit_status(&self, app_handle: &AppHandle, snapshot: MqttStatus) {
+		// Whatever was held back goes out with this snapshot
+		self.trailing_emit.store(false, Ordering::SeqCst);
+		if let Err(e) = app_handle.emit("mqtt-status-changed", &snapshot) {
+			eprintln!("Error emitiendo mqtt-status-changed: {}", e);
+		}
+		// Posted to the main thread so callers holding the connection lock never wait on it
+		let tray_handle = app_handle.clone();
+		let summary = snapshot.summary();
+		let _ = app_handle.run_on_main_thread(move || {
+			if let Some(tray) = tray_handle.tray_by_id("main-tray") {
+				let _ = tray.set_tooltip(Some(summary));
+			}
+		});
+	}
+
+	fn is_filter_in_use(&self, filter: &str) -> bool {
+		self.subscribers
+			.lock()
+			.unwrap()
+			.values()
+			.any(|s| s.filters.contains_key(filter))
+	}
+
+	// Forgets a connection whose thread has stopped, so the next acquire reconnects
+	fn drop_connection(&self, generation: u64) {
+		let mut connection = self.connection.lock().unwrap();
+		if connection
+			.as_ref()
+			.is_some_and(|active| active.generation == generation)
+		{
+			*connection = None;
+		}
+	}
+
+	fn is_current(&self, generation: u64) -> bool {
+		self.connection
+			.lock()
+			.unwrap()
+			.as_ref()
+			.is_some_and(|active| active.generation == generation)
+	}
+
+	// Replays every registered subscription, needed after each (clean session) ConnAck
+	fn resubscribe_all(&self) {
+		let filters: HashMap<String, QoS> = self
+			.subscribers
+			.lock()
+			.unwrap()
+			.values()
+			.flat_map(|s| s.filters.iter().map(|(f, q)| (f.clone(), *q)))
+			.collect();
+
+		if let Some(active) = self.connection.lock().unwrap().as_mut() {
+			for (filter, qos) in filters {
+				if let Err(e) = active.client.try_subscribe(filter.clone(), qos) {
+					eprintln!("MQTT Service: Failed to subscribe to '{}': {}", filter, e);
+				}
+			}
+		}
+	}
+
+	fn dispatch(&self, publish: Publish) {
+		let subscribers = self.subscribers.lock().unwrap();
+		for subscriber in subscribers.values() {
+			let Some(tx) = &subscriber.tx else {
+				continue;
+			};
+			if subscriber
+				.filters
+				.keys()
+				.any(|f| rumqttc::matches(&publish.topic, f))
+			{
+				let _ = tx.send(publish.clone());
+			}
+		}
+	}
+
+	fn connect(&self, app_handle: &AppHandle) -> Result<ActiveConnection, String> {
+		let profile = load_profile(app_handle)?;
+		let mqtt_options = build_mqtt_options(&profile, "app")?;
+		let (client, mut connection) = Client::new(mqtt_options, 10);
+
+		let broker = format!("{}:{}", profile.host, profile.port);
+		self.update_status(app_handle, |status| {
+			status.state = ConnectionState::Connecting;
+			status.broker = Some(broker);
+			status.last_error = None;
+		});
+
+		let generation = self.next_generation.fetch_add(1, Ordering::SeqCst);
+		let stopping = Arc::new(AtomicBool::new(false));
+		let (wake_tx, wake_rx) = std_mpsc::channel::<()>();
+
+		// Channel to send MQTT events from the blocking thread to the async task
+		let (event_tx, mut event_rx) = mpsc::channel(100);
+
+		let policy = profile.reconnect.clone();
+		let thread_stopping = stopping.clone();
+		tauri::async_runtime::spawn_blocking(move || {
+			println!("MQTT Service Connection Handler (Blocking Thread): Started.");
+			let mut attempt: u32 = 0;
+			for notification in connection.iter() {
+				if thread_stopping.load(Ordering::SeqCst) {
+					break;
+				}
+				let failed = notification.is_err();
+				if let Ok(Event::Incoming(Packet::ConnAck(_))) = &notification {
+					attempt = 0;
+				}
+				if event_tx
+					.blocking_send(ConnectionEvent::Notification(notification))
+					.is_err()
+				{
+					break;
+				}
+				if !failed {
+					continue;
+				}
+
+				attempt += 1;
+				if policy.is_exhausted(attempt) {
+					let _ = event_tx.blocking_send(ConnectionEvent::GaveUp {
+						attempts: attempt - 1,
+					});
+					break;
+				}
+
+				// Iterating again reconnects; wait first unless we are told to stop
+				let delay = policy.delay_for(attempt);
+				let _ = event_tx.blocking_send(ConnectionEvent::RetryScheduled { attempt, delay });
+				match wake_rx.recv_timeout(delay) {
+					Err(std_mpsc::RecvTimeoutError::Timeout) => {}
+					_ => break,
+				}
+				if thread_stopping.load(Ordering::SeqCst) {
+					break;
+				}
+			}
+			println!("MQTT Service Connection Handler (Blocking Thread): Stopped.");
+		});
+
+		// Async task to process MQTT events received from the blocking thread
+		let app_handle = app_handle.clone();
+		tauri::async_runtime::spawn(async move {
+			while let Some(event) = event_rx.recv().await {
+				let service = app_handle.state::<MqttService>();
+				if !service.is_current(generation) {
+					continue;
+				}
+				let notification = match event {
+					ConnectionEvent::Notification(notification) => notification,
+					ConnectionEvent::RetryScheduled { attempt, delay } => {
+						println!(
+							"MQTT Service: Reconnection attempt {} in {:?}...",
+							attempt, delay
+						);
+						let next_retry = chrono::Local::now()
+							+ chrono::Duration::milliseconds(delay.as_millis() as i64);
+						service.update_status(&app_handle, |status| {
+							status.state = ConnectionState::Reconnecting;
+							status.reconnect_attempt = attempt;
+							status.next_retry_at = Some(next_retry.to_rfc3339());
+						});
+						continue;
+					}
+					ConnectionEvent::GaveUp { attempts } => {
+						eprintln!(
+							"MQTT Service: Giving up after {} reconnection attempts.",
+							attempts
+						);
+						service.drop_connection(generation);
+						// Every owner loses the connection; the UI offers to restart the mode
+						service.subscribers.lock().unwrap().clear();
+						service.update_status(&app_handle, |status| {
+							status.state = ConnectionState::Disconnected;
+							status.next_retry_at = None;
+							status.last_error = Some(format!(
+								"Se agotaron los {} intentos de reconexión",
+								attempts
+							));
+						});
+						let _ = app_handle.emit("mqtt-gave-up", attempts);
+						break;
+					}
+				};
+
+				if let Ok(Event::Incoming(_)) = &notification {
+					let now = chrono::Local::now().to_rfc3339();
+					service.update_status(&app_handle, |status| status.last_activity = Some(now));
+				}
+				match notification {
+					Ok(Event::Incoming(Packet::ConnAck(_))) => {
+						println!("MQTT Service: Connected to broker!");
+						service.update_status(&app_handle, |status| {
+							status.state = ConnectionState::Connected;
+							status.last_error = None;
+							status.reconnect_attempt = 0;
+							status.next_retry_at = None;
+						});
+						service.resubscribe_all();
+					}
+					Ok(Event::Incoming(Packet::Publish(publish))) => {
+						service.update_status(&app_handle, |status| status.messages_received += 1);
+						service.dispatch(publish);
+					}
+					Ok(Event::Outgoing(rumqttc::Outgoing::Publish(pkid))) => {
+						service.track_acks(generation, |acks| acks.on_outgoing_publish(pkid));
+						service.update_status(&app_handle, |status| status.messages_sent += 1);
+					}
+					Ok(Event::Incoming(Packet::PubAck(puback))) => {
+						service.track_acks(generation, |acks| acks.on_puback(puback.pkid));
+					}
+					Ok(Event::Incoming(Packet::PingResp)) => {}
+					Ok(Event::Outgoing(rumqttc::Outgoing::PingReq)) => {}
+					Ok(_) => {}
+					Err(e) => {
+						eprintln!("MQTT Service: Connection error: {:?}", e);
+						service.update_status(&app_handle, |status| {
+							status.state = ConnectionState::Reconnecting;
+							status.last_error = Some(e.to_string());
+						});
+					}
+				}
+			}
+		});
+
+		Ok(ActiveConnection {
+			generation,
+			client,
+			acks: AckTracker::default(),
+			stopping,
+			wake_tx,
+		})
+	}
+}
@@ -0,0 +1,79 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use serde_json::Value;
+
+pub fn generate_key() -> SigningKey {
+	SigningKey::generate(&mut OsRng)
+}
+
+pub fn key_from_bytes(bytes: &[u8]) -> Result<SigningKey, String> {
+	let secret: [u8; 32] = bytes
+		.try_into()
+		.map_err(|_| "La clave de firma debe tener 32 bytes".to_string())?;
+	Ok(SigningKey::from_bytes(&secret))
+}
+
+/// Base64 of the public half, the form emitters are identified by in trust lists.
+pub fn public_key(key: &SigningKey) -> String {
+	BASE64.encode(key.verifying_key().to_bytes())
+}
+
+pub fn parse_public_key(public_key: &str) -> Result<VerifyingKey, String> {
+	let bytes = BASE64
+		.decode(public_key.trim())
+		.map_err(|_| "La clave pública no es base64 válido".to_string())?;
+	let bytes: [u8; 32] = bytes
+		.as_slice()
+		.try_into()
+		.map_err(|_| "La clave pública debe tener 32 bytes".to_string())?;
+	VerifyingKey::from_bytes(&bytes).map_err(|_| "La clave pública no es válida".to_string())
+}
+
+pub fn sign(key: &SigningKey, message: &[u8]) -> String {
+	BASE64.encode(key.sign(message).to_bytes())
+}
+
+pub fn verify(public_key: &str, message: &[u8], signature: &str) -> Result<(), String> {
+	let signature = BASE64
+		.decode(signature)
+		.ok()
+		.and_then(|bytes| Signature::from_slice(&bytes).ok())
+		.ok_or_else(|| "La firma no tiene un formato válido".to_string())?;
+	parse_public_key(public_key)?
+		.verify(message, &signature)
+		.map_err(|_| "La firma no corresponde al contenido".to_string())
+}
+
+/// Signs a JSON object in place: `signer` is set, the object is serialized and signed, and
+/// `signature` is appended as its last key.
+pub fn sign_value(key: &SigningKey, value: &mut Value) {
+	let object = value
+		.as_object_mut()
+		.expect("signed payloads are JSON objects");
+	object.remove("signature");
+	object.insert("signer".to_string(), public_key(key).into());
+	let signature = sign(key, &serde_json::to_vec(object).unwrap());
+	object.insert("signature".to_string(), signature.into());
+}
+
+/// Checks a payload signed with `sign_value` and returns its signer's public key.
+pub fn verify_value(value: &Value) -> Result<String, String> {
+	let mut unsigned = value.clone();
+	let object = unsigned
+		.as_object_mut()
+		.ok_or_else(|| "El mensaje no es un objeto JSON".to_string())?;
+	// Removing the last key keeps the order of the others
+	let signature = match object.remove("signature") {
+		Some(Value::String(signature)) => signature,
+		_ => return Err("El mensaje no está firmado".to_string()),
+	};
+	let signer = object
+		.get("signer")
+		.and_then(Value::as_str)
+		.ok_or_else(|| "El mensaje no indica quién lo firmó".to_string())?
+		.to_string();
+	verify(&signer, &serde_json::to_vec(object).unwrap(), &signature)?;
+	Ok(signer)
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use tauri::{command, State};
+
+use crate::mqtt::service::MqttService;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+	Disconnected,
+	Connecting,
+	Connected,
+	Reconnecting,
+}
+
+// Snapshot of the shared MQTT connection, emitted as `mqtt-status-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct MqttStatus {
+	pub state: ConnectionState,
+	pub broker: Option<String>,
+	pub last_error: Option<String>,
+	pub messages_sent: u64,
+	pub messages_received: u64,
+	/// RFC 3339 time of the last packet exchanged with the broker
+	pub last_activity: Option<String>,
+	/// Consecutive failed connection attempts since the last ConnAck
+	pub reconnect_attempt: u32,
+	pub next_retry_at: Option<String>,
+}
+
+impl Default for MqttStatus {
+	fn default() -> Self {
+		MqttStatus {
+			state: ConnectionState::Disconnected,
+			broker: None,
+			last_error: None,
+			messages_sent: 0,
+			messages_received: 0,
+			last_activity: None,
+			reconnect_attempt: 0,
+			next_retry_at: None,
+		}
+	}
+}
+
+impl MqttStatus {
+	// Short text for the tray tooltip
+	pub fn summary(&self) -> String {
+		let state = match self.state {
+			ConnectionState::Disconnected => "Desconectado",
+			ConnectionState::Connecting => "Conectando",
+			ConnectionState::Connected => "Conectado",
+			ConnectionState::Reconnecting => "Reconectando",
+		};
+		match &self.broker {
+			Some(broker) => format!(
+				"MQTT: {} ({}) - enviados {}, recibidos {}",
+				state, broker, self.messages_sent, self.messages_received
+			),
+			None => format!("MQTT: {}", state),
+		}
+	}
+}
+
+#[command]
+pub fn get_mqtt_status(service: State<'_, MqttService>) -> MqttStatus {
+	service.status()
+}
@@ -0,0 +1,51 @@
+use tauri::{command, AppHandle, Emitter, Manager};
+
+use crate::db::audit::{self, AuditEntry};
+use crate::db::store::Database;
+
+const DEFAULT_LIMIT: u32 = 200;
+
+/// Records a refused message in the audit log and notifies the UI (`message-rejected`).
+/// `subject` is the topic or path it referred to; `signer` lets the user trust an unknown
+/// emitter.
+pub fn reject(
+	app_handle: &AppHandle,
+	project_name: &str,
+	subject: &str,
+	signer: Option<&str>,
+	reason: &str,
+) {
+	eprintln!(
+		"MQTT Receiver: Rejected message on '{}': {}",
+		subject, reason
+	);
+
+	let database = app_handle.state::<Database>();
+	if let Err(e) = audit::record(
+		&database.0.lock().unwrap(),
+		project_name,
+		subject,
+		signer,
+		reason,
+	) {
+		eprintln!("Audit: Failed to record rejection: {}", e);
+	}
+
+	let _ = app_handle.emit(
+		"message-rejected",
+		serde_json::json!({
+			"projectName": project_name,
+			"topic": subject,
+			"signer": signer,
+			"reason": reason,
+		}),
+	);
+}
+
+/// Most recent rejections first.
+#[command]
+pub fn get_audit_log(app_handle: AppHandle, limit: Option<u32>) -> Result<Vec<AuditEntry>, String> {
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	audit::recent(&connection, limit.unwrap_or(DEFAULT_LIMIT)).map_err(|e| e.to_string())
+}
@@ -1,16 +1,36 @@
-use rand::Rng;
-use rumqttc::{Client, Event, MqttOptions, Packet, QoS, Transport};
-use rustls;
-use rustls_native_certs;
+use rumqttc::Publish;
 use serde_json::Value;
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use tauri::{AppHandle, Emitter, EventId, Listener, Manager};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::mpsc;
-use tokio::time::Duration;
+
+use crate::mqtt::envelope::{EventKind, LogEnvelope};
+use crate::mqtt::service::MqttService;
+use crate::mqtt::signing;
+use crate::process::audit;
+use crate::process::emitter_settings::host_id;
+use crate::process::encoding;
+use crate::process::project_keys::key_for_incoming;
+use crate::process::project_paths::{create_project, project_root, AutoCreated, ProjectPaths};
+use crate::process::project_settings::{delete_policy, DeletePolicy};
+use crate::process::safe_path::{overlaps, resolve_in_project};
+use crate::process::sequencer::{Delivery, Sequencer};
+use crate::process::snapshot::{self, LineAction, SnapshotMessage, SnapshotSync};
+use crate::process::subscriptions::{self, ReceiverSubscriptions};
+use crate::process::trust::check_trusted;
+use crate::process::watch::WatchedRoot;
+use crate::settings;
+
+pub const RECEIVER_OWNER: &str = "receiver";
+// Folder under the config dir where the Archive delete policy moves removed files
+const ARCHIVE_DIR: &str = "archive";
+// How often pending snapshots and sequence gaps are checked for timeouts
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_secs(5);
 
 // State to hold the sender for stopping the MQTT receiver
 pub struct MqttReceiverStopper(pub Mutex<Option<mpsc::Sender<()>>>);
@@ -21,274 +41,532 @@ impl Default for MqttReceiverStopper {
 	}
 }
 
-pub struct MqttReceiverListener(pub Mutex<Option<EventId>>);
-
-impl Default for MqttReceiverListener {
-	fn default() -> Self {
-		MqttReceiverListener(Mutex::new(None))
-	}
+// What the receiver applies once the local project folder is known
+enum Apply {
+	Log(LogEnvelope),
+	// A line released by SnapshotSync; it already went through the sequencer
+	Held(LogEnvelope),
+	// A verified snapshot kept in SnapshotSync under `<project>/<path>`
+	Snapshot { project: String, path: String },
 }
 
-#[derive(serde::Deserialize, Clone)]
-struct PathResponsePayload {
-	#[serde(rename = "projectPath")]
-	project_path: Option<String>,
-	#[serde(rename = "originalPayload")]
-	original_payload: Value,
+impl Apply {
+	fn project(&self) -> &str {
+		match self {
+			Apply::Log(envelope) | Apply::Held(envelope) => &envelope.project,
+			Apply::Snapshot { project, .. } => project,
+		}
+	}
 }
 
 pub async fn init(app_handle: AppHandle, mut rx: mpsc::Receiver<()>) {
 	println!("MQTT Receiver: Initializing...");
 
-	let response_handle = app_handle.clone();
-	let listener_id = response_handle.clone().listen("project-path-response", move |event| {
-		println!("MQTT Receiver: Received project-path-response from frontend.");
-		if let Ok(payload) = serde_json::from_str::<PathResponsePayload>(event.payload()) {
-			if let Some(path_str) = payload.project_path {
-				let root = PathBuf::from(path_str);
-				let original_payload = payload.original_payload;
-
-				if let (Some(event_type), Some(mqtt_path_str), Some(content)) = (
-					original_payload["event_type"].as_str(),
-					original_payload["path"].as_str(),
-					original_payload["content"].as_str(),
-				) {
-					let project_name = mqtt_path_str.split('/').next().unwrap_or("");
-					let path_from_payload =
-						PathBuf::from(mqtt_path_str.replace("/", std::path::MAIN_SEPARATOR_STR));
-
-					let path_to_join = match path_from_payload.strip_prefix(project_name) {
-						Ok(p) => p,
-						Err(_) => &path_from_payload,
-					};
-
-					let target_path = root.join(path_to_join);
-					println!(
-						"MQTT Receiver: Final target path: {}",
-						target_path.display()
-					);
+	app_handle.state::<ProjectPaths>().clear();
+	app_handle.state::<SnapshotSync>().clear();
+	app_handle.state::<Sequencer>().clear();
 
-					match event_type {
-						"created" | "modified" => {
-							if let Some(parent) = target_path.parent() {
-								if let Err(e) = fs::create_dir_all(parent) {
-									eprintln!(
-										"MQTT Receiver: Failed to create parent directories for {}: {}",
-										target_path.display(),
-										e
-									);
-									return;
-								}
-							}
-							let mut file = match OpenOptions::new()
-								.create(true)
-								.append(true)
-								.open(&target_path)
-							{
-								Ok(file) => file,
-								Err(e) => {
-									eprintln!(
-										"MQTT Receiver: Failed to open or create file {}: {}",
-										target_path.display(),
-										e
-									);
-									return;
-								}
-							};
-
-							if let Err(e) = writeln!(file, "{}", content) {
-								eprintln!(
-									"MQTT Receiver: Failed to write to file {}: {}",
-									target_path.display(),
-									e
-								);
-							} else {
-								println!(
-									"MQTT Receiver: Successfully appended to file {}",
-									target_path.display()
-								);
-								let _ = response_handle
-									.emit("file_updated", target_path.to_string_lossy().to_string());
-							}
-						}
-						"removed" => {
-							if let Err(e) = fs::remove_file(&target_path) {
-								eprintln!(
-									"MQTT Receiver: Failed to remove file {}: {}",
-									target_path.display(),
-									e
-								);
-							} else {
-								println!(
-									"MQTT Receiver: Successfully removed file {}",
-									target_path.display()
-								);
-								let _ = response_handle
-									.emit("file_updated", target_path.to_string_lossy().to_string());
-							}
-						}
-						_ => {
-							eprintln!("MQTT Receiver: Unknown event type: {}", event_type);
-						}
-					}
-				}
-			} else {
-				if let Some(project_name) = payload.original_payload["projectName"].as_str() {
-					eprintln!(
-						"MQTT Receiver: Project '{}' not found in database. Emitting notification.",
-						project_name
-					);
-					let _ = response_handle.emit("project-not-found", project_name.to_string());
-				} else {
-					eprintln!("MQTT Receiver: Project name not found in original payload for unknown project path.");
+	let service = app_handle.state::<MqttService>();
+	let mut incoming = service.incoming(RECEIVER_OWNER);
+	if let Err(e) = service.acquire(&app_handle, RECEIVER_OWNER) {
+		eprintln!("MQTT Receiver: Failed to start MQTT connection: {}", e);
+		service.release(&app_handle, RECEIVER_OWNER);
+		return;
+	}
+	subscriptions::activate(&app_handle);
+
+	let mut housekeeping = tokio::time::interval(HOUSEKEEPING_INTERVAL);
+	loop {
+		tokio::select! {
+			Some(publish) = incoming.recv() => {
+				handle_publish(&app_handle, publish);
+			},
+			_ = housekeeping.tick() => {
+				let released = app_handle.state::<SnapshotSync>().expire();
+				request_held_lines(&app_handle, released);
+				for (root, delivery) in app_handle.state::<Sequencer>().expire() {
+					apply_delivery(&app_handle, &root, delivery);
 				}
+			},
+			_ = rx.recv() => {
+				println!("MQTT Receiver: Stop signal received. Shutting down.");
+				return;
 			}
-		} else {
-			eprintln!(
-				"MQTT Receiver: Failed to parse project-path-response payload: {:?}",
-				event.payload()
-			);
 		}
+	}
+}
+
+fn handle_publish(app_handle: &AppHandle, publish: Publish) {
+	let topic = publish.topic.clone();
+
+	let segments: Vec<&str> = topic.split('/').collect();
+	let is_snapshot = segments.get(2) == Some(&"snapshot");
+	if is_snapshot && segments.get(3) != Some(&"data") {
+		// Snapshot requests are answered by emitters; receivers only take the data
+		return;
+	}
+	let project_name = match segments.get(1) {
+		Some(name) if is_snapshot || segments.get(2) == Some(&"logs") => *name,
+		_ => {
+			println!("MQTT Receiver: Ignoring message on topic '{}'", topic);
+			return;
+		}
+	};
+
+	// Only messages signed by a trusted emitter get past this point
+	let (payload, signer) = match verify_payload(app_handle, project_name, &publish.payload) {
+		Ok(verified) => verified,
+		Err((signer, e)) => {
+			audit::reject(app_handle, project_name, &topic, signer.as_deref(), &e);
+			return;
+		}
+	};
+
+	if is_snapshot {
+		handle_snapshot_message(app_handle, project_name, payload, &signer);
+		return;
+	}
+
+	let envelope = match LogEnvelope::from_value(payload) {
+		// In Both mode this installation hears its own emitter; mirroring those lines would
+		// publish them again
+		Ok(envelope) if envelope.is_from(&host_id(app_handle)) => return,
+		envelope => envelope,
+	};
+	let envelope = envelope.and_then(|envelope| {
+		if envelope.project != project_name {
+			return Err(format!(
+				"El mensaje pertenece al proyecto '{}'",
+				envelope.project
+			));
+		}
+		open_envelope(app_handle, envelope)
 	});
+	match envelope.and_then(LogEnvelope::into_lines) {
+		Ok(lines) => {
+			if lines
+				.first()
+				.is_some_and(|line| is_subscribed(app_handle, project_name, &line.path))
+			{
+				for line in lines {
+					dispatch(app_handle, Apply::Log(line), Some(&signer));
+				}
+			}
+		}
+		Err(e) => audit::reject(app_handle, project_name, &topic, None, &e),
+	}
+}
 
-	let listener_state = app_handle.state::<MqttReceiverListener>();
-	*listener_state.0.lock().unwrap() = Some(listener_id);
+// Topic filters cannot express file patterns, so they are checked here
+fn is_subscribed(app_handle: &AppHandle, project_name: &str, relative_path: &str) -> bool {
+	let subscribed = app_handle
+		.state::<ReceiverSubscriptions>()
+		.accepts(project_name, relative_path);
+	if !subscribed {
+		println!(
+			"MQTT Receiver: Ignoring {}/{} (not subscribed)",
+			project_name, relative_path
+		);
+	}
+	subscribed
+}
 
-	let app_handle_for_loop = app_handle.clone();
-	loop {
-		println!("MQTT Receiver: Attempting to connect to broker...");
-
-		// --- TLS and Authentication Setup (using system certificates) ---
-		let broker_url = "b890714b58bc47ab852b24ea4753227b.s1.eu.hivemq.cloud";
-		let broker_port = 8883;
-		// IMPORTANT: Replace with your actual username and password
-		let mqtt_user = "zhent";
-		let mqtt_password = "Zhent1234";
-
-		// Generate a random client ID suffix to prevent collisions
-		let random_suffix: String =
-			rand::thread_rng().sample_iter(&rand::distributions::Alphanumeric).take(6).map(char::from).collect();
-		let client_id = format!("tauri_receiver_{}", random_suffix);
-
-		let mut mqtt_options = MqttOptions::new(client_id, broker_url, broker_port);
-		mqtt_options.set_credentials(mqtt_user, mqtt_password);
-		mqtt_options.set_keep_alive(Duration::from_secs(30));
-
-		// Configure TLS to use the system's native certificate store
-		let mut root_cert_store = rustls::RootCertStore::empty();
-		for cert in rustls_native_certs::load_native_certs().expect("could not load platform certs") {
-			root_cert_store.add(&rustls::Certificate(cert.0)).unwrap();
+// Checks the signature and the project's trust list; returns the signer, errors carry the
+// claimed one
+fn verify_payload(
+	app_handle: &AppHandle,
+	project_name: &str,
+	payload: &[u8],
+) -> Result<(Value, String), (Option<String>, String)> {
+	let value: Value = serde_json::from_slice(payload)
+		.map_err(|e| (None, format!("El mensaje no es JSON válido: {}", e)))?;
+	let claimed_signer = value["signer"].as_str().map(str::to_string);
+	let signer = signing::verify_value(&value)
+		.and_then(|signer| check_trusted(app_handle, project_name, &signer).map(|_| signer))
+		.map_err(|e| (claimed_signer, e))?;
+	Ok((value, signer))
+}
+
+// Decrypts and decompresses the content, enforcing the project's encryption policy
+fn open_envelope(app_handle: &AppHandle, mut envelope: LogEnvelope) -> Result<LogEnvelope, String> {
+	let encrypted = envelope.encryption.is_some();
+	if let Some(key) = key_for_incoming(app_handle, &envelope.project, encrypted)? {
+		envelope.decrypt(&key)?;
+	}
+	envelope.decompress()?;
+	Ok(envelope)
+}
+
+// Looks up the local project folder and applies the payload there; `signer` may get an unknown
+// project created
+fn dispatch(app_handle: &AppHandle, apply: Apply, signer: Option<&str>) {
+	let root = match project_root(app_handle, apply.project()) {
+		Ok(Some(root)) => root,
+		Ok(None) => match create_project(app_handle, apply.project(), signer) {
+			Ok(AutoCreated::Existing(root)) => root,
+			Ok(AutoCreated::Created(root)) => {
+				println!(
+					"MQTT Receiver: Created project '{}' in {}",
+					apply.project(),
+					root.display()
+				);
+				let _ = app_handle.emit("project-created", serde_json::json!({
+					"projectName": apply.project(),
+					"path": root.to_string_lossy()
+				}));
+				root
+			}
+			Ok(AutoCreated::Disabled) => {
+				eprintln!(
+					"MQTT Receiver: Project '{}' not found in database. Emitting notification.",
+					apply.project()
+				);
+				let _ = app_handle.emit("project-not-found", apply.project().to_string());
+				return;
+			}
+			Err(e) => {
+				eprintln!(
+					"MQTT Receiver: Failed to create project '{}': {}",
+					apply.project(),
+					e
+				);
+				let _ = app_handle.emit("project-not-found", apply.project().to_string());
+				return;
+			}
+		},
+		Err(e) => {
+			eprintln!("MQTT Receiver: {}", e);
+			return;
 		}
+	};
+	let watched = app_handle.state::<WatchedRoot>().0.lock().unwrap().clone();
+	if watched.is_some_and(|watched| overlaps(&root, &watched)) {
+		eprintln!(
+			"MQTT Receiver: Not mirroring '{}' into {}, the folder this emitter publishes.",
+			apply.project(),
+			root.display()
+		);
+		return;
+	}
+	match apply {
+		Apply::Log(envelope) => {
+			let delivery = app_handle.state::<Sequencer>().accept(envelope, &root);
+			apply_delivery(app_handle, &root, delivery);
+		}
+		Apply::Held(envelope) => apply_line(app_handle, &root, envelope),
+		Apply::Snapshot { project, path } => apply_snapshot(app_handle, &root, &project, &path),
+	}
+}
 
-		let client_config = rustls::ClientConfig::builder()
-			.with_safe_defaults()
-			.with_root_certificates(root_cert_store)
-			.with_no_client_auth();
+fn request_held_lines(app_handle: &AppHandle, lines: Vec<LogEnvelope>) {
+	for line in lines {
+		dispatch(app_handle, Apply::Held(line), None);
+	}
+}
 
-		mqtt_options.set_transport(Transport::tls_with_config(client_config.into()));
-		// --- End of TLS and Authentication Setup ---
+fn handle_snapshot_message(
+	app_handle: &AppHandle,
+	project_name: &str,
+	payload: Value,
+	signer: &str,
+) {
+	let sync = app_handle.state::<SnapshotSync>();
+	match serde_json::from_value::<SnapshotMessage>(payload) {
+		Ok(SnapshotMessage::Complete {
+			request_id,
+			path,
+			files,
+		}) => {
+			println!(
+				"MQTT Receiver: Snapshot {} complete ({} files).",
+				request_id, files
+			);
+			request_held_lines(
+				app_handle,
+				sync.finish_request(&request_id, project_name, path.as_deref()),
+			);
+		}
+		Ok(SnapshotMessage::Chunk { ref path, .. })
+			if path
+				.strip_prefix(&format!("{}/", project_name))
+				.is_some_and(|path| !is_subscribed(app_handle, project_name, path)) => {}
+		Ok(chunk) => match key_for_incoming(app_handle, project_name, chunk.is_encrypted())
+			.and_then(|key| sync.add_chunk(chunk, key.as_ref()))
+		{
+			Ok(Some(mqtt_path)) => match mqtt_path.strip_prefix(&format!("{}/", project_name)) {
+				Some(path) => dispatch(
+					app_handle,
+					Apply::Snapshot {
+						project: project_name.to_string(),
+						path: path.to_string(),
+					},
+					Some(signer),
+				),
+				None => eprintln!(
+					"MQTT Receiver: Snapshot of '{}' does not belong to project '{}'.",
+					mqtt_path, project_name
+				),
+			},
+			Ok(None) => {}
+			Err(e) => eprintln!("MQTT Receiver: {}", e),
+		},
+		Err(e) => eprintln!("MQTT Receiver: Malformed snapshot message: {}", e),
+	}
+}
 
-		let (mut client, mut connection) = Client::new(mqtt_options, 10);
+fn apply_delivery(app_handle: &AppHandle, root: &Path, delivery: Delivery) {
+	for gap in delivery.gaps {
+		let _ = app_handle.emit("log-gap", &gap);
+	}
+	for envelope in delivery.ready {
+		apply_line(app_handle, root, envelope);
+	}
+}
 
-		// Channel to send MQTT events from the blocking thread to the async task
-		let (event_tx, mut event_rx) = mpsc::channel(100);
+// Paths come from the network: anything that could leave the project folder is refused
+fn target_path(
+	app_handle: &AppHandle,
+	root: &Path,
+	project_name: &str,
+	relative_path: &str,
+) -> Option<PathBuf> {
+	match resolve_in_project(root, relative_path) {
+		Ok(target_path) => {
+			println!(
+				"MQTT Receiver: Final target path: {}",
+				target_path.display()
+			);
+			Some(target_path)
+		}
+		Err(e) => {
+			audit::reject(app_handle, project_name, relative_path, None, &e);
+			None
+		}
+	}
+}
 
-		// Spawn a blocking task to handle the MQTT connection's blocking recv()
-		let blocking_event_tx = event_tx.clone();
-		tauri::async_runtime::spawn_blocking(move || {
-			println!("MQTT Receiver Connection Handler (Blocking Thread): Started.");
-			for notification in connection.iter() {
-				if let Err(e) = blocking_event_tx.blocking_send(notification) {
+// Writes one line (or removal) on top of the local copy of the project
+fn apply_line(app_handle: &AppHandle, root: &Path, envelope: LogEnvelope) {
+	let Some(target_path) = target_path(app_handle, root, &envelope.project, &envelope.path) else {
+		return;
+	};
+	let sync = app_handle.state::<SnapshotSync>();
+
+	match envelope.kind {
+		EventKind::Created | EventKind::Modified => {
+			match sync.on_line(&envelope) {
+				LineAction::Apply => {}
+				LineAction::Skip => {
+					println!(
+						"MQTT Receiver: Line already in the snapshot of {}, skipping.",
+						target_path.display()
+					);
+					return;
+				}
+				LineAction::Hold => return,
+				LineAction::HoldAndRequest => {
+					// First line of a file we have not seen: fetch it whole first
+					if let Err(e) =
+						snapshot::send_request(app_handle, &envelope.project, Some(&envelope.path))
+					{
+						eprintln!("MQTT Receiver: Failed to request snapshot: {}", e);
+					}
+					return;
+				}
+			}
+			if let Some(parent) = target_path.parent() {
+				if let Err(e) = fs::create_dir_all(parent) {
+					eprintln!(
+						"MQTT Receiver: Failed to create parent directories for {}: {}",
+						target_path.display(),
+						e
+					);
+					return;
+				}
+			}
+			let mut file = match OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(&target_path)
+			{
+				Ok(file) => file,
+				Err(e) => {
 					eprintln!(
-						"MQTT Receiver Connection Handler (Blocking Thread): Failed to send event to async task: {}",
+						"MQTT Receiver: Failed to open or create file {}: {}",
+						target_path.display(),
 						e
 					);
-					break;
+					return;
+				}
+			};
+
+			// Written back in the source file's encoding, as the emitter read it
+			let encoding = encoding::for_label(&envelope.encoding).unwrap_or_else(|e| {
+				eprintln!("MQTT Receiver: {}. Writing UTF-8.", e);
+				encoding_rs::UTF_8
+			});
+			let newline = envelope.newline.as_deref().unwrap_or("\n");
+			let line = format!("{}{}", envelope.content, newline);
+			if let Err(e) = file.write_all(&encoding::encode(encoding, &line)) {
+				eprintln!(
+					"MQTT Receiver: Failed to write to file {}: {}",
+					target_path.display(),
+					e
+				);
+			} else {
+				println!(
+					"MQTT Receiver: Successfully appended to file {}",
+					target_path.display()
+				);
+				let _ = app_handle.emit("file_updated", target_path.to_string_lossy().to_string());
+				if envelope.record {
+					let _ = app_handle.emit(
+						"log-record",
+						serde_json::json!({
+							"projectName": envelope.project,
+							"path": target_path.to_string_lossy(),
+							"lines": envelope.content.lines().count(),
+						}),
+					);
 				}
 			}
-			println!("MQTT Receiver Connection Handler (Blocking Thread): Stopped.");
-		});
-
-		// Async task to process MQTT events received from the blocking thread
-		let app_handle_for_events = app_handle_for_loop.clone();
-		let mut reconnect_needed = false;
-
-		// Subscribe to topics
-		if let Err(e) = client.subscribe("project/#", QoS::AtLeastOnce) {
-			eprintln!("MQTT Receiver: Failed to subscribe to topic: {}", e);
-			reconnect_needed = true;
-		} else {
-			println!("MQTT Receiver: Subscribed to 'project/#");
 		}
+		EventKind::Removed => {
+			sync.forget(&envelope.mqtt_path());
+			apply_removal(app_handle, &envelope.project, &envelope.path, &target_path);
+		}
+	}
+}
 
-		while !reconnect_needed {
-			tokio::select! {
-				Some(notification) = event_rx.recv() => {
-					match notification {
-						Ok(Event::Incoming(Packet::ConnAck(_))) => {
-							println!("MQTT Receiver: Connected to broker!");
-						},
-						Ok(Event::Incoming(Packet::Publish(publish))) => {
-							let topic = publish.topic.clone();
-							let payload_str = String::from_utf8_lossy(&publish.payload);
-
-							if let Ok(json_payload) = serde_json::from_str::<Value>(&payload_str) {
-								if let Some(mqtt_path_str) = json_payload["path"].as_str() {
-									let mut project_name = topic.split('/').nth(1).unwrap_or("").to_string();
-									if project_name.is_empty() || project_name == "*" {
-										project_name = mqtt_path_str.split('/').next().unwrap_or("").to_string();
-									}
-
-									if !project_name.is_empty() {
-										println!("MQTT Receiver: Requesting path for project '{}' from frontend.", project_name);
-										let _ = app_handle_for_events.emit("request-project-path", serde_json::json!({
-											"projectName": project_name,
-											"originalPayload": json_payload
-										}));
-									} else {
-										eprintln!("MQTT Receiver: Could not determine project name from topic '{}' or payload.", topic);
-									}
-								} else {
-									eprintln!("MQTT Receiver: Malformed JSON payload: {}", payload_str);
-								}
-							} else {
-								println!("MQTT Receiver: Ignoring non-JSON message on topic '{}'", topic);
-							}
-						},
-						Ok(Event::Incoming(Packet::PingResp)) => {},
-						Ok(Event::Outgoing(rumqttc::Outgoing::PingReq)) => {},
-						Ok(event) => {
-							println!("MQTT Receiver: Received event: {:?}", event);
-						},
-						Err(e) => {
-							eprintln!("MQTT Receiver: Connection error: {:?}", e);
-							reconnect_needed = true;
-						}
-					}
-				},
-				_ = rx.recv() => {
-					println!("MQTT Receiver: Stop signal received. Shutting down.");
-					return; // Exit the init function entirely
+// Applies the project's delete policy to a file the emitter reported as removed
+fn apply_removal(app_handle: &AppHandle, project_name: &str, relative_path: &str, target: &Path) {
+	if !target.exists() {
+		return;
+	}
+	let policy = delete_policy(app_handle, project_name).unwrap_or_else(|e| {
+		eprintln!("MQTT Receiver: {}. Archiving {}.", e, target.display());
+		DeletePolicy::default()
+	});
+
+	match policy {
+		DeletePolicy::Ignore => {
+			println!(
+				"MQTT Receiver: Keeping {} (deletions are ignored for '{}')",
+				target.display(),
+				project_name
+			);
+			return;
+		}
+		DeletePolicy::Mirror => {
+			if let Err(e) = fs::remove_file(target) {
+				eprintln!(
+					"MQTT Receiver: Failed to remove file {}: {}",
+					target.display(),
+					e
+				);
+				return;
+			}
+			println!(
+				"MQTT Receiver: Successfully removed file {}",
+				target.display()
+			);
+		}
+		DeletePolicy::Archive => {
+			match archive_file(app_handle, project_name, relative_path, target) {
+				Ok(archived) => println!(
+					"MQTT Receiver: Archived {} to {}",
+					target.display(),
+					archived.display()
+				),
+				Err(e) => {
+					eprintln!(
+						"MQTT Receiver: Failed to archive file {}: {}",
+						target.display(),
+						e
+					);
+					return;
 				}
 			}
 		}
+	}
+	let _ = app_handle.emit("file_updated", target.to_string_lossy().to_string());
+}
 
-		println!("MQTT Receiver: Reconnection needed. Waiting before retrying...");
-		tokio::time::sleep(Duration::from_secs(5)).await; // Wait before attempting to reconnect
+// Moves `target` to <config dir>/archive/<project>/<path>.<timestamp>
+fn archive_file(
+	app_handle: &AppHandle,
+	project_name: &str,
+	relative_path: &str,
+	target: &Path,
+) -> Result<PathBuf, String> {
+	let archive_root = settings::config_file_path(app_handle, ARCHIVE_DIR)?.join(project_name);
+	let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+	let archived = resolve_in_project(&archive_root, &format!("{}.{}", relative_path, stamp))?;
+	if let Some(parent) = archived.parent() {
+		fs::create_dir_all(parent).map_err(|e| e.to_string())?;
 	}
+	// The archive may live on another volume, where rename is not possible
+	if fs::rename(target, &archived).is_err() {
+		fs::copy(target, &archived).map_err(|e| e.to_string())?;
+		fs::remove_file(target).map_err(|e| e.to_string())?;
+	}
+	Ok(archived)
+}
+
+fn apply_snapshot(app_handle: &AppHandle, root: &Path, project_name: &str, relative_path: &str) {
+	let mqtt_path = format!("{}/{}", project_name, relative_path);
+	let sync = app_handle.state::<SnapshotSync>();
+	let Some((offset, content)) = sync.take_ready(&mqtt_path) else {
+		return;
+	};
+
+	let Some(target_path) = target_path(app_handle, root, project_name, relative_path) else {
+		// Lines held for a refused file would be refused as well
+		sync.snapshot_written(&mqtt_path, None);
+		return;
+	};
+	let base_offset = match write_snapshot(&target_path, &content) {
+		Ok(()) => {
+			println!(
+				"MQTT Receiver: Snapshot written to {}",
+				target_path.display()
+			);
+			let _ = app_handle.emit("file_updated", target_path.to_string_lossy().to_string());
+			Some(offset)
+		}
+		Err(e) => {
+			eprintln!(
+				"MQTT Receiver: Failed to write snapshot {}: {}",
+				target_path.display(),
+				e
+			);
+			None
+		}
+	};
+	// Lines that arrived meanwhile go on top of the snapshot
+	for envelope in sync.snapshot_written(&mqtt_path, base_offset) {
+		apply_line(app_handle, root, envelope);
+	}
+}
+
+fn write_snapshot(target_path: &Path, content: &[u8]) -> std::io::Result<()> {
+	if let Some(parent) = target_path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	fs::write(target_path, content)
 }
 
 pub fn stop_receiver(app_handle: &AppHandle) {
 	let stopper_state = app_handle.state::<MqttReceiverStopper>();
 	let mut stopper = stopper_state.0.lock().unwrap();
 	if let Some(tx) = stopper.take() {
-		let _ = tx.send(());
+		let _ = tx.try_send(());
 	}
 
-	let listener_state = app_handle.state::<MqttReceiverListener>();
-	if let Some(listener_id) = listener_state.0.lock().unwrap().take() {
-		app_handle.unlisten(listener_id);
-	}
+	subscriptions::deactivate(app_handle);
+	app_handle
+		.state::<MqttService>()
+		.release(app_handle, RECEIVER_OWNER);
 
 	println!("MQTT Receiver: Stopping...");
 }
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::mqtt::envelope::EventKind;
+use crate::process::emitter_settings::BatchSettings;
+use crate::process::tail::TailLine;
+
+/// Lines of one file ready to be published as one message.
+pub struct Batch {
+	pub path: PathBuf,
+	/// Kind of the first line; a batch started by a creation stays `Created`
+	pub kind: EventKind,
+	pub lines: Vec<TailLine>,
+}
+
+struct Pending {
+	kind: EventKind,
+	lines: Vec<TailLine>,
+	bytes: usize,
+	since: Instant,
+}
+
+/// Groups the new lines of each file until a batch is full or has waited long enough.
+pub struct Batcher {
+	max_lines: usize,
+	max_bytes: usize,
+	max_delay: Duration,
+	pending: HashMap<PathBuf, Pending>,
+}
+
+impl Batcher {
+	pub fn new(settings: &BatchSettings) -> Self {
+		Batcher {
+			max_lines: settings.max_lines.max(1),
+			max_bytes: settings.max_bytes.max(1),
+			max_delay: Duration::from_millis(settings.max_delay_ms),
+			pending: HashMap::new(),
+		}
+	}
+
+	/// Adds lines read from `path` and returns the batches that filled up, in order.
+	pub fn push(&mut self, path: &Path, kind: EventKind, lines: Vec<TailLine>) -> Vec<Batch> {
+		let mut full = Vec::new();
+		for line in lines {
+			let pending = self
+				.pending
+				.entry(path.to_path_buf())
+				.or_insert_with(|| Pending {
+					kind,
+					lines: Vec::new(),
+					bytes: 0,
+					since: Instant::now(),
+				});
+			// A long line starts a batch of its own rather than overflowing the current one, and
+			// a batch holds a single encoding
+			let overflows = pending.bytes + line.content.len() > self.max_bytes;
+			let reencoded = pending
+				.lines
+				.first()
+				.is_some_and(|first| first.encoding != line.encoding);
+			if !pending.lines.is_empty() && (overflows || reencoded) {
+				full.extend(self.take(path));
+				full.extend(self.push(path, kind, vec![line]));
+				continue;
+			}
+			pending.bytes += line.content.len();
+			pending.lines.push(line);
+			if pending.lines.len() >= self.max_lines || pending.bytes >= self.max_bytes {
+				full.extend(self.take(path));
+			}
+		}
+		full
+	}
+
+	/// Removes the lines waiting for `path`, e.g. before its removal is published.
+	pub fn take(&mut self, path: &Path) -> Option<Batch> {
+		let pending = self.pending.remove(path)?;
+		Some(Batch {
+			path: path.to_path_buf(),
+			kind: pending.kind,
+			lines: pending.lines,
+		})
+	}
+
+	/// When the oldest waiting batch is due, if any.
+	pub fn next_due(&self) -> Option<Instant> {
+		self.pending
+			.values()
+			.map(|pending| pending.since + self.max_delay)
+			.min()
+	}
+
+	pub fn take_due(&mut self, now: Instant) -> Vec<Batch> {
+		let due: Vec<PathBuf> = self
+			.pending
+			.iter()
+			.filter(|(_, pending)| pending.since + self.max_delay <= now)
+			.map(|(path, _)| path.clone())
+			.collect();
+		due.iter().filter_map(|path| self.take(path)).collect()
+	}
+
+	/// Removes every waiting batch, e.g. when the watcher stops.
+	pub fn drain(&mut self) -> Vec<Batch> {
+		let paths: Vec<PathBuf> = self.pending.keys().cloned().collect();
+		paths.iter().filter_map(|path| self.take(path)).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use encoding_rs::{UTF_8, WINDOWS_1252};
+
+	fn batcher(max_lines: usize, max_bytes: usize) -> Batcher {
+		Batcher::new(&BatchSettings {
+			max_lines,
+			max_bytes,
+			max_delay_ms: 1000,
+			..BatchSettings::default()
+		})
+	}
+
+	fn lines(contents: &[&str]) -> Vec<TailLine> {
+		contents
+			.iter()
+			.map(|content| TailLine {
+				content: content.to_string(),
+				end_offset: 0,
+				newline: "\n",
+				encoding: UTF_8,
+			})
+			.collect()
+	}
+
+	fn contents(batch: &Batch) -> Vec<&str> {
+		batch
+			.lines
+			.iter()
+			.map(|line| line.content.as_str())
+			.collect()
+	}
+
+	#[test]
+	fn batches_are_published_once_they_hold_max_lines() {
+		let mut batcher = batcher(2, 1024);
+		let path = Path::new("app.log");
+		let full = batcher.push(path, EventKind::Created, lines(&["a", "b", "c"]));
+		assert_eq!(full.len(), 1);
+		assert_eq!(contents(&full[0]), ["a", "b"]);
+		assert_eq!(full[0].kind, EventKind::Created);
+
+		let rest = batcher.take(path).unwrap();
+		assert_eq!(contents(&rest), ["c"]);
+		assert!(batcher.take(path).is_none());
+	}
+
+	#[test]
+	fn a_line_that_would_overflow_starts_a_new_batch() {
+		let mut batcher = batcher(100, 10);
+		let path = Path::new("app.log");
+		let full = batcher.push(path, EventKind::Modified, lines(&["1234", "123456789"]));
+		assert_eq!(full.len(), 1);
+		assert_eq!(contents(&full[0]), ["1234"]);
+
+		// A line longer than the limit is sent on its own
+		let full = batcher.push(path, EventKind::Modified, lines(&["12345678901234"]));
+		let sizes: Vec<Vec<&str>> = full.iter().map(contents).collect();
+		assert_eq!(sizes, [vec!["123456789"], vec!["12345678901234"]]);
+	}
+
+	#[test]
+	fn a_batch_holds_a_single_encoding() {
+		let mut batcher = batcher(100, 1024);
+		let path = Path::new("app.log");
+		let mut mixed = lines(&["ascii", "año"]);
+		mixed[1].encoding = WINDOWS_1252;
+		let full = batcher.push(path, EventKind::Modified, mixed);
+		assert_eq!(full.len(), 1);
+		assert_eq!(contents(&full[0]), ["ascii"]);
+		assert_eq!(batcher.take(path).unwrap().lines[0].encoding, WINDOWS_1252);
+	}
+
+	#[test]
+	fn files_are_batched_apart_and_sent_when_due() {
+		let mut batcher = batcher(100, 1024);
+		batcher.push(Path::new("a.log"), EventKind::Modified, lines(&["a"]));
+		batcher.push(Path::new("b.log"), EventKind::Modified, lines(&["b"]));
+
+		let due = batcher.next_due().unwrap();
+		assert!(batcher
+			.take_due(due - Duration::from_millis(500))
+			.is_empty());
+		let mut sent: Vec<PathBuf> = batcher
+			.take_due(due + Duration::from_millis(10))
+			.into_iter()
+			.map(|batch| batch.path)
+			.collect();
+		sent.sort();
+		assert_eq!(sent, [PathBuf::from("a.log"), PathBuf::from("b.log")]);
+		assert!(batcher.next_due().is_none());
+	}
+}
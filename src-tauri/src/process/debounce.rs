@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// A file that keeps changing is still flushed once its burst is this many windows long
+const MAX_BURST_WINDOWS: u32 = 4;
+
+/// What happened to a file during one burst of notify events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+	Created,
+	Modified,
+	Removed,
+	/// Removed and created again (log rotation): the old content is gone
+	Recreated,
+}
+
+impl FileChange {
+	fn merge(self, next: FileChange) -> FileChange {
+		use FileChange::*;
+		match (self, next) {
+			(_, Removed) => Removed,
+			(Removed, _) | (Recreated, _) => Recreated,
+			(Created, _) | (_, Created) => Created,
+			(Modified, _) => Modified,
+		}
+	}
+}
+
+struct Pending {
+	change: FileChange,
+	first: Instant,
+	due: Instant,
+}
+
+/// Coalesces the notify events of each file until it has been quiet for `window`.
+pub struct Debouncer {
+	window: Duration,
+	pending: HashMap<PathBuf, Pending>,
+}
+
+impl Debouncer {
+	pub fn new(window: Duration) -> Self {
+		Debouncer {
+			window,
+			pending: HashMap::new(),
+		}
+	}
+
+	pub fn push(&mut self, path: PathBuf, change: FileChange) {
+		let now = Instant::now();
+		let max_due = |first: Instant| first + self.window * MAX_BURST_WINDOWS;
+		match self.pending.get_mut(&path) {
+			Some(pending) => {
+				pending.change = pending.change.merge(change);
+				pending.due = (now + self.window).min(max_due(pending.first));
+			}
+			None => {
+				self.pending.insert(
+					path,
+					Pending {
+						change,
+						first: now,
+						due: now + self.window,
+					},
+				);
+			}
+		}
+	}
+
+	/// When the next burst ends, if any is pending.
+	pub fn next_due(&self) -> Option<Instant> {
+		self.pending.values().map(|pending| pending.due).min()
+	}
+
+	/// Removes and returns the bursts that ended by `now`.
+	pub fn take_due(&mut self, now: Instant) -> Vec<(PathBuf, FileChange)> {
+		let due: Vec<PathBuf> = self
+			.pending
+			.iter()
+			.filter(|(_, pending)| pending.due <= now)
+			.map(|(path, _)| path.clone())
+			.collect();
+		due.into_iter()
+			.filter_map(|path| {
+				let pending = self.pending.remove(&path)?;
+				Some((path, pending.change))
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use FileChange::*;
+
+	fn merged(changes: &[FileChange]) -> FileChange {
+		changes[1..]
+			.iter()
+			.fold(changes[0], |change, next| change.merge(*next))
+	}
+
+	#[test]
+	fn a_burst_ending_in_a_removal_is_a_removal() {
+		assert_eq!(merged(&[Created, Modified, Removed]), Removed);
+		assert_eq!(merged(&[Recreated, Removed]), Removed);
+	}
+
+	#[test]
+	fn a_removal_followed_by_anything_is_a_recreation() {
+		assert_eq!(merged(&[Removed, Created]), Recreated);
+		assert_eq!(merged(&[Removed, Modified]), Recreated);
+		assert_eq!(merged(&[Modified, Removed, Created, Modified]), Recreated);
+	}
+
+	#[test]
+	fn a_creation_wins_over_modifications() {
+		assert_eq!(merged(&[Created, Modified, Modified]), Created);
+		assert_eq!(merged(&[Modified, Created]), Created);
+		assert_eq!(merged(&[Modified, Modified]), Modified);
+	}
+
+	#[test]
+	fn bursts_are_reported_once_their_file_is_quiet() {
+		let window = Duration::from_millis(100);
+		let mut debouncer = Debouncer::new(window);
+		let start = Instant::now();
+		debouncer.push(PathBuf::from("a.log"), Created);
+		debouncer.push(PathBuf::from("a.log"), Modified);
+
+		assert!(debouncer.take_due(start).is_empty());
+		let due = debouncer.next_due().unwrap();
+		assert!(due >= start + window);
+		assert_eq!(debouncer.take_due(due), [(PathBuf::from("a.log"), Created)]);
+		assert!(debouncer.next_due().is_none());
+	}
+
+	#[test]
+	fn a_file_that_keeps_changing_is_flushed_after_the_longest_burst() {
+		let window = Duration::from_millis(10);
+		let mut debouncer = Debouncer::new(window);
+		debouncer.push(PathBuf::from("a.log"), Modified);
+		let first_due = debouncer.next_due().unwrap();
+		let longest = first_due + window * (MAX_BURST_WINDOWS - 1);
+
+		std::thread::sleep(window * MAX_BURST_WINDOWS);
+		debouncer.push(PathBuf::from("a.log"), Modified);
+		assert!(debouncer.next_due().unwrap() <= longest);
+	}
+}
@@ -0,0 +1,141 @@
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle};
+
+use crate::mqtt::compression::Compression;
+use crate::mqtt::connection::MAX_PACKET_SIZE;
+use crate::mqtt::envelope::new_message_id;
+use crate::settings;
+
+const EMITTER_SETTINGS_FILE: &str = "emitter_settings.json";
+const HOST_ID_FILE: &str = "host_id.json";
+
+static HOST_ID: OnceCell<String> = OnceCell::new();
+
+// Limits for the on-disk queue of log events waiting to reach the broker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxLimits {
+	pub max_messages: Option<u64>,
+	pub max_age_secs: Option<u64>,
+}
+
+impl Default for OutboxLimits {
+	fn default() -> Self {
+		OutboxLimits {
+			max_messages: Some(100_000),
+			max_age_secs: Some(7 * 24 * 60 * 60),
+		}
+	}
+}
+
+// How new lines of one file are grouped into messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatchSettings {
+	/// Lines per message; 1 sends every line on its own
+	pub max_lines: usize,
+	/// Bytes of line content per message
+	pub max_bytes: usize,
+	/// How long lines may wait for more before they are sent
+	pub max_delay_ms: u64,
+	pub compression: Option<Compression>,
+}
+
+impl Default for BatchSettings {
+	fn default() -> Self {
+		BatchSettings {
+			max_lines: 1,
+			max_bytes: 256 * 1024,
+			max_delay_ms: 1000,
+			compression: None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmitterSettings {
+	pub outbox: OutboxLimits,
+	/// Quiet time after the last change of a file before its new lines are read
+	pub debounce_ms: u64,
+	pub batch: BatchSettings,
+	/// Publish whole records (a dated or leveled line and its continuation lines, such as a
+	/// stack trace) instead of single lines
+	pub whole_records: bool,
+	/// Quiet time after which the last record of a file is taken as complete
+	pub record_idle_ms: u64,
+}
+
+impl Default for EmitterSettings {
+	fn default() -> Self {
+		EmitterSettings {
+			outbox: OutboxLimits::default(),
+			debounce_ms: 300,
+			batch: BatchSettings::default(),
+			whole_records: false,
+			record_idle_ms: 2000,
+		}
+	}
+}
+
+pub fn load_emitter_settings(app_handle: &AppHandle) -> EmitterSettings {
+	settings::load_json(app_handle, EMITTER_SETTINGS_FILE).unwrap_or_else(|e| {
+		eprintln!("Emitter: {}. Using default settings.", e);
+		EmitterSettings::default()
+	})
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct HostIdentity {
+	host_id: String,
+}
+
+/// Stable id of this installation, stamped on every published event. Generated on first use.
+pub fn host_id(app_handle: &AppHandle) -> String {
+	HOST_ID
+		.get_or_init(|| {
+			let mut identity: HostIdentity =
+				settings::load_json(app_handle, HOST_ID_FILE).unwrap_or_default();
+			if identity.host_id.is_empty() {
+				let host_name = sysinfo::System::host_name().unwrap_or_else(|| "host".to_string());
+				identity.host_id = format!("{}-{}", host_name, &new_message_id()[..8]);
+				if let Err(e) = settings::save_json(app_handle, HOST_ID_FILE, &identity) {
+					eprintln!("Emitter: Failed to save the host id: {}", e);
+				}
+			}
+			identity.host_id
+		})
+		.clone()
+}
+
+#[command]
+pub fn get_emitter_settings(app_handle: AppHandle) -> EmitterSettings {
+	load_emitter_settings(&app_handle)
+}
+
+#[command]
+pub fn update_emitter_settings(
+	app_handle: AppHandle,
+	new_settings: EmitterSettings,
+) -> Result<(), String> {
+	let outbox = &new_settings.outbox;
+	if outbox.max_messages == Some(0) {
+		return Err("La cola local debe admitir al menos un mensaje".to_string());
+	}
+	if outbox.max_age_secs == Some(0) {
+		return Err("La antigüedad máxima de la cola local debe ser mayor a 0".to_string());
+	}
+	let batch = &new_settings.batch;
+	if batch.max_lines == 0 {
+		return Err("Un lote debe admitir al menos una línea".to_string());
+	}
+	// Leaves room for the envelope, base64 and encryption overhead
+	if batch.max_bytes == 0 || batch.max_bytes > MAX_PACKET_SIZE / 2 {
+		return Err(format!(
+			"El tamaño máximo de un lote debe estar entre 1 y {} bytes",
+			MAX_PACKET_SIZE / 2
+		));
+	}
+	settings::save_json(&app_handle, EMITTER_SETTINGS_FILE, &new_settings)
+}
@@ -0,0 +1,197 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+// How much of a file is read to guess its encoding
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+/// Canonical encoding for a label such as "utf-8", "latin1" or "UTF-16LE".
+pub fn for_label(label: &str) -> Result<&'static Encoding, String> {
+	Encoding::for_label(label.trim().as_bytes())
+		.ok_or_else(|| format!("Codificación desconocida: '{}'", label))
+}
+
+// UTF-16 without a BOM: most text is ASCII, so one byte of each pair is zero
+fn detect_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+	let pairs = bytes.len() / 2;
+	if pairs < 4 {
+		return None;
+	}
+	let zeros = |parity: usize| {
+		bytes
+			.iter()
+			.skip(parity)
+			.step_by(2)
+			.take(pairs)
+			.filter(|&&b| b == 0)
+			.count()
+	};
+	let (even, odd) = (zeros(0), zeros(1));
+	if odd * 10 >= pairs * 3 && even * 10 < pairs {
+		Some(UTF_16LE)
+	} else if even * 10 >= pairs * 3 && odd * 10 < pairs {
+		Some(UTF_16BE)
+	} else {
+		None
+	}
+}
+
+/// Guesses the encoding of a file from its first bytes: the BOM if there is one, then UTF-16
+/// without a BOM, then a statistical guess. `None` while the text is plain ASCII, which reads
+/// the same in every candidate.
+pub fn detect(bytes: &[u8]) -> Option<&'static Encoding> {
+	if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+		return Some(encoding);
+	}
+	if let Some(encoding) = detect_utf16(bytes) {
+		return Some(encoding);
+	}
+	if bytes.is_ascii() {
+		return None;
+	}
+	// A sample cut in the middle of a character must not rule UTF-8 out
+	let sample = match bytes.iter().rposition(|&b| b == b'\n') {
+		Some(last_newline) => &bytes[..=last_newline],
+		None => bytes,
+	};
+	let mut detector = EncodingDetector::new();
+	detector.feed(sample, true);
+	Some(detector.guess(None, true))
+}
+
+/// `detect` over the first bytes of the file at `path`.
+pub fn sniff(path: &Path) -> io::Result<Option<&'static Encoding>> {
+	let mut head = Vec::new();
+	File::open(path)?.take(SNIFF_BYTES).read_to_end(&mut head)?;
+	Ok(detect(&head))
+}
+
+/// '\n' in `encoding`.
+pub fn newline(encoding: &'static Encoding) -> &'static [u8] {
+	if encoding == UTF_16LE {
+		b"\n\0"
+	} else if encoding == UTF_16BE {
+		b"\0\n"
+	} else {
+		b"\n"
+	}
+}
+
+/// '\r' in `encoding`.
+pub fn carriage_return(encoding: &'static Encoding) -> &'static [u8] {
+	if encoding == UTF_16LE {
+		b"\r\0"
+	} else if encoding == UTF_16BE {
+		b"\0\r"
+	} else {
+		b"\r"
+	}
+}
+
+/// Splits `bytes` into complete lines, each with its newline; a trailing line without one is
+/// left out.
+pub fn complete_lines<'a>(encoding: &'static Encoding, bytes: &'a [u8]) -> Vec<&'a [u8]> {
+	let newline = newline(encoding);
+	let mut lines = Vec::new();
+	let mut start = 0;
+	let mut position = 0;
+	while position + newline.len() <= bytes.len() {
+		if &bytes[position..position + newline.len()] == newline {
+			lines.push(&bytes[start..position + newline.len()]);
+			start = position + newline.len();
+		}
+		// UTF-16 newlines only count on a code unit boundary
+		position += newline.len();
+	}
+	lines
+}
+
+/// Decodes `bytes` as they are; a BOM is kept as U+FEFF so encoding the text again gives back
+/// the same bytes.
+pub fn decode(encoding: &'static Encoding, bytes: &[u8]) -> String {
+	encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Encodes `text` in `encoding`. encoding_rs only writes UTF-8 for the UTF-16 encodings, so
+/// those are encoded here.
+pub fn encode(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+	if encoding == UTF_16LE {
+		text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+	} else if encoding == UTF_16BE {
+		text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+	} else {
+		encoding.encode(text).0.into_owned()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use encoding_rs::{UTF_8, WINDOWS_1252};
+
+	#[test]
+	fn plain_ascii_has_no_encoding_yet() {
+		assert_eq!(detect(b"2024-01-01 INFO started\n"), None);
+		assert_eq!(detect(b""), None);
+	}
+
+	#[test]
+	fn a_bom_decides_the_encoding() {
+		assert_eq!(detect(b"\xEF\xBB\xBFok\n"), Some(UTF_8));
+		assert_eq!(detect(b"\xFF\xFEo\0k\0"), Some(UTF_16LE));
+		assert_eq!(detect(b"\xFE\xFF\0o\0k"), Some(UTF_16BE));
+	}
+
+	#[test]
+	fn utf16_without_a_bom_is_told_by_its_zero_bytes() {
+		assert_eq!(detect(&encode(UTF_16LE, "line one\n")), Some(UTF_16LE));
+		assert_eq!(detect(&encode(UTF_16BE, "line one\n")), Some(UTF_16BE));
+	}
+
+	#[test]
+	fn accented_text_is_told_apart() {
+		let text = "Operación completada en año fiscal\nPróxima ejecución mañana\n";
+		assert_eq!(detect(text.as_bytes()), Some(UTF_8));
+		assert_eq!(detect(&encode(WINDOWS_1252, text)), Some(WINDOWS_1252));
+	}
+
+	#[test]
+	fn a_sample_cut_inside_a_character_is_still_utf8() {
+		let bytes = "Operación completada\nañ".as_bytes();
+		let cut = &bytes[..bytes.len() - 1];
+		assert_eq!(detect(cut), Some(UTF_8));
+	}
+
+	#[test]
+	fn only_complete_lines_are_returned() {
+		let lines = complete_lines(UTF_8, b"one\ntwo\r\nthr");
+		assert_eq!(lines, [&b"one\n"[..], &b"two\r\n"[..]]);
+		assert!(complete_lines(UTF_8, b"partial").is_empty());
+	}
+
+	#[test]
+	fn utf16_lines_split_on_code_unit_boundaries() {
+		let bytes = encode(UTF_16LE, "a\nb\nc");
+		let lines: Vec<String> = complete_lines(UTF_16LE, &bytes)
+			.into_iter()
+			.map(|line| decode(UTF_16LE, line))
+			.collect();
+		assert_eq!(lines, ["a\n", "b\n"]);
+
+		// Both hold a newline's bytes at an odd offset, across two characters
+		let bytes = encode(UTF_16LE, "\u{0A00}\u{4E00}\n");
+		assert_eq!(complete_lines(UTF_16LE, &bytes), [&bytes[..]]);
+		let bytes = encode(UTF_16BE, "\u{4E00}\u{0A41}");
+		assert!(complete_lines(UTF_16BE, &bytes).is_empty());
+	}
+
+	#[test]
+	fn decoding_and_encoding_give_back_the_same_bytes() {
+		for encoding in [UTF_8, WINDOWS_1252, UTF_16LE, UTF_16BE] {
+			let bytes = encode(encoding, "\u{FEFF}Año: 2024\n");
+			assert_eq!(encode(encoding, &decode(encoding, &bytes)), bytes);
+		}
+	}
+}
@@ -0,0 +1,170 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::process::watch::project_relative_path;
+
+// Published when a project has no include rules, as before rules existed
+const DEFAULT_INCLUDE: [&str; 2] = ["*.log", "*.txt"];
+
+/// Which files of a project the emitter publishes, as gitignore-style globs.
+///
+/// A pattern without '/' matches a file or folder name at any depth, one with a '/' (or a
+/// leading '/') is relative to the project root, and a trailing '/' only matches folders.
+/// `*` stays within a folder and `**` crosses them. A matched folder covers everything in it.
+/// Exclude patterns win. A leading '!' makes a pattern an exclusion in either list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileRules {
+	pub include: Vec<String>,
+	pub exclude: Vec<String>,
+}
+
+pub struct FileFilter {
+	include: GlobSet,
+	exclude: GlobSet,
+}
+
+impl Default for FileFilter {
+	fn default() -> Self {
+		FileFilter::compile(&FileRules::default()).expect("default file rules")
+	}
+}
+
+impl FileFilter {
+	pub fn compile(rules: &FileRules) -> Result<Self, String> {
+		// "!pattern" among the includes is an exclusion, as in gitignore
+		let (negated, mut include): (Vec<&str>, Vec<&str>) = rules
+			.include
+			.iter()
+			.map(|pattern| pattern.trim())
+			.partition(|pattern| pattern.starts_with('!'));
+		if include.iter().all(|pattern| pattern.is_empty()) {
+			include = DEFAULT_INCLUDE.to_vec();
+		}
+		let exclude: Vec<&str> = rules
+			.exclude
+			.iter()
+			.map(|pattern| pattern.trim())
+			.chain(negated)
+			.map(|pattern| pattern.trim_start_matches('!'))
+			.collect();
+		Ok(FileFilter {
+			include: build_set(&include)?,
+			exclude: build_set(&exclude)?,
+		})
+	}
+
+	/// Whether the file at `relative_path` ('/'-separated, relative to the root) is published.
+	pub fn matches(&self, relative_path: &str) -> bool {
+		self.include.is_match(relative_path) && !self.exclude.is_match(relative_path)
+	}
+
+	pub fn matches_path(&self, root: &Path, path: &Path) -> bool {
+		self.matches(&project_relative_path(root, path).1)
+	}
+}
+
+fn build_set(patterns: &[&str]) -> Result<GlobSet, String> {
+	let mut builder = GlobSetBuilder::new();
+	for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+		for glob in translate(pattern) {
+			// Windows paths are case-insensitive, and so are the rules
+			let glob = GlobBuilder::new(&glob)
+				.literal_separator(true)
+				.case_insensitive(true)
+				.build()
+				.map_err(|e| format!("Patrón inválido '{}': {}", pattern, e))?;
+			builder.add(glob);
+		}
+	}
+	builder
+		.build()
+		.map_err(|e| format!("Error compilando los patrones: {}", e))
+}
+
+// Turns one gitignore-style pattern into globs over the relative file path
+fn translate(pattern: &str) -> Vec<String> {
+	let folder_only = pattern.ends_with('/');
+	let pattern = pattern.trim_end_matches('/');
+	let anchored = pattern.contains('/');
+	let pattern = pattern.trim_start_matches('/');
+	let base = if anchored || pattern.starts_with("**") {
+		pattern.to_string()
+	} else {
+		format!("**/{}", pattern)
+	};
+
+	// Files inside a matched folder
+	let contents = format!("{}/**", base);
+	if folder_only {
+		vec![contents]
+	} else {
+		vec![base, contents]
+	}
+}
+
+/// Filter of the project the emitter is watching.
+#[derive(Default)]
+pub struct EmitterFileFilter(pub Mutex<FileFilter>);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rules(include: &[&str], exclude: &[&str]) -> FileFilter {
+		FileFilter::compile(&FileRules {
+			include: include.iter().map(|p| p.to_string()).collect(),
+			exclude: exclude.iter().map(|p| p.to_string()).collect(),
+		})
+		.unwrap()
+	}
+
+	#[test]
+	fn logs_and_text_files_are_published_by_default() {
+		let filter = FileFilter::default();
+		assert!(filter.matches("app.log"));
+		assert!(filter.matches("bots/run/output.TXT"));
+		assert!(!filter.matches("config.json"));
+	}
+
+	#[test]
+	fn names_match_at_any_depth_and_paths_from_the_root() {
+		let filter = rules(&["*.csv", "/exports/*.json"], &[]);
+		assert!(filter.matches("a/b/data.csv"));
+		assert!(filter.matches("exports/out.json"));
+		assert!(!filter.matches("other/exports/out.json"));
+		// `*` stays within a folder
+		assert!(!filter.matches("exports/old/out.json"));
+	}
+
+	#[test]
+	fn folder_patterns_cover_their_contents() {
+		let filter = rules(&["logs/"], &[]);
+		assert!(filter.matches("logs/app.log"));
+		assert!(filter.matches("logs/2026/app.log"));
+		// A trailing '/' only matches folders
+		assert!(!filter.matches("logs"));
+	}
+
+	#[test]
+	fn excludes_win_over_includes() {
+		let filter = rules(&["*.log"], &["tmp/", "!debug.log"]);
+		assert!(filter.matches("app.log"));
+		assert!(!filter.matches("tmp/app.log"));
+		assert!(!filter.matches("sub/debug.log"));
+	}
+
+	#[test]
+	fn negated_includes_are_exclusions() {
+		let filter = rules(&["*.log", "!trace.log"], &[]);
+		assert!(filter.matches("app.log"));
+		assert!(!filter.matches("trace.log"));
+
+		// Only exclusions keep the default includes
+		let filter = rules(&["!*.txt"], &[]);
+		assert!(filter.matches("app.log"));
+		assert!(!filter.matches("notes.txt"));
+	}
+}
@@ -0,0 +1,42 @@
+use keyring::Entry;
+
+// Name the app's secrets are filed under in the OS credential store (Windows Credential
+// Manager, macOS Keychain or the Secret Service), out of reach of the webview
+const SERVICE: &str = "proyecto-monitoreo-bots";
+
+fn entry(name: &str) -> Result<Entry, String> {
+	Entry::new(SERVICE, name)
+		.map_err(|e| format!("Error abriendo el almacén de credenciales: {}", e))
+}
+
+/// The secret stored as `name`, `None` when there is none.
+pub fn get(name: &str) -> Result<Option<Vec<u8>>, String> {
+	match entry(name)?.get_secret() {
+		Ok(secret) => Ok(Some(secret)),
+		Err(keyring::Error::NoEntry) => Ok(None),
+		Err(e) => Err(format!(
+			"Error leyendo '{}' del almacén de credenciales: {}",
+			name, e
+		)),
+	}
+}
+
+pub fn set(name: &str, secret: &[u8]) -> Result<(), String> {
+	entry(name)?.set_secret(secret).map_err(|e| {
+		format!(
+			"Error guardando '{}' en el almacén de credenciales: {}",
+			name, e
+		)
+	})
+}
+
+/// Deletes the secret stored as `name`; nothing happens when there is none.
+pub fn delete(name: &str) -> Result<(), String> {
+	match entry(name)?.delete_credential() {
+		Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+		Err(e) => Err(format!(
+			"Error eliminando '{}' del almacén de credenciales: {}",
+			name, e
+		)),
+	}
+}
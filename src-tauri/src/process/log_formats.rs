@@ -0,0 +1,431 @@
+use chrono::{DateTime, FixedOffset};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::process::logs::RecordDetector;
+use crate::process::timestamps::TimestampParser;
+
+/// Profile used by projects that have not selected one: the columns the viewer always assumed.
+pub const DEFAULT_PROFILE: &str = "delimited";
+
+/// Named value a format profile can extract from a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+	Timestamp,
+	Level,
+	Bot,
+	Machine,
+	Process,
+	Message,
+}
+
+impl Field {
+	const ALL: [Field; 6] = [
+		Field::Timestamp,
+		Field::Level,
+		Field::Bot,
+		Field::Machine,
+		Field::Process,
+		Field::Message,
+	];
+
+	fn name(self) -> &'static str {
+		match self {
+			Field::Timestamp => "timestamp",
+			Field::Level => "level",
+			Field::Bot => "bot",
+			Field::Machine => "machine",
+			Field::Process => "process",
+			Field::Message => "message",
+		}
+	}
+}
+
+/// How the fields are found in the first line of a record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FormatParser {
+	/// Columns split by `delimiter` (the project's separator when absent). Each field names
+	/// its column; negative positions count from the end, -1 being the last column. A date
+	/// followed by a level in the first two columns is swapped first, as the viewer expects.
+	Delimited {
+		#[serde(default)]
+		delimiter: Option<String>,
+		columns: BTreeMap<Field, i64>,
+	},
+	/// A regular expression whose named groups are the fields (`(?P<level>...)`)
+	Regex { pattern: String },
+	/// One JSON object per record, possibly after a text prefix; `keys` names the property
+	/// holding each field, '.' reaching into nested objects
+	JsonLines { keys: BTreeMap<Field, String> },
+	/// `key=value` pairs separated by spaces, values optionally in double quotes
+	KeyValue { keys: BTreeMap<Field, String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatProfile {
+	pub name: String,
+	#[serde(default)]
+	pub description: String,
+	#[serde(flatten)]
+	pub parser: FormatParser,
+}
+
+/// Fields extracted from one record; absent when the profile does not provide them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LogFields {
+	/// RFC 3339, read with the project's timestamp formats
+	pub timestamp: Option<String>,
+	/// The timestamp as written in the log
+	pub raw_timestamp: Option<String>,
+	/// Set when the record has a timestamp none of the formats could read
+	pub timestamp_error: bool,
+	#[serde(skip)]
+	pub time: Option<DateTime<FixedOffset>>,
+	pub level: Option<String>,
+	pub bot: Option<String>,
+	pub machine: Option<String>,
+	pub process: Option<String>,
+	pub message: Option<String>,
+}
+
+impl LogFields {
+	fn set(&mut self, field: Field, value: String) {
+		let slot = match field {
+			Field::Timestamp => &mut self.raw_timestamp,
+			Field::Level => &mut self.level,
+			Field::Bot => &mut self.bot,
+			Field::Machine => &mut self.machine,
+			Field::Process => &mut self.process,
+			Field::Message => &mut self.message,
+		};
+		if !value.is_empty() {
+			*slot = Some(value);
+		}
+	}
+}
+
+fn keys(pairs: &[(Field, &str)]) -> BTreeMap<Field, String> {
+	pairs
+		.iter()
+		.map(|(field, key)| (*field, key.to_string()))
+		.collect()
+}
+
+/// Profiles every project can select.
+pub fn builtin_profiles() -> Vec<FormatProfile> {
+	vec![
+		FormatProfile {
+			name: DEFAULT_PROFILE.to_string(),
+			description: "Nivel | Fecha | Mensaje | ... | Máquina, con el separador del proyecto"
+				.to_string(),
+			parser: FormatParser::Delimited {
+				delimiter: None,
+				columns: [
+					(Field::Level, 0),
+					(Field::Timestamp, 1),
+					(Field::Message, 2),
+					(Field::Machine, -1),
+				]
+				.into_iter()
+				.collect(),
+			},
+		},
+		FormatProfile {
+			name: "uipath".to_string(),
+			description: "Logs de ejecución de UiPath Robot (JSON por línea)".to_string(),
+			parser: FormatParser::JsonLines {
+				keys: keys(&[
+					(Field::Timestamp, "timeStamp"),
+					(Field::Level, "level"),
+					(Field::Bot, "robotName"),
+					(Field::Machine, "machineName"),
+					(Field::Process, "processName"),
+					(Field::Message, "message"),
+				]),
+			},
+		},
+		FormatProfile {
+			name: "logfmt".to_string(),
+			description: "Pares clave=valor (logfmt)".to_string(),
+			parser: FormatParser::KeyValue {
+				keys: keys(&[
+					(Field::Timestamp, "time"),
+					(Field::Level, "level"),
+					(Field::Bot, "bot"),
+					(Field::Machine, "host"),
+					(Field::Process, "process"),
+					(Field::Message, "msg"),
+				]),
+			},
+		},
+	]
+}
+
+enum Parser {
+	Delimited(BTreeMap<Field, i64>),
+	Regex(Regex),
+	JsonLines(BTreeMap<Field, String>),
+	KeyValue(BTreeMap<Field, String>),
+}
+
+/// A compiled profile.
+pub struct LogFormat {
+	// Splits delimited records and tells dates and levels apart
+	detector: RecordDetector,
+	parser: Parser,
+	timestamps: TimestampParser,
+}
+
+impl LogFormat {
+	/// `separator` is the project's, used by delimited profiles that name no delimiter.
+	pub fn compile(profile: &FormatProfile, separator: &str) -> Result<Self, String> {
+		let invalid = |reason: String| format!("Perfil de formato '{}': {}", profile.name, reason);
+		let (delimiter, parser) = match &profile.parser {
+			FormatParser::Delimited { delimiter, columns } => {
+				let delimiter = delimiter.as_deref().unwrap_or(separator);
+				if delimiter.is_empty() {
+					return Err(invalid("el separador no puede estar vacío".to_string()));
+				}
+				(delimiter, Parser::Delimited(columns.clone()))
+			}
+			FormatParser::Regex { pattern } => {
+				let regex = Regex::new(pattern).map_err(|e| invalid(e.to_string()))?;
+				let named =
+					|field: Field| regex.capture_names().flatten().any(|n| n == field.name());
+				if !Field::ALL.into_iter().any(named) {
+					return Err(invalid(
+						"la expresión no tiene grupos con nombre de campo".to_string(),
+					));
+				}
+				(separator, Parser::Regex(regex))
+			}
+			FormatParser::JsonLines { keys } => (separator, Parser::JsonLines(keys.clone())),
+			FormatParser::KeyValue { keys } => (separator, Parser::KeyValue(keys.clone())),
+		};
+		Ok(LogFormat {
+			detector: RecordDetector::new(delimiter),
+			parser,
+			timestamps: TimestampParser::default(),
+		})
+	}
+
+	/// Reads timestamps with `timestamps` instead of the default formats.
+	pub fn with_timestamps(mut self, timestamps: TimestampParser) -> Self {
+		self.timestamps = timestamps;
+		self
+	}
+
+	/// The default profile with `separator`, which must not be empty.
+	pub fn default_for(separator: &str) -> Self {
+		let profile = builtin_profiles().swap_remove(0);
+		LogFormat::compile(&profile, separator).expect("default profile")
+	}
+
+	/// Whether `line` starts a record; other lines continue the previous one.
+	pub fn starts_record(&self, line: &str) -> bool {
+		let line = line.trim_start_matches('\u{feff}');
+		match &self.parser {
+			Parser::Delimited(_) => self.detector.starts_record(line),
+			Parser::Regex(regex) => regex.is_match(line),
+			Parser::JsonLines(_) => json_object(line).is_some(),
+			Parser::KeyValue(keys) => {
+				let pairs = key_values(line);
+				[Field::Timestamp, Field::Level].iter().any(|field| {
+					keys.get(field)
+						.is_some_and(|key| pairs.iter().any(|(k, _)| k == key))
+				})
+			}
+		}
+	}
+
+	/// Columns of a record as the viewer shows them: split by the delimiter for delimited
+	/// profiles, else level, timestamp and message followed by bot, process and machine, taken
+	/// from the record's `fields`.
+	pub fn columns(&self, record: &str, fields: &LogFields) -> Vec<String> {
+		if let Parser::Delimited(_) = self.parser {
+			return self.detector.columns(record);
+		}
+		[
+			&fields.level,
+			&fields.raw_timestamp,
+			&fields.message,
+			&fields.bot,
+			&fields.process,
+			&fields.machine,
+		]
+		.into_iter()
+		.map(|value| value.clone().unwrap_or_default())
+		.collect()
+	}
+
+	/// Fields of a record, read from its first line; continuation lines are part of the
+	/// message.
+	pub fn fields(&self, record: &str) -> LogFields {
+		let record = record.trim_start_matches('\u{feff}');
+		let mut fields = LogFields::default();
+		let (first, rest) = match record.split_once('\n') {
+			Some((first, rest)) => (first, Some(rest)),
+			None => (record, None),
+		};
+		match &self.parser {
+			Parser::Delimited(columns) => {
+				let values = self.detector.columns(first);
+				for (field, &position) in columns {
+					let index = if position < 0 {
+						values.len() as i64 + position
+					} else {
+						position
+					};
+					if let Some(value) = usize::try_from(index).ok().and_then(|i| values.get(i)) {
+						fields.set(*field, value.clone());
+					}
+				}
+			}
+			Parser::Regex(regex) => {
+				if let Some(captures) = regex.captures(first) {
+					for field in Field::ALL {
+						if let Some(value) = captures.name(field.name()) {
+							fields.set(field, value.as_str().trim().to_string());
+						}
+					}
+				}
+			}
+			Parser::JsonLines(keys) => {
+				if let Some(object) = json_object(first) {
+					for (field, key) in keys {
+						let value = key
+							.split('.')
+							.try_fold(&object, |value, part| value.get(part));
+						match value {
+							Some(Value::String(text)) => fields.set(*field, text.clone()),
+							Some(Value::Null) | None => {}
+							Some(other) => fields.set(*field, other.to_string()),
+						}
+					}
+				}
+			}
+			Parser::KeyValue(keys) => {
+				let pairs = key_values(first);
+				for (field, key) in keys {
+					if let Some((_, value)) = pairs.iter().find(|(k, _)| k == key) {
+						fields.set(*field, value.clone());
+					}
+				}
+			}
+		}
+		if let Some(rest) = rest {
+			let message = fields.message.get_or_insert_with(String::new);
+			message.push('\n');
+			message.push_str(rest);
+		}
+		if let Some(raw) = &fields.raw_timestamp {
+			fields.time = self.timestamps.parse(raw);
+			fields.timestamp = fields.time.map(|time| time.to_rfc3339());
+			fields.timestamp_error = fields.time.is_none();
+		}
+		fields
+	}
+}
+
+// The JSON object in a line, skipping any text before it (UiPath prefixes a time and level)
+fn json_object(line: &str) -> Option<Value> {
+	let start = line.find('{')?;
+	match serde_json::from_str(line[start..].trim_end()) {
+		Ok(value @ Value::Object(_)) => Some(value),
+		_ => None,
+	}
+}
+
+// `key=value` and `key="quoted value"` pairs
+fn key_values(line: &str) -> Vec<(String, String)> {
+	let mut pairs = Vec::new();
+	let mut rest = line.trim();
+	while !rest.is_empty() {
+		let Some(equals) = rest.find('=') else {
+			break;
+		};
+		let key = rest[..equals]
+			.rsplit(char::is_whitespace)
+			.next()
+			.unwrap_or("");
+		rest = &rest[equals + 1..];
+		let value = match rest.strip_prefix('"') {
+			Some(quoted) => {
+				let end = quoted.find('"').unwrap_or(quoted.len());
+				rest = quoted.get(end + 1..).unwrap_or("");
+				quoted[..end].to_string()
+			}
+			None => {
+				let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+				let value = rest[..end].to_string();
+				rest = &rest[end..];
+				value
+			}
+		};
+		if !key.is_empty() {
+			pairs.push((key.to_string(), value));
+		}
+		rest = rest.trim_start();
+	}
+	pairs
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_pairs(line: &str, expected: &[(&str, &str)]) {
+		let expected: Vec<(String, String)> = expected
+			.iter()
+			.map(|(key, value)| (key.to_string(), value.to_string()))
+			.collect();
+		assert_eq!(key_values(line), expected, "{}", line);
+	}
+
+	#[test]
+	fn plain_and_quoted_values_are_read() {
+		assert_pairs(
+			r#"level=INFO msg="Bot iniciado correctamente" bot=Facturas"#,
+			&[
+				("level", "INFO"),
+				("msg", "Bot iniciado correctamente"),
+				("bot", "Facturas"),
+			],
+		);
+	}
+
+	#[test]
+	fn text_before_a_key_is_not_part_of_it() {
+		assert_pairs(
+			"2024-05-01 10:00:00 user=ana action=login",
+			&[("user", "ana"), ("action", "login")],
+		);
+	}
+
+	#[test]
+	fn empty_and_unterminated_values_are_kept() {
+		assert_pairs(
+			r#"machine= msg="sin cerrar"#,
+			&[("machine", ""), ("msg", "sin cerrar")],
+		);
+	}
+
+	#[test]
+	fn lines_without_pairs_give_nothing() {
+		for line in ["ERROR algo falló", "=huérfano", ""] {
+			assert_pairs(line, &[]);
+		}
+	}
+
+	#[test]
+	fn json_objects_are_found_after_a_prefix() {
+		let value = json_object(r#"10:00:00 Info {"message":"hola","level":"Info"}"#).unwrap();
+		assert_eq!(value["message"], "hola");
+		assert!(json_object("[1, 2]").is_none());
+		assert!(json_object("{roto").is_none());
+	}
+}
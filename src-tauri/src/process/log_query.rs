@@ -0,0 +1,460 @@
+use chrono::{DateTime, FixedOffset};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle};
+
+use crate::process::log_formats::LogFields;
+use crate::process::log_sessions::read_rows;
+
+// Rows read from the file at a time while scanning
+const SCAN_PAGE: usize = 5000;
+// Most matches a single query returns
+const MAX_MATCHES: usize = 5000;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnOp {
+	#[default]
+	Equals,
+	NotEquals,
+	Contains,
+	Regex,
+}
+
+/// Condition on one column of the row, as `get_log_rows` returns it. Text comparisons ignore
+/// case.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnPredicate {
+	/// Position of the column, from 0
+	pub column: usize,
+	#[serde(default)]
+	pub op: ColumnOp,
+	pub value: String,
+}
+
+/// Filters of a `query_log` call; a row must pass every one that is set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LogQuery {
+	/// Levels to keep, ignoring case; every level when empty
+	pub levels: Vec<String>,
+	/// RFC 3339 bounds, both included. Rows without a readable timestamp are left out when
+	/// either is set.
+	pub from: Option<String>,
+	pub to: Option<String>,
+	/// Text looked for in every column; where it is found is returned as highlights
+	pub search: Option<String>,
+	/// `search` is a regular expression
+	pub regex: bool,
+	pub case_sensitive: bool,
+	/// Machine name, ignoring case
+	pub machine: Option<String>,
+	pub columns: Vec<ColumnPredicate>,
+	/// Matches to skip, to page through the results
+	pub offset: usize,
+	/// Most matches returned; 0 for the maximum
+	pub limit: usize,
+}
+
+/// Where the search matched, in UTF-16 code units as JavaScript strings count them.
+#[derive(Debug, Clone, Serialize)]
+pub struct Highlight {
+	pub column: usize,
+	pub start: usize,
+	pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogMatch {
+	/// Index of the row in the session
+	pub row: usize,
+	/// Line of the file the row starts at, from 1
+	pub line: u64,
+	pub columns: Vec<String>,
+	pub fields: LogFields,
+	pub highlights: Vec<Highlight>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogQueryResult {
+	pub matches: Vec<LogMatch>,
+	/// Every match among the scanned rows, including those skipped or past the limit
+	pub total_matches: usize,
+	pub scanned_rows: usize,
+	/// False while the file is still being indexed; the query can be repeated later
+	pub complete: bool,
+}
+
+enum ColumnTest {
+	Equals(String),
+	NotEquals(String),
+	Contains(String),
+	Regex(Regex),
+}
+
+impl ColumnTest {
+	fn passes(&self, value: &str) -> bool {
+		match self {
+			ColumnTest::Equals(expected) => value.to_lowercase() == *expected,
+			ColumnTest::NotEquals(expected) => value.to_lowercase() != *expected,
+			ColumnTest::Contains(expected) => value.to_lowercase().contains(expected.as_str()),
+			ColumnTest::Regex(regex) => regex.is_match(value),
+		}
+	}
+}
+
+struct Filter {
+	levels: Vec<String>,
+	from: Option<DateTime<FixedOffset>>,
+	to: Option<DateTime<FixedOffset>>,
+	search: Option<Regex>,
+	machine: Option<String>,
+	columns: Vec<(usize, ColumnTest)>,
+}
+
+fn parse_bound(bound: &Option<String>) -> Result<Option<DateTime<FixedOffset>>, String> {
+	match bound.as_deref().map(str::trim) {
+		Some(bound) if !bound.is_empty() => DateTime::parse_from_rfc3339(bound)
+			.map(Some)
+			.map_err(|e| format!("Fecha inválida '{}': {}", bound, e)),
+		_ => Ok(None),
+	}
+}
+
+fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, String> {
+	RegexBuilder::new(pattern)
+		.case_insensitive(!case_sensitive)
+		.build()
+		.map_err(|e| format!("Expresión de búsqueda inválida '{}': {}", pattern, e))
+}
+
+// Offset of byte `index` of `text` in UTF-16 code units
+fn utf16_offset(text: &str, index: usize) -> usize {
+	text[..index].encode_utf16().count()
+}
+
+impl Filter {
+	fn compile(query: &LogQuery) -> Result<Self, String> {
+		let search = match query.search.as_deref() {
+			Some(search) if !search.is_empty() => {
+				let pattern = if query.regex {
+					search.to_string()
+				} else {
+					regex::escape(search)
+				};
+				Some(build_regex(&pattern, query.case_sensitive)?)
+			}
+			_ => None,
+		};
+		let mut columns = Vec::new();
+		for predicate in &query.columns {
+			let expected = predicate.value.to_lowercase();
+			let test = match predicate.op {
+				ColumnOp::Equals => ColumnTest::Equals(expected),
+				ColumnOp::NotEquals => ColumnTest::NotEquals(expected),
+				ColumnOp::Contains => ColumnTest::Contains(expected),
+				ColumnOp::Regex => ColumnTest::Regex(build_regex(&predicate.value, false)?),
+			};
+			columns.push((predicate.column, test));
+		}
+		Ok(Filter {
+			levels: query
+				.levels
+				.iter()
+				.map(|level| level.to_lowercase())
+				.collect(),
+			from: parse_bound(&query.from)?,
+			to: parse_bound(&query.to)?,
+			search,
+			machine: query
+				.machine
+				.as_deref()
+				.map(str::trim)
+				.filter(|machine| !machine.is_empty())
+				.map(str::to_lowercase),
+			columns,
+		})
+	}
+
+	// The search highlights when the row passes, `None` when it does not
+	fn check(&self, columns: &[String], fields: &LogFields) -> Option<Vec<Highlight>> {
+		if !self.levels.is_empty() {
+			let level = fields.level.as_deref()?.to_lowercase();
+			if !self.levels.contains(&level) {
+				return None;
+			}
+		}
+		if self.from.is_some() || self.to.is_some() {
+			let time = fields.time?;
+			if self.from.is_some_and(|from| time < from) || self.to.is_some_and(|to| time > to) {
+				return None;
+			}
+		}
+		if let Some(machine) = &self.machine {
+			if fields.machine.as_deref()?.to_lowercase() != *machine {
+				return None;
+			}
+		}
+		for (column, test) in &self.columns {
+			if !test.passes(columns.get(*column).map(String::as_str).unwrap_or("")) {
+				return None;
+			}
+		}
+
+		let Some(search) = &self.search else {
+			return Some(Vec::new());
+		};
+		let highlights: Vec<Highlight> = columns
+			.iter()
+			.enumerate()
+			.flat_map(|(column, text)| {
+				search
+					.find_iter(text)
+					.filter(|found| !found.is_empty())
+					.map(move |found| Highlight {
+						column,
+						start: utf16_offset(text, found.start()),
+						end: utf16_offset(text, found.end()),
+					})
+			})
+			.collect();
+		if highlights.is_empty() {
+			None
+		} else {
+			Some(highlights)
+		}
+	}
+}
+
+/// Scans the rows of a log session and returns those passing `query`, with their line numbers
+/// and where the search matched. Only the part of the file indexed so far is scanned, off the
+/// main thread.
+#[command]
+pub async fn query_log(
+	app_handle: AppHandle,
+	session_id: u64,
+	query: LogQuery,
+) -> Result<LogQueryResult, String> {
+	tauri::async_runtime::spawn_blocking(move || scan(&app_handle, session_id, &query))
+		.await
+		.map_err(|e| e.to_string())?
+}
+
+fn scan(
+	app_handle: &AppHandle,
+	session_id: u64,
+	query: &LogQuery,
+) -> Result<LogQueryResult, String> {
+	let filter = Filter::compile(query)?;
+	let limit = match query.limit {
+		0 => MAX_MATCHES,
+		limit => limit.min(MAX_MATCHES),
+	};
+
+	let mut result = LogQueryResult {
+		matches: Vec::new(),
+		total_matches: 0,
+		scanned_rows: 0,
+		complete: false,
+	};
+	loop {
+		let page = read_rows(app_handle, session_id, result.scanned_rows, SCAN_PAGE)?;
+		let rows = page.rows.len();
+		for (i, (columns, fields)) in page.rows.into_iter().zip(page.fields).enumerate() {
+			let Some(highlights) = filter.check(&columns, &fields) else {
+				continue;
+			};
+			result.total_matches += 1;
+			if result.total_matches > query.offset && result.matches.len() < limit {
+				result.matches.push(LogMatch {
+					row: page.start + i,
+					line: page.line_numbers.get(i).copied().unwrap_or_default(),
+					columns,
+					fields,
+					highlights,
+				});
+			}
+		}
+		result.scanned_rows += rows;
+		if rows == 0 || result.scanned_rows >= page.total_rows {
+			result.complete = page.complete && result.scanned_rows >= page.total_rows;
+			break;
+		}
+	}
+	Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn row(columns: &[&str]) -> Vec<String> {
+		columns.iter().map(|column| column.to_string()).collect()
+	}
+
+	fn fields(level: &str, timestamp: &str, machine: &str) -> LogFields {
+		LogFields {
+			level: Some(level.to_string()),
+			time: DateTime::parse_from_rfc3339(timestamp).ok(),
+			machine: Some(machine.to_string()),
+			..LogFields::default()
+		}
+	}
+
+	fn passes(query: &LogQuery, columns: &[&str], fields: &LogFields) -> bool {
+		Filter::compile(query)
+			.unwrap()
+			.check(&row(columns), fields)
+			.is_some()
+	}
+
+	#[test]
+	fn levels_and_machine_ignore_case() {
+		let query = LogQuery {
+			levels: vec!["ERROR".to_string(), "warn".to_string()],
+			machine: Some(" PC-01 ".to_string()),
+			..LogQuery::default()
+		};
+		let row = ["x"];
+		assert!(passes(
+			&query,
+			&row,
+			&fields("Error", "2024-05-01T10:00:00Z", "pc-01")
+		));
+		assert!(passes(
+			&query,
+			&row,
+			&fields("WARN", "2024-05-01T10:00:00Z", "PC-01")
+		));
+		assert!(!passes(
+			&query,
+			&row,
+			&fields("INFO", "2024-05-01T10:00:00Z", "pc-01")
+		));
+		assert!(!passes(
+			&query,
+			&row,
+			&fields("ERROR", "2024-05-01T10:00:00Z", "pc-02")
+		));
+		assert!(!passes(&query, &row, &LogFields::default()));
+	}
+
+	#[test]
+	fn time_bounds_are_inclusive_and_need_a_timestamp() {
+		let query = LogQuery {
+			from: Some("2024-05-01T10:00:00Z".to_string()),
+			to: Some("2024-05-01T12:00:00+02:00".to_string()),
+			..LogQuery::default()
+		};
+		let row = ["x"];
+		assert!(passes(
+			&query,
+			&row,
+			&fields("INFO", "2024-05-01T10:00:00Z", "pc")
+		));
+		assert!(passes(
+			&query,
+			&row,
+			&fields("INFO", "2024-05-01T07:00:00-03:00", "pc")
+		));
+		assert!(!passes(
+			&query,
+			&row,
+			&fields("INFO", "2024-05-01T10:00:01Z", "pc")
+		));
+		assert!(!passes(
+			&query,
+			&row,
+			&fields("INFO", "2024-05-01T09:59:59Z", "pc")
+		));
+		assert!(!passes(&query, &row, &LogFields::default()));
+	}
+
+	#[test]
+	fn column_predicates_compare_one_column() {
+		let predicate = |op, value: &str| LogQuery {
+			columns: vec![ColumnPredicate {
+				column: 1,
+				op,
+				value: value.to_string(),
+			}],
+			..LogQuery::default()
+		};
+		let row = ["2024-05-01", "Facturas Mensuales", "ok"];
+		let any = LogFields::default();
+		assert!(passes(
+			&predicate(ColumnOp::Equals, "facturas mensuales"),
+			&row,
+			&any
+		));
+		assert!(!passes(
+			&predicate(ColumnOp::NotEquals, "FACTURAS MENSUALES"),
+			&row,
+			&any
+		));
+		assert!(passes(
+			&predicate(ColumnOp::Contains, "MENSUAL"),
+			&row,
+			&any
+		));
+		assert!(passes(&predicate(ColumnOp::Regex, "^factura"), &row, &any));
+		// A missing column reads as empty
+		let short = ["2024-05-01"];
+		assert!(passes(&predicate(ColumnOp::Equals, ""), &short, &any));
+	}
+
+	#[test]
+	fn search_highlights_count_utf16_code_units() {
+		let query = LogQuery {
+			search: Some("error".to_string()),
+			..LogQuery::default()
+		};
+		let filter = Filter::compile(&query).unwrap();
+		let highlights = filter
+			.check(&row(&["INFO", "Añ😀 Error y error"]), &LogFields::default())
+			.unwrap();
+		let found: Vec<(usize, usize, usize)> = highlights
+			.iter()
+			.map(|highlight| (highlight.column, highlight.start, highlight.end))
+			.collect();
+		assert_eq!(found, [(1, 5, 10), (1, 13, 18)]);
+		assert!(filter
+			.check(&row(&["INFO", "todo bien"]), &LogFields::default())
+			.is_none());
+	}
+
+	#[test]
+	fn plain_searches_are_escaped_and_can_match_case() {
+		let query = LogQuery {
+			search: Some("a.b".to_string()),
+			case_sensitive: true,
+			..LogQuery::default()
+		};
+		let any = LogFields::default();
+		assert!(passes(&query, &["x a.b"], &any));
+		assert!(!passes(&query, &["x axb"], &any));
+		assert!(!passes(&query, &["x A.B"], &any));
+
+		let query = LogQuery {
+			search: Some("a.b".to_string()),
+			regex: true,
+			..LogQuery::default()
+		};
+		assert!(passes(&query, &["x AXB"], &any));
+	}
+
+	#[test]
+	fn invalid_expressions_and_dates_are_reported() {
+		let regex = LogQuery {
+			search: Some("(".to_string()),
+			regex: true,
+			..LogQuery::default()
+		};
+		assert!(Filter::compile(&regex).is_err());
+		let date = LogQuery {
+			from: Some("ayer".to_string()),
+			..LogQuery::default()
+		};
+		assert!(Filter::compile(&date).is_err());
+	}
+}
@@ -0,0 +1,548 @@
+use encoding_rs::Encoding;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager};
+
+use crate::process::encoding;
+use crate::process::log_formats::{LogFields, LogFormat};
+use crate::process::logs::file_encoding;
+use crate::process::project_settings::project_log_format;
+
+// Bytes read at a time while indexing
+const READ_CHUNK: usize = 1024 * 1024;
+// Largest page a single call returns
+const MAX_PAGE_ROWS: usize = 5000;
+// Progress events are at least this far apart
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+// Records between two offsets kept in the index; pages are read from the closest one
+const CHECKPOINT_STRIDE: usize = 256;
+// Sessions open at once; opening another closes the least recently used
+const MAX_SESSIONS: usize = 8;
+// Sessions not read for this long are closed when another one opens
+const SESSION_IDLE: Duration = Duration::from_secs(10 * 60);
+
+// Where a record starts
+#[derive(Debug, Clone, Copy)]
+struct Checkpoint {
+	offset: u64,
+	// Number (from 1) of the record's first line in the file
+	line: u64,
+}
+
+#[derive(Default)]
+struct RecordIndex {
+	// Start of every `CHECKPOINT_STRIDE`th record, from the first
+	checkpoints: Vec<Checkpoint>,
+	rows: usize,
+	lines_read: u64,
+	// End of the indexed part of the file, where the last record ends
+	end: u64,
+	// Last column of the first record, the machine that wrote the log
+	maquina: Option<String>,
+	done: bool,
+	error: Option<String>,
+}
+
+struct LogSession {
+	path: PathBuf,
+	encoding: &'static Encoding,
+	format: LogFormat,
+	index: Mutex<RecordIndex>,
+	closed: AtomicBool,
+	last_used: Mutex<Instant>,
+}
+
+#[derive(Default)]
+struct Sessions {
+	next_id: u64,
+	open: HashMap<u64, Arc<LogSession>>,
+}
+
+impl Sessions {
+	// Closes the sessions left unread, and the least recently used past the cap, so one more
+	// fits
+	fn make_room(&mut self) {
+		let now = Instant::now();
+		let mut by_use: Vec<(Instant, u64)> = self
+			.open
+			.iter()
+			.map(|(id, session)| (*session.last_used.lock().unwrap(), *id))
+			.collect();
+		by_use.sort();
+		let excess = (by_use.len() + 1).saturating_sub(MAX_SESSIONS);
+		for (i, (used, id)) in by_use.into_iter().enumerate() {
+			if i >= excess && now.duration_since(used) < SESSION_IDLE {
+				continue;
+			}
+			if let Some(session) = self.open.remove(&id) {
+				session.closed.store(true, Ordering::Relaxed);
+				println!("Log Session {}: Closed, unused", id);
+			}
+		}
+	}
+}
+
+/// Log files opened for paged reading, by session id.
+#[derive(Default)]
+pub struct LogSessions(Mutex<Sessions>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogSessionInfo {
+	pub session_id: u64,
+	pub encoding: String,
+	pub total_bytes: u64,
+}
+
+/// Sent as `log-session-progress` while the file is indexed.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogSessionProgress {
+	pub session_id: u64,
+	pub bytes_read: u64,
+	pub total_bytes: u64,
+	pub rows: usize,
+	pub done: bool,
+	pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogPage {
+	pub start: usize,
+	pub rows: Vec<Vec<String>>,
+	/// Fields of each row, as the project's format profile extracts them
+	pub fields: Vec<LogFields>,
+	/// Line of the file each row starts at, from 1
+	pub line_numbers: Vec<u64>,
+	/// Rows indexed so far; final once `complete`
+	pub total_rows: usize,
+	pub complete: bool,
+	pub maquina: Option<String>,
+}
+
+impl LogSession {
+	// Length of a byte order mark matching the session's encoding, which is not part of any row
+	fn bom_len(&self, file: &mut File) -> io::Result<u64> {
+		let mut head = [0u8; 3];
+		let read = file.read(&mut head)?;
+		file.seek(SeekFrom::Start(0))?;
+		Ok(match Encoding::for_bom(&head[..read]) {
+			Some((encoding, len)) if encoding == self.encoding => len as u64,
+			_ => 0,
+		})
+	}
+
+	// Text of one line without its line break
+	fn decode_line(&self, line: &[u8]) -> String {
+		let line = line
+			.strip_suffix(encoding::newline(self.encoding))
+			.unwrap_or(line);
+		let line = line
+			.strip_suffix(encoding::carriage_return(self.encoding))
+			.unwrap_or(line);
+		encoding::decode(self.encoding, line)
+	}
+
+	// Records the starts of the records in `lines`, `offset` being where the first one begins
+	fn index_lines(&self, lines: &[&[u8]], mut offset: u64) {
+		let mut index = self.index.lock().unwrap();
+		for line in lines {
+			let text = self.decode_line(line);
+			index.lines_read += 1;
+			if self.format.starts_record(&text) {
+				if index.rows == 0 {
+					index.maquina = self.format.fields(&text).machine;
+				}
+				if index.rows.is_multiple_of(CHECKPOINT_STRIDE) {
+					let line = index.lines_read;
+					index.checkpoints.push(Checkpoint { offset, line });
+				}
+				index.rows += 1;
+			}
+			// Lines before the first record belong to none and are left out
+			offset += line.len() as u64;
+			if index.rows > 0 {
+				index.end = offset;
+			}
+		}
+	}
+
+	fn progress(&self, session_id: u64, bytes_read: u64, total_bytes: u64) -> LogSessionProgress {
+		let index = self.index.lock().unwrap();
+		LogSessionProgress {
+			session_id,
+			bytes_read,
+			total_bytes,
+			rows: index.rows,
+			done: index.done,
+			error: index.error.clone(),
+		}
+	}
+
+	// Reads the file once, recording where every `CHECKPOINT_STRIDE`th record starts
+	fn build_index(&self, app_handle: &AppHandle, session_id: u64) -> io::Result<()> {
+		let mut file = File::open(&self.path)?;
+		let total_bytes = file.metadata()?.len();
+		let mut offset = self.bom_len(&mut file)?;
+		file.seek(SeekFrom::Start(offset))?;
+
+		let mut chunk = vec![0u8; READ_CHUNK];
+		let mut pending: Vec<u8> = Vec::new();
+		let mut last_progress = Instant::now();
+		loop {
+			if self.closed.load(Ordering::Relaxed) {
+				return Ok(());
+			}
+			let read = file.read(&mut chunk)?;
+			if read == 0 {
+				// The last line may lack its line break
+				if !pending.is_empty() {
+					self.index_lines(&[&pending], offset);
+				}
+				break;
+			}
+			pending.extend_from_slice(&chunk[..read]);
+			let lines = encoding::complete_lines(self.encoding, &pending);
+			let consumed: usize = lines.iter().map(|line| line.len()).sum();
+			self.index_lines(&lines, offset);
+			offset += consumed as u64;
+			pending.drain(..consumed);
+
+			if last_progress.elapsed() >= PROGRESS_INTERVAL {
+				last_progress = Instant::now();
+				let progress = self.progress(session_id, offset, total_bytes);
+				let _ = app_handle.emit("log-session-progress", &progress);
+			}
+		}
+		println!(
+			"Log Session {}: {} indexed, {} records",
+			session_id,
+			self.path.display(),
+			self.index.lock().unwrap().rows
+		);
+		Ok(())
+	}
+
+	fn read_page(&self, start: usize, count: usize) -> Result<LogPage, String> {
+		let (from, to, first_line, skip, total_rows, complete, maquina) = {
+			let index = self.index.lock().unwrap();
+			let last = start.saturating_add(count).min(index.rows);
+			// Reading starts at the checkpoint before `start` and ends at the one after `last`
+			let first = index.checkpoints.get(start / CHECKPOINT_STRIDE);
+			let from = first.map_or(index.end, |checkpoint| checkpoint.offset);
+			let to = match index.checkpoints.get(last.div_ceil(CHECKPOINT_STRIDE)) {
+				Some(checkpoint) if last < index.rows => checkpoint.offset,
+				_ => index.end,
+			};
+			(
+				from,
+				to,
+				first.map_or(0, |checkpoint| checkpoint.line),
+				start % CHECKPOINT_STRIDE,
+				index.rows,
+				index.done,
+				index.maquina.clone(),
+			)
+		};
+		let mut page = LogPage {
+			start,
+			rows: Vec::new(),
+			fields: Vec::new(),
+			line_numbers: Vec::new(),
+			total_rows,
+			complete,
+			maquina,
+		};
+		if from >= to || start >= total_rows {
+			return Ok(page);
+		}
+
+		let mut file =
+			File::open(&self.path).map_err(|e| format!("Error abriendo archivo: {}", e))?;
+		file.seek(SeekFrom::Start(from))
+			.map_err(|e| format!("Error leyendo archivo: {}", e))?;
+		let mut bytes = Vec::new();
+		file.take(to - from)
+			.read_to_end(&mut bytes)
+			.map_err(|e| format!("Error leyendo archivo: {}", e))?;
+
+		let mut lines = encoding::complete_lines(self.encoding, &bytes);
+		let consumed: usize = lines.iter().map(|line| line.len()).sum();
+		if consumed < bytes.len() {
+			lines.push(&bytes[consumed..]);
+		}
+		// The range starts at a record, so every line either starts one or continues the last
+		let mut records: Vec<(u64, String)> = Vec::new();
+		for (line_number, line) in (first_line..).zip(lines) {
+			let text = self.decode_line(line);
+			match records.last_mut() {
+				Some((_, record)) if !self.format.starts_record(&text) => {
+					record.push('\n');
+					record.push_str(&text);
+				}
+				_ => records.push((line_number, text)),
+			}
+		}
+		let (line_numbers, records): (Vec<u64>, Vec<String>) = records
+			.into_iter()
+			.skip(skip)
+			.take(count.min(total_rows - start))
+			.unzip();
+		page.line_numbers = line_numbers;
+		page.fields = records
+			.iter()
+			.map(|record| self.format.fields(record))
+			.collect();
+		page.rows = records
+			.iter()
+			.zip(&page.fields)
+			.map(|(record, fields)| self.format.columns(record, fields))
+			.collect();
+		Ok(page)
+	}
+}
+
+fn session(app_handle: &AppHandle, session_id: u64) -> Result<Arc<LogSession>, String> {
+	let sessions = app_handle.state::<LogSessions>();
+	let sessions = sessions.0.lock().unwrap();
+	let session = sessions
+		.open
+		.get(&session_id)
+		.cloned()
+		.ok_or_else(|| format!("La sesión de log {} no existe", session_id))?;
+	*session.last_used.lock().unwrap() = Instant::now();
+	Ok(session)
+}
+
+/// Opens `path` for paged reading and indexes it in the background, reporting
+/// `log-session-progress` events. Rows can be fetched while indexing goes on.
+#[command]
+pub fn open_log_session(
+	app_handle: AppHandle,
+	path: String,
+	delimiter: String,
+) -> Result<LogSessionInfo, String> {
+	let path = PathBuf::from(path);
+	let total_bytes = path
+		.metadata()
+		.map_err(|e| format!("Error abriendo archivo: {}", e))?
+		.len();
+	let session = Arc::new(LogSession {
+		encoding: file_encoding(&app_handle, &path),
+		format: project_log_format(&app_handle, &path, Some(&delimiter)),
+		path,
+		index: Mutex::new(RecordIndex::default()),
+		closed: AtomicBool::new(false),
+		last_used: Mutex::new(Instant::now()),
+	});
+	let session_id = {
+		let sessions = app_handle.state::<LogSessions>();
+		let mut sessions = sessions.0.lock().unwrap();
+		sessions.make_room();
+		sessions.next_id += 1;
+		let session_id = sessions.next_id;
+		sessions.open.insert(session_id, session.clone());
+		session_id
+	};
+	println!(
+		"Log Session {}: Opening {} ({})",
+		session_id,
+		session.path.display(),
+		session.encoding.name()
+	);
+
+	let info = LogSessionInfo {
+		session_id,
+		encoding: session.encoding.name().to_string(),
+		total_bytes,
+	};
+	let app = app_handle.clone();
+	tauri::async_runtime::spawn_blocking(move || {
+		let result = session.build_index(&app, session_id);
+		{
+			let mut index = session.index.lock().unwrap();
+			index.done = true;
+			if let Err(e) = result {
+				eprintln!("Log Session {}: Indexing failed: {}", session_id, e);
+				index.error = Some(format!("Error indexando el archivo: {}", e));
+			}
+		}
+		if !session.closed.load(Ordering::Relaxed) {
+			let progress = session.progress(session_id, total_bytes, total_bytes);
+			let _ = app.emit("log-session-progress", &progress);
+		}
+	});
+	Ok(info)
+}
+
+/// Rows `start..start + count` of a session (records, continuation lines included), as far as
+/// indexed.
+pub fn read_rows(
+	app_handle: &AppHandle,
+	session_id: u64,
+	start: usize,
+	count: usize,
+) -> Result<LogPage, String> {
+	session(app_handle, session_id)?.read_page(start, count)
+}
+
+// Reads the file, so it runs off the main thread
+#[command]
+pub async fn get_log_rows(
+	app_handle: AppHandle,
+	session_id: u64,
+	start: usize,
+	count: usize,
+) -> Result<LogPage, String> {
+	tauri::async_runtime::spawn_blocking(move || {
+		read_rows(&app_handle, session_id, start, count.min(MAX_PAGE_ROWS))
+	})
+	.await
+	.map_err(|e| e.to_string())?
+}
+
+/// Stops indexing and forgets the session.
+#[command]
+pub fn close_log_session(app_handle: AppHandle, session_id: u64) {
+	let sessions = app_handle.state::<LogSessions>();
+	let removed = sessions.0.lock().unwrap().open.remove(&session_id);
+	if let Some(session) = removed {
+		session.closed.store(true, Ordering::Relaxed);
+		println!("Log Session {}: Closed", session_id);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use encoding_rs::UTF_8;
+
+	// Writes `contents` to a fresh file and indexes it the way `build_index` does, a few lines
+	// per call
+	fn indexed(name: &str, contents: &[u8]) -> LogSession {
+		let path =
+			std::env::temp_dir().join(format!("log-sessions-{}-{}.log", std::process::id(), name));
+		std::fs::write(&path, contents).unwrap();
+		let session = LogSession {
+			path,
+			encoding: UTF_8,
+			format: LogFormat::default_for("|"),
+			index: Mutex::new(RecordIndex::default()),
+			closed: AtomicBool::new(false),
+			last_used: Mutex::new(Instant::now()),
+		};
+		let mut file = File::open(&session.path).unwrap();
+		let mut offset = session.bom_len(&mut file).unwrap();
+		let body = &contents[offset as usize..];
+		let mut lines = encoding::complete_lines(UTF_8, body);
+		let consumed: usize = lines.iter().map(|line| line.len()).sum();
+		if consumed < body.len() {
+			lines.push(&body[consumed..]);
+		}
+		for chunk in lines.chunks(100) {
+			session.index_lines(chunk, offset);
+			offset += chunk.iter().map(|line| line.len() as u64).sum::<u64>();
+		}
+		session.index.lock().unwrap().done = true;
+		session
+	}
+
+	// `count` records, every third one followed by a continuation line, and the line each
+	// record starts at
+	fn records(count: usize) -> (String, Vec<u64>) {
+		let mut contents = String::new();
+		let mut starts = Vec::new();
+		let mut line = 1;
+		for i in 0..count {
+			starts.push(line);
+			contents.push_str(&format!("INFO|2024-05-01 10:00:00|Mensaje {}|PC1\n", i));
+			line += 1;
+			if i % 3 == 0 {
+				contents.push_str(&format!("  detalle {}\n", i));
+				line += 1;
+			}
+		}
+		(contents, starts)
+	}
+
+	fn messages(page: &LogPage) -> Vec<String> {
+		page.rows.iter().map(|row| row[2].clone()).collect()
+	}
+
+	#[test]
+	fn continuation_lines_stay_with_their_record() {
+		let session = indexed(
+			"continuation",
+			b"INFO|2024-05-01 10:00:00|Inicio|PC1\n  detalle\r\nERROR|2024-05-01 10:00:01|Fallo|PC1",
+		);
+		let page = session.read_page(0, 10).unwrap();
+		assert_eq!(page.total_rows, 2);
+		assert!(page.complete);
+		assert_eq!(page.line_numbers, vec![1, 3]);
+		// The line break of a continuation line is dropped, a carriage return included
+		assert_eq!(page.fields[0].message.as_deref(), Some("Inicio\n  detalle"));
+		// The last line has no line break and is still a record
+		assert_eq!(messages(&page)[1], "Fallo");
+	}
+
+	#[test]
+	fn lines_before_the_first_record_and_the_bom_are_left_out() {
+		let mut contents = b"\xEF\xBB\xBFEncabezado del log\n\n".to_vec();
+		contents.extend_from_slice(b"WARN|2024-05-01 10:00:00|Primero|PC7\n");
+		contents.extend_from_slice(b"INFO|2024-05-01 10:00:01|Segundo|PC8\n");
+		let session = indexed("preamble", &contents);
+		{
+			let index = session.index.lock().unwrap();
+			assert_eq!(index.checkpoints[0].offset, 3 + 19 + 1);
+			assert_eq!(index.end, contents.len() as u64);
+		}
+		let page = session.read_page(0, 10).unwrap();
+		assert_eq!(page.line_numbers, vec![3, 4]);
+		assert_eq!(messages(&page), vec!["Primero", "Segundo"]);
+		assert_eq!(page.maquina.as_deref(), Some("PC7"));
+	}
+
+	#[test]
+	fn pages_across_a_checkpoint_keep_their_rows_and_line_numbers() {
+		let (contents, starts) = records(CHECKPOINT_STRIDE * 2 + 10);
+		let session = indexed("checkpoints", contents.as_bytes());
+		assert_eq!(session.index.lock().unwrap().checkpoints.len(), 3);
+
+		for (start, count) in [
+			(CHECKPOINT_STRIDE - 5, 10),
+			(0, CHECKPOINT_STRIDE),
+			(CHECKPOINT_STRIDE, CHECKPOINT_STRIDE),
+			(CHECKPOINT_STRIDE + 1, CHECKPOINT_STRIDE + 2),
+			(CHECKPOINT_STRIDE * 2 - 1, 1),
+		] {
+			let page = session.read_page(start, count).unwrap();
+			let expected: Vec<String> = (start..start + count)
+				.map(|i| format!("Mensaje {}", i))
+				.collect();
+			assert_eq!(messages(&page), expected, "{} + {}", start, count);
+			assert_eq!(page.line_numbers, starts[start..start + count], "{}", start);
+		}
+	}
+
+	#[test]
+	fn pages_past_the_end_are_cut_short_or_empty() {
+		let rows = CHECKPOINT_STRIDE + 10;
+		let (contents, starts) = records(rows);
+		let session = indexed("end", contents.as_bytes());
+
+		let page = session.read_page(rows - 4, 50).unwrap();
+		assert_eq!(page.rows.len(), 4);
+		assert_eq!(page.line_numbers, starts[rows - 4..]);
+		assert_eq!(messages(&page)[3], format!("Mensaje {}", rows - 1));
+
+		for start in [rows, rows + CHECKPOINT_STRIDE] {
+			let page = session.read_page(start, 10).unwrap();
+			assert!(page.rows.is_empty(), "{}", start);
+			assert_eq!(page.total_rows, rows);
+		}
+	}
+}
@@ -1,104 +1,73 @@
-use encoding_rs::WINDOWS_1252;
-use encoding_rs_io::DecodeReaderBytesBuilder;
+use encoding_rs::{Encoding, WINDOWS_1252};
 use regex::Regex;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use tauri::command;
+use std::path::Path;
+use tauri::AppHandle;
 
-#[command]
-pub fn process_log_file(
-	path: String,
-	delimiter: String,
-) -> Result<(Vec<Vec<String>>, Option<String>), String> {
-	println!(
-		"🟡 Iniciando lectura del archivo: {}, delimitador {}",
-		path, delimiter
-	);
-
-	let file = File::open(&path).map_err(|e| format!("❌ Error abriendo archivo: {}", e))?;
-	println!("✅ Archivo abierto correctamente.");
-
-	let reader = BufReader::new(
-		DecodeReaderBytesBuilder::new()
-			.encoding(Some(WINDOWS_1252))
-			.build(file),
-	);
-
-	// Regex para validar si una columna parece ser una fecha (acepta varios formatos)
-	let date_regex = Regex::new(r#"\d{1,4}[-/]\d{1,2}[-/]\d{1,4}"#).unwrap();
-
-	// Regex para detectar niveles de log comunes
-	let level_regex = Regex::new(r#"(?i)^(INFO|ERROR|WARN|WARNING|DEBUG|TRACE|FATAL)$"#).unwrap();
-
-	let mut processed_lines: Vec<String> = Vec::new();
-	let mut line_count = 0;
+use crate::process::encoding;
+use crate::process::project_settings::project_encoding;
 
-	for line_result in reader.lines() {
-		let line = line_result.map_err(|e| e.to_string())?;
-		line_count += 1;
+/// Tells lines that start a log record (a date or a level in one of the first two columns)
+/// from continuation lines such as stack traces, which belong to the previous record.
+pub struct RecordDetector {
+	delimiter: String,
+	date_regex: Regex,
+	level_regex: Regex,
+}
 
-		if line_count <= 5 {
-			println!("📄 Línea {}: {}", line_count, line);
+impl RecordDetector {
+	pub fn new(delimiter: &str) -> Self {
+		RecordDetector {
+			delimiter: delimiter.to_string(),
+			// Regex para validar si una columna parece ser una fecha (acepta varios formatos)
+			date_regex: Regex::new(r#"\d{1,4}[-/]\d{1,2}[-/]\d{1,4}"#).unwrap(),
+			// Regex para detectar niveles de log comunes
+			level_regex: Regex::new(r#"(?i)^(INFO|ERROR|WARN|WARNING|DEBUG|TRACE|FATAL)$"#)
+				.unwrap(),
 		}
-
-		let columns: Vec<&str> = line.split(&delimiter).collect();
-		println!("🔹 Columnas detectadas ({}): {:?}", columns.len(), columns);
-
-		if columns.len() > 1
-			&& (date_regex.is_match(columns[0].trim())
-				|| date_regex.is_match(columns[1].trim())
-				|| level_regex.is_match(columns[0].trim())
-				|| level_regex.is_match(columns[1].trim()))
-		{
-			processed_lines.push(line);
-		} else if let Some(last_line) = processed_lines.last_mut() {
-  				last_line.push('\n');
-  				last_line.push_str(&line);
-  			}
 	}
 
-	println!("📊 Total de líneas procesadas: {}", line_count);
-	println!("🧩 Líneas válidas para procesar: {}", processed_lines.len());
-
-	let escaped_delimiter = regex::escape(&delimiter);
-	let regex = Regex::new(&escaped_delimiter).map_err(|e| format!("Error en regex: {}", e))?;
-	let mut rows = Vec::new();
-	let mut maquina: Option<String> = None;
-
-	for (i, line) in processed_lines.iter().enumerate() {
-		let mut columns: Vec<String> = regex.split(line).map(|s| s.trim().to_string()).collect();
+	pub fn is_date(&self, column: &str) -> bool {
+		self.date_regex.is_match(column)
+	}
 
-		// --- 🔁 Detectar si la primera columna es fecha y la segunda es nivel ---
-		if columns.len() >= 2 {
-			let first_is_date = date_regex.is_match(&columns[0]);
-			let _second_is_date = date_regex.is_match(&columns[1]);
-			let _first_is_level = level_regex.is_match(&columns[0]);
-			let second_is_level = level_regex.is_match(&columns[1]);
+	pub fn is_level(&self, column: &str) -> bool {
+		self.level_regex.is_match(column)
+	}
 
-			// Si la fecha está primero y el nivel después → invertirlas
-			if first_is_date && second_is_level {
-				columns.swap(0, 1);
+	pub fn starts_record(&self, line: &str) -> bool {
+		// The first line of a file may still carry its byte order mark
+		let line = line.trim_start_matches('\u{feff}');
+		let mut columns = line.split(self.delimiter.as_str()).map(str::trim);
+		match (columns.next(), columns.next()) {
+			(Some(first), Some(second)) => {
+				self.is_date(first)
+					|| self.is_date(second)
+					|| self.is_level(first)
+					|| self.is_level(second)
 			}
+			_ => false,
 		}
+	}
 
-		if i < 3 {
-			println!("🔹 Fila {}: {:?}", i + 1, columns);
-		}
+	/// Splits a record into trimmed columns, with the level first when a date precedes it.
+	pub fn columns(&self, record: &str) -> Vec<String> {
+		let mut columns: Vec<String> = record
+			.split(self.delimiter.as_str())
+			.map(|column| column.trim().to_string())
+			.collect();
 
-		if maquina.is_none() {
-			maquina = columns.last().cloned();
+		// --- 🔁 Detectar si la primera columna es fecha y la segunda es nivel ---
+		// Si la fecha está primero y el nivel después → invertirlas
+		if columns.len() >= 2 && self.is_date(&columns[0]) && self.is_level(&columns[1]) {
+			columns.swap(0, 1);
 		}
-
-		rows.push(columns);
-	}
-
-	println!(
-		"✅ Archivo procesado correctamente. Filas finales: {}",
-		rows.len()
-	);
-	if let Some(ref m) = maquina {
-		println!("🖥️ Máquina detectada: {}", m);
+		columns
 	}
+}
 
-	Ok((rows, maquina))
+/// The project's encoding for `path`, else the detected one; plain ASCII reads the same in any.
+pub fn file_encoding(app_handle: &AppHandle, path: &Path) -> &'static Encoding {
+	project_encoding(app_handle, path)
+		.or_else(|| encoding::sniff(path).ok().flatten())
+		.unwrap_or(WINDOWS_1252)
 }
@@ -1,10 +1,33 @@
+pub mod audit;
 pub mod background;
+pub mod batch;
+pub mod debounce;
+pub mod emitter_settings;
+pub mod encoding;
 pub mod excel;
+pub mod file_rules;
 pub mod getpath;
+pub mod keychain;
+pub mod log_formats;
+pub mod log_query;
+pub mod log_sessions;
 pub mod logs;
 pub mod mode;
+pub mod outbox;
+pub mod project_keys;
+pub mod project_paths;
+pub mod project_settings;
+pub mod receiver_settings;
+pub mod records;
+pub mod safe_path;
+pub mod sequencer;
+pub mod snapshot;
+pub mod subscriptions;
 pub mod state_sync;
 pub mod system_info;
+pub mod tail;
+pub mod timestamps;
+pub mod trust;
 pub mod r#try;
 pub mod watch;
 pub mod watch_file;
@@ -1,11 +1,24 @@
 use std::sync::Mutex;
 use serde::{Serialize, Deserialize};
+use tauri::{command, State};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AppMode {
     None,
     Emitter,
     Receiver,
+    // Emitter and Receiver sharing the same MQTT connection
+    Both,
+}
+
+impl AppMode {
+    pub fn is_emitter(self) -> bool {
+        matches!(self, AppMode::Emitter | AppMode::Both)
+    }
+
+    pub fn is_receiver(self) -> bool {
+        matches!(self, AppMode::Receiver | AppMode::Both)
+    }
 }
 
 pub struct AppModeState(pub Mutex<AppMode>);
@@ -15,3 +28,9 @@ impl Default for AppModeState {
         AppModeState(Mutex::new(AppMode::None))
     }
 }
+
+/// Mode currently running; the UI changes it by emitting `app_mode_changed`.
+#[command]
+pub fn get_app_mode(state: State<'_, AppModeState>) -> AppMode {
+    *state.0.lock().unwrap()
+}
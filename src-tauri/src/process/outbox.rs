@@ -0,0 +1,116 @@
+use rumqttc::QoS;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use tokio::time::{timeout, timeout_at};
+
+use crate::db::outbox;
+use crate::db::store::Database;
+use crate::mqtt::service::MqttService;
+use crate::process::emitter_settings::load_emitter_settings;
+
+// Publishes kept in flight at once; stays below the MQTT request queue capacity
+const IN_FLIGHT_WINDOW: usize = 8;
+const ACK_TIMEOUT: Duration = Duration::from_secs(30);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+// Wakes the drainer when a new entry is stored
+#[derive(Default)]
+pub struct OutboxSignal(pub Notify);
+
+/// Stores an outgoing message on disk; the drainer publishes it once the broker is reachable.
+pub fn enqueue(app_handle: &AppHandle, topic: &str, payload: &[u8]) -> Result<i64, String> {
+	let database = app_handle.state::<Database>();
+	let seq = outbox::enqueue(&database.0.lock().unwrap(), topic, payload)
+		.map_err(|e| format!("Error guardando el mensaje en la cola local: {}", e))?;
+	app_handle.state::<OutboxSignal>().0.notify_one();
+	Ok(seq)
+}
+
+/// Drains the outbox in sequence order for as long as the task is alive. Entries are only
+/// removed after the broker acknowledged them.
+pub async fn run_drainer(app_handle: AppHandle) {
+	println!("Outbox: Drainer started.");
+	let mut last_prune: Option<Instant> = None;
+
+	loop {
+		if last_prune.is_none_or(|t| t.elapsed() >= PRUNE_INTERVAL) {
+			prune(&app_handle);
+			last_prune = Some(Instant::now());
+		}
+
+		let made_progress = if app_handle.state::<MqttService>().is_connected() {
+			drain_window(&app_handle).await
+		} else {
+			false
+		};
+
+		if !made_progress {
+			let signal = app_handle.state::<OutboxSignal>();
+			let _ = timeout(IDLE_POLL, signal.0.notified()).await;
+		}
+	}
+}
+
+// Publishes the oldest entries and waits for their acknowledgements. Each entry is removed as
+// soon as its own acknowledgement arrives, whatever the order; those still unacknowledged when
+// `ACK_TIMEOUT` runs out stay queued and are the only ones published again on the next pass
+async fn drain_window(app_handle: &AppHandle) -> bool {
+	let database = app_handle.state::<Database>();
+	let entries = match outbox::oldest(&database.0.lock().unwrap(), IN_FLIGHT_WINDOW) {
+		Ok(entries) => entries,
+		Err(e) => {
+			eprintln!("Outbox: Failed to read pending entries: {}", e);
+			return false;
+		}
+	};
+	if entries.is_empty() {
+		return false;
+	}
+
+	let service = app_handle.state::<MqttService>();
+	let mut in_flight = Vec::with_capacity(entries.len());
+	for entry in entries {
+		match service.publish_confirmed(&entry.topic, entry.payload, QoS::AtLeastOnce) {
+			Ok(ack) => in_flight.push((entry.seq, entry.topic, ack)),
+			Err(e) => {
+				eprintln!("Outbox: {}", e);
+				break;
+			}
+		}
+	}
+
+	let deadline = tokio::time::Instant::now() + ACK_TIMEOUT;
+	let mut acknowledged = 0;
+	for (seq, topic, ack) in in_flight {
+		// An acknowledgement that already arrived is taken even once the deadline has passed
+		if let Ok(Ok(())) = timeout_at(deadline, ack).await {
+			if let Err(e) = outbox::remove(&database.0.lock().unwrap(), seq) {
+				eprintln!("Outbox: Failed to remove entry {}: {}", seq, e);
+			}
+			println!("✅ Publicado MQTT (#{}): {}", seq, topic);
+			acknowledged += 1;
+		}
+	}
+	acknowledged > 0
+}
+
+fn prune(app_handle: &AppHandle) {
+	let limits = load_emitter_settings(app_handle).outbox;
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	match outbox::prune(&connection, limits.max_messages, limits.max_age_secs) {
+		Ok(0) => {}
+		Ok(removed) => eprintln!(
+			"Outbox: Discarded {} entries over the configured limits.",
+			removed
+		),
+		Err(e) => eprintln!("Outbox: Failed to prune: {}", e),
+	}
+	if let Ok(pending) = outbox::count(&connection) {
+		if pending > 0 {
+			println!("Outbox: {} entries waiting for the broker.", pending);
+		}
+	}
+}
@@ -0,0 +1,152 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Manager};
+
+use crate::db::keys;
+use crate::db::store::Database;
+use crate::mqtt::crypto::{self, ProjectKey};
+use crate::process::keychain;
+
+// Name of a project's key in the OS credential store
+fn keychain_name(project: &str) -> String {
+	format!("project-key:{}", project)
+}
+
+/// Keys read from the credential store, by project; `None` for projects sent in plaintext.
+#[derive(Default)]
+pub struct ProjectKeys(Mutex<HashMap<String, Option<ProjectKey>>>);
+
+/// Key configured for `project`, if its traffic is encrypted.
+pub fn project_key(app_handle: &AppHandle, project: &str) -> Result<Option<ProjectKey>, String> {
+	let cache = app_handle.state::<ProjectKeys>();
+	if let Some(key) = cache.0.lock().unwrap().get(project) {
+		return Ok(*key);
+	}
+
+	let encrypted = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		keys::contains(&connection, project)
+			.map_err(|e| format!("Error leyendo la clave del proyecto '{}': {}", project, e))?
+	};
+	let key = if encrypted {
+		let stored = keychain::get(&keychain_name(project))?.ok_or_else(|| {
+			format!(
+				"La clave del proyecto '{}' no está en el almacén de credenciales",
+				project
+			)
+		})?;
+		Some(crypto::key_from_bytes(&stored)?)
+	} else {
+		None
+	};
+	cache.0.lock().unwrap().insert(project.to_string(), key);
+	Ok(key)
+}
+
+/// Key to open an incoming payload of `project`. Once a project has a key, plaintext
+/// payloads are refused so a peer without the key cannot inject lines.
+pub fn key_for_incoming(
+	app_handle: &AppHandle,
+	project: &str,
+	encrypted: bool,
+) -> Result<Option<ProjectKey>, String> {
+	match (project_key(app_handle, project)?, encrypted) {
+		(Some(key), true) => Ok(Some(key)),
+		(None, false) => Ok(None),
+		(Some(_), false) => Err(format!(
+			"El proyecto '{}' requiere cifrado y el mensaje llegó sin cifrar",
+			project
+		)),
+		(None, true) => Err(format!(
+			"El mensaje de '{}' está cifrado y no hay una clave configurada para el proyecto",
+			project
+		)),
+	}
+}
+
+fn store_key(app_handle: &AppHandle, project_name: &str, key: &ProjectKey) -> Result<(), String> {
+	if project_name.trim().is_empty() {
+		return Err("El nombre del proyecto no puede estar vacío".to_string());
+	}
+	keychain::set(&keychain_name(project_name), key)?;
+	{
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		keys::add(&connection, project_name)
+			.map_err(|e| format!("Error guardando la clave del proyecto: {}", e))?;
+	}
+	app_handle
+		.state::<ProjectKeys>()
+		.0
+		.lock()
+		.unwrap()
+		.insert(project_name.to_string(), Some(*key));
+	Ok(())
+}
+
+/// Creates a new random key for the project and returns it (base64) so it can be shared
+/// with the other side.
+#[command]
+pub fn generate_project_key(app_handle: AppHandle, project_name: String) -> Result<String, String> {
+	let key = crypto::generate_key();
+	store_key(&app_handle, &project_name, &key)?;
+	println!(
+		"Encryption: New key generated for project '{}'",
+		project_name
+	);
+	Ok(BASE64.encode(key))
+}
+
+#[command]
+pub fn import_project_key(
+	app_handle: AppHandle,
+	project_name: String,
+	key: String,
+) -> Result<(), String> {
+	let bytes = BASE64
+		.decode(key.trim())
+		.map_err(|_| "La clave no es base64 válido".to_string())?;
+	store_key(&app_handle, &project_name, &crypto::key_from_bytes(&bytes)?)?;
+	println!("Encryption: Key imported for project '{}'", project_name);
+	Ok(())
+}
+
+#[command]
+pub fn export_project_key(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<Option<String>, String> {
+	Ok(project_key(&app_handle, &project_name)?.map(|key| BASE64.encode(key)))
+}
+
+/// Stops encrypting the project's traffic.
+#[command]
+pub fn remove_project_key(app_handle: AppHandle, project_name: String) -> Result<bool, String> {
+	let removed = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		keys::remove(&connection, &project_name)
+			.map_err(|e| format!("Error eliminando la clave del proyecto: {}", e))?
+	};
+	keychain::delete(&keychain_name(&project_name))?;
+	app_handle
+		.state::<ProjectKeys>()
+		.0
+		.lock()
+		.unwrap()
+		.remove(&project_name);
+	if removed {
+		println!("Encryption: Key removed for project '{}'", project_name);
+	}
+	Ok(removed)
+}
+
+#[command]
+pub fn list_encrypted_projects(app_handle: AppHandle) -> Result<Vec<String>, String> {
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	keys::projects(&connection).map_err(|e| e.to_string())
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Manager};
+
+use crate::db::store::Database;
+use crate::db::{projects, trust};
+use crate::process::receiver_settings::load_receiver_settings;
+use crate::process::safe_path::resolve_in_project;
+
+// The frontend edits the proyects table directly, so entries also expire on their own in case
+// an invalidation is missed
+const FOUND_TTL: Duration = Duration::from_secs(60);
+// Kept short so a project registered after its first message is picked up quickly
+const MISSING_TTL: Duration = Duration::from_secs(5);
+
+struct CachedPath {
+	root: Option<PathBuf>,
+	looked_up: Instant,
+}
+
+/// Cached name → local folder lookups for the receiver.
+#[derive(Default)]
+pub struct ProjectPaths(Mutex<HashMap<String, CachedPath>>);
+
+impl ProjectPaths {
+	pub fn clear(&self) {
+		self.0.lock().unwrap().clear();
+	}
+}
+
+// Same normalization as the TRIM(LOWER(name)) match in the database
+fn cache_key(project_name: &str) -> String {
+	project_name.trim().to_lowercase()
+}
+
+/// Local folder of `project_name`, or `None` if no project with that name is registered.
+pub fn project_root(app_handle: &AppHandle, project_name: &str) -> Result<Option<PathBuf>, String> {
+	let cache = app_handle.state::<ProjectPaths>();
+	let key = cache_key(project_name);
+	if let Some(cached) = cache.0.lock().unwrap().get(&key) {
+		let ttl = if cached.root.is_some() {
+			FOUND_TTL
+		} else {
+			MISSING_TTL
+		};
+		if cached.looked_up.elapsed() < ttl {
+			return Ok(cached.root.clone());
+		}
+	}
+
+	let database = app_handle.state::<Database>();
+	let stored = projects::find_path(&database.0.lock().unwrap(), project_name)
+		.map_err(|e| format!("Error buscando el proyecto '{}': {}", project_name, e))?;
+	let root = stored.map(PathBuf::from);
+	remember(&cache, key, root.clone());
+	Ok(root)
+}
+
+fn remember(cache: &ProjectPaths, key: String, root: Option<PathBuf>) {
+	cache.0.lock().unwrap().insert(
+		key,
+		CachedPath {
+			root,
+			looked_up: Instant::now(),
+		},
+	);
+}
+
+/// Outcome of `create_project`.
+pub enum AutoCreated {
+	/// Auto-creation is disabled
+	Disabled,
+	/// Registered meanwhile, e.g. from the UI
+	Existing(PathBuf),
+	Created(PathBuf),
+}
+
+/// Registers an unknown project under the receiver root when auto-creation is enabled and
+/// the user added `signer` to the project's trust list beforehand; a signer pinned on first
+/// use, or none (lines released later), creates nothing.
+pub fn create_project(
+	app_handle: &AppHandle,
+	project_name: &str,
+	signer: Option<&str>,
+) -> Result<AutoCreated, String> {
+	let settings = load_receiver_settings(app_handle);
+	if !settings.auto_create_projects {
+		return Ok(AutoCreated::Disabled);
+	}
+	let explicit = match signer {
+		Some(signer) => {
+			let database = app_handle.state::<Database>();
+			let connection = database.0.lock().unwrap();
+			trust::trusted_explicitly(&connection, project_name, signer)
+				.map_err(|e| format!("Error leyendo los emisores de confianza: {}", e))?
+		}
+		None => false,
+	};
+	if !explicit {
+		return Err(format!(
+			"Solo un emisor agregado por el usuario al proyecto '{}' puede crearlo",
+			project_name
+		));
+	}
+	let receiver_root = settings.receiver_root.ok_or_else(|| {
+		"La creación automática de proyectos requiere una carpeta raíz del receptor".to_string()
+	})?;
+	// The name comes from the topic, so it must be a single plain folder name
+	let root = resolve_in_project(Path::new(&receiver_root), project_name.trim())?;
+
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	let existing = projects::find_path(&connection, project_name)
+		.map_err(|e| format!("Error buscando el proyecto '{}': {}", project_name, e))?;
+	let created = match existing {
+		Some(existing) => AutoCreated::Existing(PathBuf::from(existing)),
+		None => {
+			fs::create_dir_all(&root)
+				.map_err(|e| format!("No se pudo crear la carpeta {}: {}", root.display(), e))?;
+			projects::insert(
+				&connection,
+				project_name.trim(),
+				&root.to_string_lossy(),
+				&settings.default_separator,
+			)
+			.map_err(|e| format!("Error registrando el proyecto '{}': {}", project_name, e))?;
+			AutoCreated::Created(root)
+		}
+	};
+	drop(connection);
+
+	if let AutoCreated::Existing(root) | AutoCreated::Created(root) = &created {
+		remember(
+			&app_handle.state::<ProjectPaths>(),
+			cache_key(project_name),
+			Some(root.clone()),
+		);
+	}
+	Ok(created)
+}
+
+/// Called by the frontend after it adds, renames or removes projects.
+#[command]
+pub fn invalidate_project_paths(app_handle: AppHandle) {
+	app_handle.state::<ProjectPaths>().clear();
+}
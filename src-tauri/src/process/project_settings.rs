@@ -0,0 +1,405 @@
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{command, AppHandle, Manager};
+
+use crate::db::projects;
+use crate::db::store::Database;
+use crate::process::encoding;
+use crate::process::file_rules::{EmitterFileFilter, FileFilter, FileRules};
+use crate::process::log_formats::{builtin_profiles, FormatProfile, LogFormat, DEFAULT_PROFILE};
+use crate::process::timestamps::{TimestampParser, TimestampSettings};
+use crate::ActiveProjectPath;
+
+/// What the receiver does when an emitter reports a file as removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletePolicy {
+	/// Remove the local copy too
+	Mirror,
+	/// Keep the local copy untouched
+	Ignore,
+	/// Move the local copy to the archive folder, stamped with the time
+	#[default]
+	Archive,
+}
+
+/// How a project's trust list takes in signers it does not know yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustPolicy {
+	/// Only signers added by the user are accepted
+	#[default]
+	Strict,
+	/// While the list is empty, the first signer with a valid signature is pinned in it. Any
+	/// client of the broker may be that signer, so the user turns this on per project
+	FirstUse,
+}
+
+// Plain enums are stored as their serde name, anything else as JSON text
+fn read_setting<T: for<'de> Deserialize<'de> + Default>(
+	app_handle: &AppHandle,
+	project_name: &str,
+	column: &str,
+) -> Result<T, String> {
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	let stored = projects::setting(&connection, project_name, column)
+		.map_err(|e| format!("Error leyendo la configuración del proyecto: {}", e))?
+		.ok_or_else(|| format!("El proyecto '{}' no existe", project_name))?;
+	match stored {
+		Some(value) => serde_json::from_str(&value)
+			.or_else(|_| serde_json::from_value(serde_json::Value::String(value)))
+			.map_err(|e| format!("Valor inválido en la columna {}: {}", column, e)),
+		None => Ok(T::default()),
+	}
+}
+
+fn write_setting<T: Serialize>(
+	app_handle: &AppHandle,
+	project_name: &str,
+	column: &str,
+	value: &T,
+) -> Result<(), String> {
+	let value = match serde_json::to_value(value).map_err(|e| e.to_string())? {
+		serde_json::Value::String(value) => value,
+		other => other.to_string(),
+	};
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	let updated = projects::set_setting(&connection, project_name, column, Some(&value))
+		.map_err(|e| format!("Error guardando la configuración del proyecto: {}", e))?;
+	if updated {
+		Ok(())
+	} else {
+		Err(format!("El proyecto '{}' no existe", project_name))
+	}
+}
+
+pub fn delete_policy(app_handle: &AppHandle, project_name: &str) -> Result<DeletePolicy, String> {
+	read_setting(app_handle, project_name, "delete_policy")
+}
+
+#[command]
+pub fn get_project_delete_policy(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<DeletePolicy, String> {
+	delete_policy(&app_handle, &project_name)
+}
+
+#[command]
+pub fn set_project_delete_policy(
+	app_handle: AppHandle,
+	project_name: String,
+	policy: DeletePolicy,
+) -> Result<(), String> {
+	write_setting(&app_handle, &project_name, "delete_policy", &policy)?;
+	println!(
+		"Receiver: Delete policy of '{}' set to {:?}",
+		project_name, policy
+	);
+	Ok(())
+}
+
+/// Trust policy of the project; the strict default for projects not created yet, so only
+/// signers the user added can get a project created.
+pub fn trust_policy(app_handle: &AppHandle, project_name: &str) -> TrustPolicy {
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	projects::setting(&connection, project_name, "trust_policy")
+		.ok()
+		.flatten()
+		.flatten()
+		.and_then(|value| serde_json::from_value(serde_json::Value::String(value)).ok())
+		.unwrap_or_default()
+}
+
+#[command]
+pub fn get_project_trust_policy(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<TrustPolicy, String> {
+	read_setting(&app_handle, &project_name, "trust_policy")
+}
+
+#[command]
+pub fn set_project_trust_policy(
+	app_handle: AppHandle,
+	project_name: String,
+	policy: TrustPolicy,
+) -> Result<(), String> {
+	write_setting(&app_handle, &project_name, "trust_policy", &policy)?;
+	println!(
+		"Signing: Trust policy of '{}' set to {:?}",
+		project_name, policy
+	);
+	Ok(())
+}
+
+/// Compiled file rules of the project registered at `root`; the defaults when the folder is
+/// not a registered project or its rules cannot be read.
+pub fn emitter_file_filter(app_handle: &AppHandle, root: &Path) -> FileFilter {
+	let name = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		projects::find_name(&connection, &root.to_string_lossy())
+	};
+	let rules = match name {
+		Ok(Some(name)) => read_setting(app_handle, &name, "file_rules"),
+		Ok(None) => Ok(FileRules::default()),
+		Err(e) => Err(e.to_string()),
+	};
+	rules
+		.and_then(|rules| FileFilter::compile(&rules))
+		.unwrap_or_else(|e| {
+			eprintln!("File Watcher: {}. Using the default file rules.", e);
+			FileFilter::default()
+		})
+}
+
+#[command]
+pub fn get_project_file_rules(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<FileRules, String> {
+	read_setting(&app_handle, &project_name, "file_rules")
+}
+
+/// Saves the rules; the emitter applies them at once if it is watching this project.
+#[command]
+pub fn set_project_file_rules(
+	app_handle: AppHandle,
+	project_name: String,
+	rules: FileRules,
+) -> Result<(), String> {
+	let filter = FileFilter::compile(&rules)?;
+	write_setting(&app_handle, &project_name, "file_rules", &rules)?;
+
+	let project_path = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		projects::find_path(&connection, &project_name).map_err(|e| e.to_string())?
+	};
+	let active_path = app_handle.state::<ActiveProjectPath>();
+	let watched = active_path.0.lock().unwrap().clone();
+	if watched.is_some() && watched == project_path.map(Into::into) {
+		*app_handle.state::<EmitterFileFilter>().0.lock().unwrap() = filter;
+		println!("File Watcher: File rules of '{}' updated", project_name);
+	}
+	Ok(())
+}
+
+/// Encoding forced for the project holding `path`; `None` when its files are detected one by
+/// one.
+pub fn project_encoding(app_handle: &AppHandle, path: &Path) -> Option<&'static Encoding> {
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	let name = projects::find_containing(&connection, path).ok()??;
+	let label = projects::setting(&connection, &name, "encoding").ok()??;
+	match encoding::for_label(&label?) {
+		Ok(encoding) => Some(encoding),
+		Err(e) => {
+			eprintln!("{}. Detecting the encoding of '{}' instead.", e, name);
+			None
+		}
+	}
+}
+
+#[command]
+pub fn get_project_encoding(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<Option<String>, String> {
+	read_setting(&app_handle, &project_name, "encoding")
+}
+
+/// Forces the encoding of the project's files, or restores detection with `None`. The emitter
+/// applies it the next time it starts watching the project.
+#[command]
+pub fn set_project_encoding(
+	app_handle: AppHandle,
+	project_name: String,
+	encoding: Option<String>,
+) -> Result<(), String> {
+	let name = match encoding.as_deref().map(str::trim) {
+		Some(label) if !label.is_empty() => Some(encoding::for_label(label)?.name()),
+		_ => None,
+	};
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	let updated = projects::set_setting(&connection, &project_name, "encoding", name)
+		.map_err(|e| format!("Error guardando la configuración del proyecto: {}", e))?;
+	if !updated {
+		return Err(format!("El proyecto '{}' no existe", project_name));
+	}
+	println!(
+		"Project: Encoding of '{}' set to {}",
+		project_name,
+		name.unwrap_or("auto")
+	);
+	Ok(())
+}
+
+// Separator assumed for delimited profiles when the project has none
+const DEFAULT_SEPARATOR: &str = "|";
+
+// Built-in profiles followed by the project's own
+fn log_formats(app_handle: &AppHandle, project_name: &str) -> Result<Vec<FormatProfile>, String> {
+	let mut profiles = builtin_profiles();
+	profiles.extend(read_setting::<Vec<FormatProfile>>(
+		app_handle,
+		project_name,
+		"log_formats",
+	)?);
+	Ok(profiles)
+}
+
+fn selected_log_format(
+	app_handle: &AppHandle,
+	project_name: &str,
+) -> Result<FormatProfile, String> {
+	let selected = read_setting::<Option<String>>(app_handle, project_name, "log_format")?;
+	let selected = selected.as_deref().unwrap_or(DEFAULT_PROFILE);
+	log_formats(app_handle, project_name)?
+		.into_iter()
+		.find(|profile| profile.name == selected)
+		.ok_or_else(|| format!("El perfil de formato '{}' no existe", selected))
+}
+
+/// Compiled format profile of the project holding `path`. Delimited profiles without their own
+/// delimiter use `separator`, else the project's. Falls back to the default profile.
+pub fn project_log_format(
+	app_handle: &AppHandle,
+	path: &Path,
+	separator: Option<&str>,
+) -> LogFormat {
+	let (name, project_separator) = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		let name = projects::find_containing(&connection, path).ok().flatten();
+		let project_separator = name.as_deref().and_then(|name| {
+			projects::setting(&connection, name, "separator")
+				.ok()
+				.flatten()
+				.flatten()
+		});
+		(name, project_separator)
+	};
+	let separator = separator
+		.or(project_separator.as_deref())
+		.filter(|separator| !separator.is_empty())
+		.unwrap_or(DEFAULT_SEPARATOR);
+
+	// Files outside every project are read with the default profile
+	let Some(name) = name else {
+		return LogFormat::default_for(separator);
+	};
+	let format = selected_log_format(app_handle, &name)
+		.and_then(|profile| LogFormat::compile(&profile, separator))
+		.unwrap_or_else(|e| {
+			eprintln!("Log Format: {}. Using '{}'.", e, DEFAULT_PROFILE);
+			LogFormat::default_for(separator)
+		});
+	match read_setting(app_handle, &name, "timestamps")
+		.and_then(|settings| TimestampParser::new(&settings))
+	{
+		Ok(timestamps) => format.with_timestamps(timestamps),
+		Err(e) => {
+			eprintln!("Log Format: {}. Using the default timestamp formats.", e);
+			format
+		}
+	}
+}
+
+/// Built-in profiles followed by the project's own.
+#[command]
+pub fn get_log_formats(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<Vec<FormatProfile>, String> {
+	log_formats(&app_handle, &project_name)
+}
+
+/// Replaces the project's own profiles. Names must be unique and differ from the built-in ones.
+#[command]
+pub fn set_project_log_formats(
+	app_handle: AppHandle,
+	project_name: String,
+	profiles: Vec<FormatProfile>,
+) -> Result<(), String> {
+	let builtin = builtin_profiles();
+	for (i, profile) in profiles.iter().enumerate() {
+		if profile.name.trim().is_empty() {
+			return Err("El perfil de formato necesita un nombre".to_string());
+		}
+		let taken = builtin.iter().chain(&profiles[..i]).any(|other| other.name == profile.name);
+		if taken {
+			return Err(format!("El perfil de formato '{}' ya existe", profile.name));
+		}
+		LogFormat::compile(profile, DEFAULT_SEPARATOR)?;
+	}
+	write_setting(&app_handle, &project_name, "log_formats", &profiles)?;
+	println!(
+		"Log Format: '{}' has {} custom profiles",
+		project_name,
+		profiles.len()
+	);
+	Ok(())
+}
+
+/// Name of the profile the project's logs are read with.
+#[command]
+pub fn get_project_log_format(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<String, String> {
+	let selected = read_setting::<Option<String>>(&app_handle, &project_name, "log_format")?;
+	Ok(selected.unwrap_or_else(|| DEFAULT_PROFILE.to_string()))
+}
+
+/// Selects the profile the project's logs are read with. Views opened from now on and the
+/// emitter, when it next starts, use it.
+#[command]
+pub fn select_project_log_format(
+	app_handle: AppHandle,
+	project_name: String,
+	name: String,
+) -> Result<(), String> {
+	if !log_formats(&app_handle, &project_name)?
+		.iter()
+		.any(|profile| profile.name == name)
+	{
+		return Err(format!("El perfil de formato '{}' no existe", name));
+	}
+	write_setting(&app_handle, &project_name, "log_format", &name)?;
+	println!("Log Format: '{}' reads its logs as '{}'", project_name, name);
+	Ok(())
+}
+
+#[command]
+pub fn get_project_timestamps(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<TimestampSettings, String> {
+	read_setting(&app_handle, &project_name, "timestamps")
+}
+
+/// Sets the formats and zone the project's timestamps are read with; views opened from now on
+/// use them.
+#[command]
+pub fn set_project_timestamps(
+	app_handle: AppHandle,
+	project_name: String,
+	settings: TimestampSettings,
+) -> Result<(), String> {
+	TimestampParser::new(&settings)?;
+	write_setting(&app_handle, &project_name, "timestamps", &settings)?;
+	println!(
+		"Log Format: '{}' reads timestamps with {} formats ({})",
+		project_name,
+		settings.formats.len(),
+		settings.timezone
+	);
+	Ok(())
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{command, AppHandle};
+
+use crate::settings;
+
+const RECEIVER_SETTINGS_FILE: &str = "receiver_settings.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReceiverSettings {
+	/// Create a local project for messages of projects not registered here
+	pub auto_create_projects: bool,
+	/// Folder under which auto-created projects get their own folder
+	pub receiver_root: Option<String>,
+	/// Log separator stored with auto-created projects
+	pub default_separator: String,
+}
+
+impl Default for ReceiverSettings {
+	fn default() -> Self {
+		ReceiverSettings {
+			auto_create_projects: false,
+			receiver_root: None,
+			default_separator: "|".to_string(),
+		}
+	}
+}
+
+pub fn load_receiver_settings(app_handle: &AppHandle) -> ReceiverSettings {
+	settings::load_json(app_handle, RECEIVER_SETTINGS_FILE).unwrap_or_else(|e| {
+		eprintln!("MQTT Receiver: {}. Using default settings.", e);
+		ReceiverSettings::default()
+	})
+}
+
+#[command]
+pub fn get_receiver_settings(app_handle: AppHandle) -> ReceiverSettings {
+	load_receiver_settings(&app_handle)
+}
+
+#[command]
+pub fn update_receiver_settings(
+	app_handle: AppHandle,
+	new_settings: ReceiverSettings,
+) -> Result<(), String> {
+	match new_settings.receiver_root.as_deref() {
+		Some(root) if !Path::new(root).is_absolute() => {
+			return Err(format!(
+				"La carpeta raíz del receptor debe ser una ruta absoluta: '{}'",
+				root
+			));
+		}
+		None if new_settings.auto_create_projects => {
+			return Err(
+				"Configure la carpeta raíz del receptor para crear proyectos automáticamente"
+					.to_string(),
+			);
+		}
+		_ => {}
+	}
+	if new_settings.default_separator.is_empty() {
+		return Err("El separador por defecto no puede estar vacío".to_string());
+	}
+	settings::save_json(&app_handle, RECEIVER_SETTINGS_FILE, &new_settings)
+}
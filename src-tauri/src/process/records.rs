@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::mqtt::envelope::EventKind;
+use crate::process::log_formats::LogFormat;
+use crate::process::tail::TailLine;
+
+struct OpenRecord {
+	kind: EventKind,
+	// Physical lines joined with their own terminators; the offset and newline are the last
+	// one's
+	line: TailLine,
+	updated: Instant,
+}
+
+/// Joins continuation lines (stack traces, ...) to the record they belong to, so the emitter
+/// publishes whole records. The last record of a file is held until the next one starts or
+/// the file stays quiet for `idle`. A record never grows past `max_bytes`; the line that
+/// would make it longer starts another one.
+pub struct RecordAssembler {
+	format: LogFormat,
+	idle: Duration,
+	max_bytes: usize,
+	open: HashMap<PathBuf, OpenRecord>,
+}
+
+impl RecordAssembler {
+	pub fn new(format: LogFormat, idle: Duration, max_bytes: usize) -> Self {
+		RecordAssembler {
+			format,
+			idle,
+			max_bytes,
+			open: HashMap::new(),
+		}
+	}
+
+	/// Takes lines read from `path` and returns the records they completed, in order, with
+	/// the kind of event that started each one.
+	pub fn push(
+		&mut self,
+		path: &Path,
+		kind: EventKind,
+		lines: Vec<TailLine>,
+	) -> Vec<(EventKind, TailLine)> {
+		let mut complete = Vec::new();
+		for line in lines {
+			match self.open.get_mut(path) {
+				Some(record)
+					if !self.format.starts_record(&line.content)
+						&& record.line.content.len()
+							+ record.line.newline.len()
+							+ line.content.len() <= self.max_bytes =>
+				{
+					record.line.content.push_str(record.line.newline);
+					record.line.content.push_str(&line.content);
+					record.line.end_offset = line.end_offset;
+					record.line.newline = line.newline;
+					// The earlier lines were ASCII if the file's encoding was detected since
+					record.line.encoding = line.encoding;
+					record.updated = Instant::now();
+				}
+				// A new record, or a continuation with nothing to attach it to or too long to
+				// attach
+				_ => {
+					let previous = self.open.insert(
+						path.to_path_buf(),
+						OpenRecord {
+							kind,
+							line,
+							updated: Instant::now(),
+						},
+					);
+					complete.extend(previous.map(|record| (record.kind, record.line)));
+				}
+			}
+		}
+		complete
+	}
+
+	/// Closes the record held for `path`, e.g. before its removal is published.
+	pub fn take(&mut self, path: &Path) -> Option<(EventKind, TailLine)> {
+		self.open
+			.remove(path)
+			.map(|record| (record.kind, record.line))
+	}
+
+	pub fn next_due(&self) -> Option<Instant> {
+		self.open
+			.values()
+			.map(|record| record.updated + self.idle)
+			.min()
+	}
+
+	/// Closes the records whose file stayed quiet long enough.
+	pub fn take_due(&mut self, now: Instant) -> Vec<(PathBuf, EventKind, TailLine)> {
+		let due: Vec<PathBuf> = self
+			.open
+			.iter()
+			.filter(|(_, record)| record.updated + self.idle <= now)
+			.map(|(path, _)| path.clone())
+			.collect();
+		due.into_iter()
+			.filter_map(|path| {
+				let (kind, line) = self.take(&path)?;
+				Some((path, kind, line))
+			})
+			.collect()
+	}
+
+	/// Closes every record, e.g. when the watcher stops.
+	pub fn drain(&mut self) -> Vec<(PathBuf, EventKind, TailLine)> {
+		self.open
+			.drain()
+			.map(|(path, record)| (path, record.kind, record.line))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use encoding_rs::UTF_8;
+
+	const IDLE: Duration = Duration::from_millis(500);
+
+	fn assembler() -> RecordAssembler {
+		RecordAssembler::new(LogFormat::default_for("|"), IDLE, 1024)
+	}
+
+	// Lines ending at consecutive offsets, as the tailer reads them
+	fn lines(contents: &[(&str, &'static str)]) -> Vec<TailLine> {
+		let mut end_offset = 0;
+		contents
+			.iter()
+			.map(|(content, newline)| {
+				end_offset += (content.len() + newline.len()) as u64;
+				TailLine {
+					content: content.to_string(),
+					end_offset,
+					newline,
+					encoding: UTF_8,
+				}
+			})
+			.collect()
+	}
+
+	#[test]
+	fn continuation_lines_join_the_previous_record() {
+		let mut assembler = assembler();
+		let path = Path::new("app.log");
+		let read = lines(&[
+			("2024-05-01 10:00:00|ERROR|Falló la descarga", "\r\n"),
+			("   at Descargar()", "\r\n"),
+			("   at Main()", "\n"),
+			("2024-05-01 10:00:05|INFO|Reintento", "\n"),
+		]);
+		let last_continuation = read[2].end_offset;
+
+		let complete = assembler.push(path, EventKind::Created, read);
+		assert_eq!(complete.len(), 1);
+		let (kind, record) = &complete[0];
+		assert_eq!(*kind, EventKind::Created);
+		assert_eq!(
+			record.content,
+			"2024-05-01 10:00:00|ERROR|Falló la descarga\r\n   at Descargar()\r\n   at Main()"
+		);
+		assert_eq!(record.end_offset, last_continuation);
+		assert_eq!(record.newline, "\n");
+
+		// The record that started last is held until the next one or a flush
+		let (_, held) = assembler.take(path).unwrap();
+		assert_eq!(held.content, "2024-05-01 10:00:05|INFO|Reintento");
+		assert!(assembler.take(path).is_none());
+	}
+
+	#[test]
+	fn a_first_line_that_is_not_a_record_stands_on_its_own() {
+		let mut assembler = assembler();
+		let path = Path::new("app.log");
+		let complete = assembler.push(
+			path,
+			EventKind::Modified,
+			lines(&[
+				("   at Main()", "\n"),
+				("   at Program()", "\n"),
+				("2024-05-01 10:00:00|INFO|Inicio", "\n"),
+			]),
+		);
+		let contents: Vec<&str> = complete
+			.iter()
+			.map(|(_, record)| record.content.as_str())
+			.collect();
+		assert_eq!(contents, ["   at Main()\n   at Program()"]);
+	}
+
+	#[test]
+	fn records_are_split_before_they_grow_past_the_limit() {
+		let mut assembler = RecordAssembler::new(LogFormat::default_for("|"), IDLE, 40);
+		let path = Path::new("app.log");
+		let complete = assembler.push(
+			path,
+			EventKind::Modified,
+			lines(&[
+				("2024-05-01 10:00:00|ERROR|Falló", "\n"),
+				("   at Descargar()", "\n"),
+				("   at Main()", "\n"),
+			]),
+		);
+		let contents: Vec<&str> = complete
+			.iter()
+			.map(|(_, record)| record.content.as_str())
+			.collect();
+		assert_eq!(contents, ["2024-05-01 10:00:00|ERROR|Falló"]);
+		let (_, held) = assembler.take(path).unwrap();
+		assert_eq!(held.content, "   at Descargar()\n   at Main()");
+	}
+
+	#[test]
+	fn quiet_files_are_flushed_after_the_idle_time() {
+		let mut assembler = assembler();
+		assembler.push(
+			Path::new("a.log"),
+			EventKind::Modified,
+			lines(&[("2024-05-01 10:00:00|INFO|a", "\n")]),
+		);
+		let due = assembler.next_due().unwrap();
+		assert!(assembler.take_due(due - IDLE / 2).is_empty());
+
+		let flushed = assembler.take_due(due);
+		assert_eq!(flushed.len(), 1);
+		assert_eq!(flushed[0].0, PathBuf::from("a.log"));
+		assert_eq!(flushed[0].2.content, "2024-05-01 10:00:00|INFO|a");
+		assert!(assembler.next_due().is_none());
+	}
+
+	#[test]
+	fn closing_flushes_every_file() {
+		let mut assembler = assembler();
+		for path in ["a.log", "b.log"] {
+			assembler.push(
+				Path::new(path),
+				EventKind::Created,
+				lines(&[("2024-05-01 10:00:00|INFO|x", "\n"), ("   detalle", "\n")]),
+			);
+		}
+		let mut drained: Vec<(PathBuf, String)> = assembler
+			.drain()
+			.into_iter()
+			.map(|(path, _, record)| (path, record.content))
+			.collect();
+		drained.sort();
+		let record = "2024-05-01 10:00:00|INFO|x\n   detalle".to_string();
+		assert_eq!(
+			drained,
+			[
+				(PathBuf::from("a.log"), record.clone()),
+				(PathBuf::from("b.log"), record)
+			]
+		);
+		assert!(assembler.next_due().is_none());
+	}
+}
@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Names Windows maps to devices in every directory, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+	"CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+	"COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Resolves a '/'-separated path received from a peer against the project `root`.
+///
+/// Only plain relative paths are accepted: no absolute paths, drive letters, `.`/`..`
+/// components, backslashes, reserved device names or symbolic links on the way, so the
+/// result always stays inside `root`.
+pub fn resolve_in_project(root: &Path, relative: &str) -> Result<PathBuf, String> {
+	if relative.is_empty() {
+		return Err("La ruta está vacía".to_string());
+	}
+	if relative.starts_with('/') {
+		return Err(format!("Ruta absoluta no permitida: '{}'", relative));
+	}
+	if let Some(c) = relative
+		.chars()
+		.find(|c| matches!(c, '\\' | ':' | '\0') || c.is_control())
+	{
+		return Err(format!(
+			"Carácter no permitido {:?} en la ruta '{}'",
+			c, relative
+		));
+	}
+
+	let mut target = root.to_path_buf();
+	for component in relative.split('/') {
+		check_component(component)
+			.map_err(|e| format!("Ruta '{}' no permitida: {}", relative, e))?;
+		target.push(component);
+	}
+
+	check_no_links(root, &target)?;
+	Ok(target)
+}
+
+fn check_component(component: &str) -> Result<(), String> {
+	match component {
+		"" => return Err("contiene un segmento vacío".to_string()),
+		"." | ".." => return Err("contiene '.' o '..'".to_string()),
+		_ => {}
+	}
+	// Windows drops trailing dots and spaces, which would alias another name
+	if component.ends_with('.') || component.ends_with(' ') {
+		return Err(format!("'{}' termina en punto o espacio", component));
+	}
+	let stem = component.split('.').next().unwrap_or(component).trim_end();
+	if RESERVED_NAMES
+		.iter()
+		.any(|name| name.eq_ignore_ascii_case(stem))
+	{
+		return Err(format!(
+			"'{}' es un nombre reservado del sistema",
+			component
+		));
+	}
+	Ok(())
+}
+
+// Rejects symbolic links between the root and the target, so writes cannot be redirected
+// outside the project through a link created in it
+fn check_no_links(root: &Path, target: &Path) -> Result<(), String> {
+	if !root.exists() {
+		// Created on the first write; nothing below it can be a link yet
+		return Ok(());
+	}
+	let canonical_root = fs::canonicalize(root).map_err(|e| {
+		format!(
+			"No se pudo resolver la carpeta del proyecto {}: {}",
+			root.display(),
+			e
+		)
+	})?;
+
+	let relative = target.strip_prefix(root).unwrap_or(target);
+	let mut current = root.to_path_buf();
+	for component in relative.components() {
+		current.push(component);
+		match fs::symlink_metadata(&current) {
+			Ok(metadata) if metadata.file_type().is_symlink() => {
+				return Err(format!(
+					"La ruta {} atraviesa un enlace simbólico",
+					current.display()
+				));
+			}
+			Ok(_) => {}
+			// Nothing exists from here on, so nothing else can be a link
+			Err(_) => break,
+		}
+	}
+
+	// Junctions and similar redirections are not reported as symlinks on every platform
+	let existing = target.ancestors().find(|p| p.exists()).unwrap_or(root);
+	match fs::canonicalize(existing) {
+		Ok(canonical) if canonical.starts_with(&canonical_root) => Ok(()),
+		Ok(canonical) => Err(format!(
+			"La ruta {} sale de la carpeta del proyecto",
+			canonical.display()
+		)),
+		Err(e) => Err(format!("No se pudo resolver {}: {}", existing.display(), e)),
+	}
+}
+
+/// Whether `a` and `b` are the same folder or one contains the other, after resolving links.
+pub fn overlaps(a: &Path, b: &Path) -> bool {
+	let a = fs::canonicalize(a).unwrap_or_else(|_| a.to_path_buf());
+	let b = fs::canonicalize(b).unwrap_or_else(|_| b.to_path_buf());
+	a.starts_with(&b) || b.starts_with(&a)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// A fresh folder per test, so tests running in parallel do not see each other's files
+	fn temp_root(name: &str) -> PathBuf {
+		let root = std::env::temp_dir().join(format!("safe-path-{}-{}", std::process::id(), name));
+		let _ = fs::remove_dir_all(&root);
+		fs::create_dir_all(&root).unwrap();
+		root
+	}
+
+	#[test]
+	fn plain_relative_paths_stay_under_the_root() {
+		let root = Path::new("/missing/project");
+		assert_eq!(
+			resolve_in_project(root, "logs/2024/app.log").unwrap(),
+			root.join("logs").join("2024").join("app.log")
+		);
+	}
+
+	#[test]
+	fn parent_and_current_components_are_rejected() {
+		let root = Path::new("/missing/project");
+		for relative in [
+			"../outside.log",
+			"logs/../../outside.log",
+			"logs/./app.log",
+			"..",
+		] {
+			assert!(resolve_in_project(root, relative).is_err(), "{}", relative);
+		}
+	}
+
+	#[test]
+	fn absolute_and_drive_paths_are_rejected() {
+		let root = Path::new("/missing/project");
+		for relative in ["/etc/passwd", "C:/Windows/win.ini", "logs\\..\\app.log", ""] {
+			assert!(resolve_in_project(root, relative).is_err(), "{}", relative);
+		}
+	}
+
+	#[test]
+	fn empty_segments_and_control_characters_are_rejected() {
+		let root = Path::new("/missing/project");
+		for relative in ["logs//app.log", "logs/", "logs/app\0.log", "logs/app\n.log"] {
+			assert!(
+				resolve_in_project(root, relative).is_err(),
+				"{:?}",
+				relative
+			);
+		}
+	}
+
+	#[test]
+	fn reserved_device_names_are_rejected_with_any_extension_or_case() {
+		let root = Path::new("/missing/project");
+		for relative in [
+			"CON",
+			"logs/nul.txt",
+			"aux.log.1",
+			"logs/Com1",
+			"LPT9.tar.gz",
+		] {
+			assert!(resolve_in_project(root, relative).is_err(), "{}", relative);
+		}
+		// Only the whole stem is reserved
+		assert!(resolve_in_project(root, "logs/console.log").is_ok());
+		assert!(resolve_in_project(root, "COM10.log").is_ok());
+	}
+
+	#[test]
+	fn trailing_dots_and_spaces_are_rejected() {
+		let root = Path::new("/missing/project");
+		for relative in ["app.log.", "logs /app.log", "logs/app.log "] {
+			assert!(
+				resolve_in_project(root, relative).is_err(),
+				"{:?}",
+				relative
+			);
+		}
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn symbolic_links_inside_the_project_are_rejected() {
+		let root = temp_root("links");
+		let outside = temp_root("links-outside");
+		std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+		std::os::unix::fs::symlink(outside.join("app.log"), root.join("app.log")).unwrap();
+		fs::create_dir_all(root.join("logs")).unwrap();
+
+		assert!(resolve_in_project(&root, "escape/app.log").is_err());
+		assert!(resolve_in_project(&root, "app.log").is_err());
+		assert!(resolve_in_project(&root, "logs/new.log").is_ok());
+
+		let _ = fs::remove_dir_all(&root);
+		let _ = fs::remove_dir_all(&outside);
+	}
+
+	#[test]
+	fn nested_and_aliased_folders_overlap() {
+		let root = temp_root("overlaps");
+		fs::create_dir_all(root.join("emitter").join("logs")).unwrap();
+		fs::create_dir_all(root.join("receiver")).unwrap();
+
+		let emitter = root.join("emitter");
+		assert!(overlaps(&emitter, &emitter));
+		assert!(overlaps(&emitter.join("logs"), &emitter));
+		assert!(overlaps(&root, &emitter));
+		assert!(!overlaps(&root.join("receiver"), &emitter));
+		// "emitter-copy" starts with the same text but is another folder
+		assert!(!overlaps(&root.join("emitter-copy"), &emitter));
+		#[cfg(unix)]
+		{
+			std::os::unix::fs::symlink(&emitter, root.join("alias")).unwrap();
+			assert!(overlaps(&root.join("alias"), &emitter));
+		}
+
+		let _ = fs::remove_dir_all(&root);
+	}
+}
@@ -0,0 +1,355 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::mqtt::envelope::LogEnvelope;
+
+// How long a missing line is waited for before it is reported and skipped
+const GAP_TIMEOUT: Duration = Duration::from_secs(10);
+// Lines held behind a gap; past this the gap is skipped right away
+const MAX_HELD: usize = 1000;
+// Message ids remembered per stream to catch redeliveries
+const RECENT_IDS: usize = 256;
+
+/// Lines missing from a stream, reported to the UI as `log-gap`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GapReport {
+	pub host_id: String,
+	pub project: String,
+	pub path: String,
+	pub first_missing: u64,
+	pub last_missing: u64,
+}
+
+#[derive(Default)]
+pub struct Delivery {
+	/// Lines to apply, in sequence order
+	pub ready: Vec<LogEnvelope>,
+	pub gaps: Vec<GapReport>,
+}
+
+struct Stream {
+	next: u64,
+	// Epoch of the lines being ordered, and those the emitter left behind
+	epoch: Option<String>,
+	retired_epochs: HashSet<String>,
+	held: BTreeMap<u64, LogEnvelope>,
+	gap_since: Option<Instant>,
+	// Project root the held lines will be written under
+	root: PathBuf,
+	recent_ids: VecDeque<String>,
+	recent_set: HashSet<String>,
+}
+
+impl Stream {
+	// Returns false if the message id was already seen
+	fn remember(&mut self, message_id: &str) -> bool {
+		if !self.recent_set.insert(message_id.to_string()) {
+			return false;
+		}
+		self.recent_ids.push_back(message_id.to_string());
+		if self.recent_ids.len() > RECENT_IDS {
+			if let Some(oldest) = self.recent_ids.pop_front() {
+				self.recent_set.remove(&oldest);
+			}
+		}
+		true
+	}
+
+	fn drain(&mut self, delivery: &mut Delivery) {
+		while let Some(envelope) = self.held.remove(&self.next) {
+			delivery.ready.push(envelope);
+			self.next += 1;
+		}
+		if self.held.is_empty() {
+			self.gap_since = None;
+		}
+	}
+
+	// Gives up on the missing lines before the first held one
+	fn skip_gap(&mut self, delivery: &mut Delivery) {
+		let Some((&first_held, envelope)) = self.held.iter().next() else {
+			return;
+		};
+		let gap = GapReport {
+			host_id: envelope.host_id.clone(),
+			project: envelope.project.clone(),
+			path: envelope.path.clone(),
+			first_missing: self.next,
+			last_missing: first_held - 1,
+		};
+		eprintln!(
+			"Sequencer: Lines {}-{} of {}/{} never arrived.",
+			gap.first_missing, gap.last_missing, gap.project, gap.path
+		);
+		delivery.gaps.push(gap);
+		self.next = first_held;
+		self.gap_since = None;
+		self.drain(delivery);
+	}
+
+	// Starts over at line 1 of `epoch`; lines held from the previous one are released first
+	fn restart(&mut self, epoch: Option<String>, delivery: &mut Delivery) {
+		while !self.held.is_empty() {
+			self.skip_gap(delivery);
+		}
+		if let Some(previous) = self.epoch.take() {
+			self.retired_epochs.insert(previous);
+		}
+		self.epoch = epoch;
+		self.next = 1;
+		self.gap_since = None;
+	}
+}
+
+/// Orders the incoming lines of every (emitter, file) stream by their sequence number.
+#[derive(Default)]
+pub struct Sequencer(Mutex<HashMap<String, Stream>>);
+
+impl Sequencer {
+	pub fn clear(&self) {
+		self.0.lock().unwrap().clear();
+	}
+
+	/// Takes one line and returns those that can be applied now. Duplicates are dropped and
+	/// lines after a gap are held until the gap fills or times out.
+	pub fn accept(&self, envelope: LogEnvelope, root: &Path) -> Delivery {
+		let mut delivery = Delivery::default();
+		// Upgraded payloads carry no sequence
+		if envelope.sequence == 0 {
+			delivery.ready.push(envelope);
+			return delivery;
+		}
+
+		let key = format!("{}/{}", envelope.host_id, envelope.mqtt_path());
+		let mut streams = self.0.lock().unwrap();
+		// Earlier lines of a stream seen for the first time are covered by the snapshot
+		let stream = streams.entry(key).or_insert_with(|| Stream {
+			next: envelope.sequence,
+			epoch: envelope.epoch.clone(),
+			retired_epochs: HashSet::new(),
+			held: BTreeMap::new(),
+			gap_since: None,
+			root: root.to_path_buf(),
+			recent_ids: VecDeque::new(),
+			recent_set: HashSet::new(),
+		});
+		stream.root = root.to_path_buf();
+
+		if !stream.remember(&envelope.message_id) {
+			println!(
+				"Sequencer: Dropping redelivered message {} of {}.",
+				envelope.message_id,
+				envelope.mqtt_path()
+			);
+			return delivery;
+		}
+
+		let sequence = envelope.sequence;
+		match &envelope.epoch {
+			Some(epoch) if stream.retired_epochs.contains(epoch) => {
+				println!(
+					"Sequencer: Dropping line {} of {} from a previous epoch.",
+					sequence,
+					envelope.mqtt_path()
+				);
+				return delivery;
+			}
+			Some(epoch) if stream.epoch.as_ref() != Some(epoch) => {
+				// The emitter lost its counters (reinstalled or database reset)
+				println!(
+					"Sequencer: {} restarted its sequence.",
+					envelope.mqtt_path()
+				);
+				stream.restart(Some(epoch.clone()), &mut delivery);
+			}
+			Some(_) => {}
+			// Older emitters send no epoch; line 1 after later ones is taken as a restart
+			None if sequence == 1 && stream.next > 1 => {
+				println!(
+					"Sequencer: {} restarted its sequence.",
+					envelope.mqtt_path()
+				);
+				stream.restart(None, &mut delivery);
+			}
+			None => {}
+		}
+
+		if sequence < stream.next {
+			println!(
+				"Sequencer: Dropping duplicate line {} of {}.",
+				sequence,
+				envelope.mqtt_path()
+			);
+		} else if sequence > stream.next {
+			stream.held.insert(sequence, envelope);
+			stream.gap_since.get_or_insert_with(Instant::now);
+			if stream.held.len() > MAX_HELD {
+				stream.skip_gap(&mut delivery);
+			}
+		} else {
+			delivery.ready.push(envelope);
+			stream.next += 1;
+			stream.drain(&mut delivery);
+		}
+		delivery
+	}
+
+	/// Skips gaps that outlived the timeout. Returns the released lines with the project root
+	/// they belong under.
+	pub fn expire(&self) -> Vec<(PathBuf, Delivery)> {
+		let mut streams = self.0.lock().unwrap();
+		let mut released = Vec::new();
+		for stream in streams.values_mut() {
+			if stream
+				.gap_since
+				.is_some_and(|since| since.elapsed() >= GAP_TIMEOUT)
+			{
+				let mut delivery = Delivery::default();
+				stream.skip_gap(&mut delivery);
+				released.push((stream.root.clone(), delivery));
+			}
+		}
+		released
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mqtt::envelope::EventKind;
+
+	fn line(sequence: u64, epoch: &str) -> LogEnvelope {
+		let mut envelope = LogEnvelope::new(
+			"host",
+			"project",
+			"logs/app.log",
+			EventKind::Modified,
+			sequence,
+			None,
+			&format!("line {}", sequence),
+		);
+		envelope.epoch = Some(epoch.to_string());
+		envelope
+	}
+
+	fn accept(sequencer: &Sequencer, envelope: LogEnvelope) -> Vec<u64> {
+		let delivery = sequencer.accept(envelope, Path::new("/root"));
+		delivery.ready.iter().map(|line| line.sequence).collect()
+	}
+
+	#[test]
+	fn lines_in_order_are_applied_at_once() {
+		let sequencer = Sequencer::default();
+		assert_eq!(accept(&sequencer, line(1, "a")), [1]);
+		assert_eq!(accept(&sequencer, line(2, "a")), [2]);
+		assert_eq!(accept(&sequencer, line(3, "a")), [3]);
+	}
+
+	#[test]
+	fn first_line_seen_starts_the_stream() {
+		let sequencer = Sequencer::default();
+		assert_eq!(accept(&sequencer, line(40, "a")), [40]);
+		assert_eq!(accept(&sequencer, line(41, "a")), [41]);
+	}
+
+	#[test]
+	fn gap_holds_lines_until_it_fills() {
+		let sequencer = Sequencer::default();
+		assert_eq!(accept(&sequencer, line(1, "a")), [1]);
+		assert!(accept(&sequencer, line(3, "a")).is_empty());
+		assert!(accept(&sequencer, line(4, "a")).is_empty());
+		assert_eq!(accept(&sequencer, line(2, "a")), [2, 3, 4]);
+	}
+
+	#[test]
+	fn too_many_held_lines_skip_the_gap() {
+		let sequencer = Sequencer::default();
+		accept(&sequencer, line(1, "a"));
+		for sequence in 3..3 + MAX_HELD as u64 {
+			assert!(accept(&sequencer, line(sequence, "a")).is_empty());
+		}
+		let delivery = sequencer.accept(line(3 + MAX_HELD as u64, "a"), Path::new("/root"));
+		assert_eq!(delivery.ready.len(), MAX_HELD + 1);
+		assert_eq!(delivery.gaps.len(), 1);
+		assert_eq!(delivery.gaps[0].first_missing, 2);
+		assert_eq!(delivery.gaps[0].last_missing, 2);
+	}
+
+	#[test]
+	fn redelivered_message_is_dropped() {
+		let sequencer = Sequencer::default();
+		let first = line(1, "a");
+		assert_eq!(accept(&sequencer, first.clone()), [1]);
+		assert!(accept(&sequencer, first).is_empty());
+		assert_eq!(accept(&sequencer, line(2, "a")), [2]);
+	}
+
+	#[test]
+	fn duplicate_sequence_is_dropped() {
+		let sequencer = Sequencer::default();
+		accept(&sequencer, line(1, "a"));
+		accept(&sequencer, line(2, "a"));
+		assert!(accept(&sequencer, line(2, "a")).is_empty());
+	}
+
+	#[test]
+	fn late_first_line_of_the_same_epoch_is_not_a_restart() {
+		let sequencer = Sequencer::default();
+		accept(&sequencer, line(1, "a"));
+		accept(&sequencer, line(2, "a"));
+		accept(&sequencer, line(3, "a"));
+		assert!(accept(&sequencer, line(1, "a")).is_empty());
+		assert_eq!(accept(&sequencer, line(4, "a")), [4]);
+	}
+
+	#[test]
+	fn new_epoch_restarts_the_stream() {
+		let sequencer = Sequencer::default();
+		accept(&sequencer, line(1, "a"));
+		accept(&sequencer, line(2, "a"));
+		assert_eq!(accept(&sequencer, line(1, "b")), [1]);
+		assert_eq!(accept(&sequencer, line(2, "b")), [2]);
+	}
+
+	#[test]
+	fn new_epoch_waits_for_its_missing_first_lines() {
+		let sequencer = Sequencer::default();
+		accept(&sequencer, line(5, "a"));
+		assert!(accept(&sequencer, line(2, "b")).is_empty());
+		assert_eq!(accept(&sequencer, line(1, "b")), [1, 2]);
+	}
+
+	#[test]
+	fn lines_of_a_previous_epoch_are_dropped() {
+		let sequencer = Sequencer::default();
+		accept(&sequencer, line(7, "a"));
+		accept(&sequencer, line(1, "b"));
+		assert!(accept(&sequencer, line(8, "a")).is_empty());
+		assert_eq!(accept(&sequencer, line(2, "b")), [2]);
+	}
+
+	#[test]
+	fn restart_releases_lines_held_from_the_previous_epoch() {
+		let sequencer = Sequencer::default();
+		accept(&sequencer, line(1, "a"));
+		accept(&sequencer, line(3, "a"));
+		let delivery = sequencer.accept(line(1, "b"), Path::new("/root"));
+		let ready: Vec<u64> = delivery.ready.iter().map(|line| line.sequence).collect();
+		assert_eq!(ready, [3, 1]);
+		assert_eq!(delivery.gaps.len(), 1);
+	}
+
+	#[test]
+	fn first_line_without_epoch_restarts_older_emitters() {
+		let sequencer = Sequencer::default();
+		let mut old = line(5, "a");
+		old.epoch = None;
+		accept(&sequencer, old);
+		let mut restarted = line(1, "a");
+		restarted.epoch = None;
+		assert_eq!(accept(&sequencer, restarted), [1]);
+	}
+}
@@ -0,0 +1,720 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rumqttc::{Publish, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Manager};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout};
+use walkdir::WalkDir;
+
+use crate::mqtt::crypto::{self, ProjectKey};
+use crate::mqtt::envelope::{new_message_id, LogEnvelope};
+use crate::mqtt::service::MqttService;
+use crate::mqtt::signing;
+use crate::process::audit;
+use crate::process::file_rules::{EmitterFileFilter, FileFilter};
+use crate::process::mode::AppModeState;
+use crate::process::project_keys::project_key;
+use crate::process::safe_path::resolve_in_project;
+use crate::process::trust::{check_requester, signing_key};
+use crate::process::watch::project_relative_path;
+
+// Raw bytes per chunk; base64 grows it to 64 KiB on the wire
+const CHUNK_SIZE: usize = 48 * 1024;
+const ACK_TIMEOUT: Duration = Duration::from_secs(30);
+// The MQTT request queue is shared with the outbox drainer and may be momentarily full
+const QUEUE_RETRIES: u32 = 20;
+const QUEUE_RETRY_DELAY: Duration = Duration::from_millis(250);
+// A receiver stops waiting for a snapshot and applies the lines it held after this long
+const PENDING_TIMEOUT: Duration = Duration::from_secs(60);
+// Upper bound for a single file (about 1 GiB), so a bad chunk count cannot exhaust memory
+const MAX_CHUNKS: u32 = 20_000;
+
+pub fn request_topic(project_name: &str) -> String {
+	format!("project/{}/snapshot/request", project_name)
+}
+
+fn data_topic(project_name: &str, request_id: &str) -> String {
+	format!("project/{}/snapshot/data/{}", project_name, request_id)
+}
+
+/// Sent by a receiver, signed like log messages: `path` (relative to the project root) asks
+/// for one file, `None` for every log file of the project.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotRequest {
+	pub request_id: String,
+	pub path: Option<String>,
+}
+
+/// Emitter answer, published on the request's data topic. Every file is split in `count`
+/// chunks; `sha256` covers the whole file (sealed with the project key, like the chunks, when
+/// the snapshot is encrypted) and `offset` is the byte offset the snapshot ends at, so
+/// incremental lines up to it are already part of the snapshot.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SnapshotMessage {
+	Chunk {
+		request_id: String,
+		path: String,
+		offset: u64,
+		sha256: String,
+		index: u32,
+		count: u32,
+		data: String,
+		/// Set when `data` is base64 of the nonce and ciphertext
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		encryption: Option<String>,
+	},
+	Complete {
+		request_id: String,
+		path: Option<String>,
+		files: u32,
+	},
+}
+
+impl SnapshotMessage {
+	pub fn is_encrypted(&self) -> bool {
+		matches!(
+			self,
+			SnapshotMessage::Chunk {
+				encryption: Some(_),
+				..
+			}
+		)
+	}
+}
+
+fn chunk_aad(request_id: &str, path: &str, index: u32) -> String {
+	format!("{}|{}|{}", request_id, path, index)
+}
+
+fn hash_aad(request_id: &str, path: &str) -> String {
+	format!("{}|{}|sha256", request_id, path)
+}
+
+// ---- Emitter side ----
+
+/// Answers the snapshot requests received for the project at `root` until the channel closes.
+pub async fn serve_requests(
+	app_handle: AppHandle,
+	root: PathBuf,
+	mut incoming: mpsc::UnboundedReceiver<Publish>,
+) {
+	let project_name = root.file_name().unwrap().to_string_lossy().to_string();
+	while let Some(publish) = incoming.recv().await {
+		let request = match verify_request(&app_handle, &project_name, &publish.payload) {
+			Ok(request) => request,
+			Err((signer, e)) => {
+				audit::reject(
+					&app_handle,
+					&project_name,
+					&publish.topic,
+					signer.as_deref(),
+					&e,
+				);
+				continue;
+			}
+		};
+		println!(
+			"Snapshot: Request {} received for {}",
+			request.request_id,
+			request.path.as_deref().unwrap_or("the whole project")
+		);
+		if let Err(e) = answer_request(&app_handle, &root, &request).await {
+			eprintln!("Snapshot: Request {} failed: {}", request.request_id, e);
+		}
+	}
+}
+
+// Only requests signed by a key in the project's requester list are answered; errors carry
+// the claimed signer
+fn verify_request(
+	app_handle: &AppHandle,
+	project_name: &str,
+	payload: &[u8],
+) -> Result<SnapshotRequest, (Option<String>, String)> {
+	let value: Value = serde_json::from_slice(payload)
+		.map_err(|e| (None, format!("La solicitud no es JSON válido: {}", e)))?;
+	let claimed_signer = value["signer"].as_str().map(str::to_string);
+	signing::verify_value(&value)
+		.and_then(|signer| check_requester(app_handle, project_name, &signer))
+		.and_then(|_| {
+			serde_json::from_value(value)
+				.map_err(|e| format!("La solicitud no tiene un formato válido: {}", e))
+		})
+		.map_err(|e| (claimed_signer, e))
+}
+
+async fn answer_request(
+	app_handle: &AppHandle,
+	root: &Path,
+	request: &SnapshotRequest,
+) -> Result<(), String> {
+	let project_name = root.file_name().unwrap().to_string_lossy().to_string();
+	// Only files the project's rules publish are served
+	let files: Vec<PathBuf> = {
+		let file_filter = app_handle.state::<EmitterFileFilter>();
+		let filter = file_filter.0.lock().unwrap();
+		match &request.path {
+			Some(relative) => resolve_requested(root, relative, &filter).into_iter().collect(),
+			None => WalkDir::new(root)
+				.into_iter()
+				.filter_map(|e| e.ok())
+				.filter(|e| e.file_type().is_file() && filter.matches_path(root, e.path()))
+				.map(|e| e.into_path())
+				.collect(),
+		}
+	};
+
+	let topic = data_topic(&project_name, &request.request_id);
+	let mut sent = 0;
+	for path in &files {
+		match send_file(app_handle, &topic, root, &request.request_id, path).await {
+			Ok(()) => sent += 1,
+			Err(e) => eprintln!("Snapshot: Skipping {}: {}", path.display(), e),
+		}
+	}
+
+	let complete = SnapshotMessage::Complete {
+		request_id: request.request_id.clone(),
+		path: request.path.clone(),
+		files: sent,
+	};
+	publish_acked(app_handle, &topic, &complete).await?;
+	println!(
+		"Snapshot: Request {} answered with {} files",
+		request.request_id, sent
+	);
+	Ok(())
+}
+
+// Only log files inside the project can be requested
+fn resolve_requested(root: &Path, relative: &str, filter: &FileFilter) -> Option<PathBuf> {
+	let path = resolve_in_project(root, relative).ok()?;
+	(filter.matches(relative) && path.is_file()).then_some(path)
+}
+
+async fn send_file(
+	app_handle: &AppHandle,
+	topic: &str,
+	root: &Path,
+	request_id: &str,
+	path: &Path,
+) -> Result<(), String> {
+	let mut content = fs::read(path).map_err(|e| e.to_string())?;
+	// Like the tailer, only complete lines are sent; the rest arrives as incremental lines
+	let end = content
+		.iter()
+		.rposition(|&b| b == b'\n')
+		.map_or(0, |i| i + 1);
+	content.truncate(end);
+
+	let (project_name, relative_path) = project_relative_path(root, path);
+	let mqtt_path = format!("{}/{}", project_name, relative_path);
+	let key = project_key(app_handle, &project_name)?;
+	let sha256 = format!("{:x}", Sha256::digest(&content));
+	// In the clear, the hash would let anyone on the broker confirm a guess of the content
+	let sha256 = match &key {
+		Some(key) => {
+			let aad = hash_aad(request_id, &mqtt_path);
+			BASE64.encode(crypto::encrypt(key, sha256.as_bytes(), aad.as_bytes()))
+		}
+		None => sha256,
+	};
+	let chunks: Vec<&[u8]> = if content.is_empty() {
+		vec![&[]]
+	} else {
+		content.chunks(CHUNK_SIZE).collect()
+	};
+
+	let count = chunks.len() as u32;
+	for (index, chunk) in chunks.into_iter().enumerate() {
+		let index = index as u32;
+		let (data, encryption) = match &key {
+			Some(key) => {
+				let aad = chunk_aad(request_id, &mqtt_path, index);
+				let sealed = crypto::encrypt(key, chunk, aad.as_bytes());
+				(BASE64.encode(sealed), Some(crypto::ALGORITHM.to_string()))
+			}
+			None => (BASE64.encode(chunk), None),
+		};
+		let message = SnapshotMessage::Chunk {
+			request_id: request_id.to_string(),
+			path: mqtt_path.clone(),
+			offset: end as u64,
+			sha256: sha256.clone(),
+			index,
+			count,
+			data,
+			encryption,
+		};
+		publish_acked(app_handle, topic, &message).await?;
+	}
+	Ok(())
+}
+
+// Signs and publishes one message, then waits for the broker so large files never flood
+// the queue
+async fn publish_acked(
+	app_handle: &AppHandle,
+	topic: &str,
+	message: &SnapshotMessage,
+) -> Result<(), String> {
+	let mut signed = serde_json::to_value(message).unwrap();
+	signing::sign_value(&signing_key(app_handle)?, &mut signed);
+	let payload = serde_json::to_vec(&signed).unwrap();
+
+	let service = app_handle.state::<MqttService>();
+	let mut attempt = 0;
+	let ack = loop {
+		match service.publish_confirmed(topic, payload.clone(), QoS::AtLeastOnce) {
+			Ok(ack) => break ack,
+			Err(e) => {
+				attempt += 1;
+				if attempt >= QUEUE_RETRIES {
+					return Err(e);
+				}
+				sleep(QUEUE_RETRY_DELAY).await;
+			}
+		}
+	};
+	match timeout(ACK_TIMEOUT, ack).await {
+		Ok(Ok(())) => Ok(()),
+		_ => Err(format!(
+			"El broker no confirmó la publicación en '{}'",
+			topic
+		)),
+	}
+}
+
+// ---- Receiver side ----
+
+enum FileSync {
+	// Waiting for a snapshot; incremental lines are held until it is written
+	Pending {
+		since: Instant,
+		held: Vec<LogEnvelope>,
+		received: bool,
+	},
+	// Lines ending at or before `base_offset` are already part of the local copy
+	Synced {
+		base_offset: Option<u64>,
+	},
+}
+
+struct Assembly {
+	path: String,
+	offset: u64,
+	sha256: String,
+	chunks: Vec<Option<Vec<u8>>>,
+	started: Instant,
+}
+
+// A request this receiver sent; only its chunks are taken in, and only until it completes
+struct SentRequest {
+	project: String,
+	// MQTT path of the file, `None` for the whole project
+	path: Option<String>,
+	since: Instant,
+}
+
+#[derive(Default)]
+struct SyncState {
+	// Keyed by request id
+	requests: HashMap<String, SentRequest>,
+	// Keyed by the MQTT path ("<project>/<relative path>")
+	files: HashMap<String, FileSync>,
+	// Projects with a whole-project snapshot in progress
+	projects: HashMap<String, Instant>,
+	// Keyed by (request id, MQTT path)
+	assemblies: HashMap<(String, String), Assembly>,
+	// Verified snapshots waiting for the project path to be resolved
+	ready: HashMap<String, (u64, Vec<u8>)>,
+}
+
+impl SyncState {
+	// Whether `path` (an MQTT path) is part of the pending request `request_id`
+	fn expects(&self, request_id: &str, path: &str) -> bool {
+		self.requests
+			.get(request_id)
+			.is_some_and(|request| match &request.path {
+				Some(only) => only == path,
+				None => path.starts_with(&format!("{}/", request.project)),
+			})
+	}
+}
+
+pub enum LineAction {
+	Apply,
+	Skip,
+	Hold,
+	HoldAndRequest,
+}
+
+/// Receiver bookkeeping that decides how incremental lines relate to snapshots.
+#[derive(Default)]
+pub struct SnapshotSync(Mutex<SyncState>);
+
+impl SnapshotSync {
+	pub fn clear(&self) {
+		*self.0.lock().unwrap() = SyncState::default();
+	}
+
+	/// Remembers a request about to be sent, so the chunks answering it are accepted.
+	pub fn track_request(&self, request_id: &str, project_name: &str, path: Option<&str>) {
+		self.0.lock().unwrap().requests.insert(
+			request_id.to_string(),
+			SentRequest {
+				project: project_name.to_string(),
+				path: path.map(|path| format!("{}/{}", project_name, path)),
+				since: Instant::now(),
+			},
+		);
+	}
+
+	/// Forgets a request that could not be sent.
+	pub fn untrack_request(&self, request_id: &str) {
+		self.0.lock().unwrap().requests.remove(request_id);
+	}
+
+	/// Decides what to do with an incremental line that is about to be written. The first
+	/// line of a file never seen before is held and a snapshot of that file is requested.
+	pub fn on_line(&self, envelope: &LogEnvelope) -> LineAction {
+		let key = envelope.mqtt_path();
+
+		let mut state = self.0.lock().unwrap();
+		let project_pending = state.projects.contains_key(&envelope.project);
+		match state.files.get_mut(&key) {
+			Some(FileSync::Pending { held, .. }) => {
+				held.push(envelope.clone());
+				return LineAction::Hold;
+			}
+			Some(FileSync::Synced { base_offset }) if !project_pending => {
+				return match (*base_offset, envelope.offset) {
+					(Some(base), Some(offset)) if offset <= base => LineAction::Skip,
+					_ => LineAction::Apply,
+				};
+			}
+			_ => {}
+		}
+
+		let first_seen = !state.files.contains_key(&key);
+		state.files.insert(
+			key,
+			FileSync::Pending {
+				since: Instant::now(),
+				held: vec![envelope.clone()],
+				received: false,
+			},
+		);
+		if first_seen && !project_pending {
+			LineAction::HoldAndRequest
+		} else {
+			LineAction::Hold
+		}
+	}
+
+	pub fn forget(&self, key: &str) {
+		self.0.lock().unwrap().files.remove(key);
+	}
+
+	/// Holds the lines of every file in the project until the snapshot completes.
+	pub fn begin_project(&self, project_name: &str) {
+		self.0
+			.lock()
+			.unwrap()
+			.projects
+			.insert(project_name.to_string(), Instant::now());
+	}
+
+	pub fn begin_file(&self, key: &str) {
+		let mut state = self.0.lock().unwrap();
+		if !matches!(state.files.get(key), Some(FileSync::Pending { .. })) {
+			state.files.insert(
+				key.to_string(),
+				FileSync::Pending {
+					since: Instant::now(),
+					held: Vec::new(),
+					received: false,
+				},
+			);
+		}
+	}
+
+	/// Stores one chunk, decrypting it with `key` if it is encrypted. Returns the file's MQTT
+	/// path once all its chunks arrived and the content matches the announced hash; the
+	/// content is then kept for `take_ready`. Chunks of a request this receiver did not send,
+	/// or that already completed, are refused so a replayed snapshot cannot overwrite a file.
+	pub fn add_chunk(
+		&self,
+		message: SnapshotMessage,
+		key: Option<&ProjectKey>,
+	) -> Result<Option<String>, String> {
+		let SnapshotMessage::Chunk {
+			request_id,
+			path,
+			offset,
+			sha256,
+			index,
+			count,
+			data,
+			encryption,
+		} = message
+		else {
+			return Ok(None);
+		};
+		if !self.0.lock().unwrap().expects(&request_id, &path) {
+			return Err(format!(
+				"Fragmento de {} descartado: la solicitud {} no está pendiente",
+				path, request_id
+			));
+		}
+		let mut bytes = BASE64
+			.decode(data)
+			.map_err(|e| format!("Fragmento inválido de {}: {}", path, e))?;
+		let mut sha256 = sha256;
+		if encryption.is_some() {
+			let key = key.ok_or_else(|| format!("Falta la clave para descifrar {}", path))?;
+			bytes = crypto::decrypt(key, &bytes, chunk_aad(&request_id, &path, index).as_bytes())?;
+			let sealed = BASE64
+				.decode(&sha256)
+				.map_err(|e| format!("Hash inválido de {}: {}", path, e))?;
+			let hash = crypto::decrypt(key, &sealed, hash_aad(&request_id, &path).as_bytes())?;
+			sha256 = String::from_utf8(hash).map_err(|_| format!("Hash inválido de {}", path))?;
+		}
+		if count > MAX_CHUNKS {
+			return Err(format!(
+				"La instantánea de {} excede el tamaño máximo",
+				path
+			));
+		}
+		if index >= count {
+			return Err(format!(
+				"Fragmento {} de {} fuera de rango para {}",
+				index, count, path
+			));
+		}
+
+		let mut state = self.0.lock().unwrap();
+		// Completed while the chunk was being decrypted
+		if !state.expects(&request_id, &path) {
+			return Ok(None);
+		}
+		// Progress keeps a large transfer from timing out
+		if let Some(request) = state.requests.get_mut(&request_id) {
+			request.since = Instant::now();
+		}
+		if let Some(FileSync::Pending { since, .. }) = state.files.get_mut(&path) {
+			*since = Instant::now();
+		}
+		let id = (request_id, path.clone());
+		let assembly = state
+			.assemblies
+			.entry(id.clone())
+			.or_insert_with(|| Assembly {
+				path,
+				offset,
+				sha256,
+				chunks: vec![None; count as usize],
+				started: Instant::now(),
+			});
+		if let Some(slot) = assembly.chunks.get_mut(index as usize) {
+			*slot = Some(bytes);
+		}
+		if assembly.chunks.iter().any(Option::is_none) {
+			return Ok(None);
+		}
+
+		let assembly = state.assemblies.remove(&id).unwrap();
+		let content: Vec<u8> = assembly.chunks.into_iter().flatten().flatten().collect();
+		if format!("{:x}", Sha256::digest(&content)) != assembly.sha256 {
+			return Err(format!(
+				"El hash de la instantánea de {} no coincide",
+				assembly.path
+			));
+		}
+
+		// Lines keep being held until the snapshot is on disk
+		match state.files.get_mut(&assembly.path) {
+			Some(FileSync::Pending { received, .. }) => *received = true,
+			_ => {
+				state.files.insert(
+					assembly.path.clone(),
+					FileSync::Pending {
+						since: Instant::now(),
+						held: Vec::new(),
+						received: true,
+					},
+				);
+			}
+		}
+		state
+			.ready
+			.insert(assembly.path.clone(), (assembly.offset, content));
+		Ok(Some(assembly.path))
+	}
+
+	pub fn take_ready(&self, key: &str) -> Option<(u64, Vec<u8>)> {
+		self.0.lock().unwrap().ready.remove(key)
+	}
+
+	/// Marks the snapshot of `key` as written (or failed, with `None`) and returns the lines
+	/// held meanwhile, to be applied again on top of it.
+	pub fn snapshot_written(&self, key: &str, base_offset: Option<u64>) -> Vec<LogEnvelope> {
+		let mut state = self.0.lock().unwrap();
+		match state
+			.files
+			.insert(key.to_string(), FileSync::Synced { base_offset })
+		{
+			Some(FileSync::Pending { held, .. }) => held,
+			_ => Vec::new(),
+		}
+	}
+
+	/// Ends a request once the emitter reported it complete. Files that got no snapshot go
+	/// back to plain incremental lines; their held lines are returned. Completions of
+	/// requests that are not pending change nothing.
+	pub fn finish_request(
+		&self,
+		request_id: &str,
+		project_name: &str,
+		path: Option<&str>,
+	) -> Vec<LogEnvelope> {
+		let mut state = self.0.lock().unwrap();
+		if state
+			.requests
+			.get(request_id)
+			.is_none_or(|request| request.project != project_name)
+		{
+			return Vec::new();
+		}
+		state.requests.remove(request_id);
+		state.assemblies.retain(|(id, _), _| id != request_id);
+		let prefix = format!("{}/", project_name);
+		let only = path.map(|p| format!("{}{}", prefix, p));
+		if only.is_none() {
+			state.projects.remove(project_name);
+		}
+
+		let mut released = Vec::new();
+		for (key, sync) in state.files.iter_mut() {
+			let in_scope = match &only {
+				Some(only) => key == only,
+				None => key.starts_with(&prefix),
+			};
+			if let FileSync::Pending {
+				held,
+				received: false,
+				..
+			} = sync
+			{
+				if in_scope {
+					released.append(held);
+					*sync = FileSync::Synced { base_offset: None };
+				}
+			}
+		}
+		released
+	}
+
+	/// Gives up on snapshots that never arrived and returns the lines held for them.
+	pub fn expire(&self) -> Vec<LogEnvelope> {
+		let mut state = self.0.lock().unwrap();
+		state
+			.projects
+			.retain(|_, since| since.elapsed() < PENDING_TIMEOUT);
+		state
+			.assemblies
+			.retain(|_, assembly| assembly.started.elapsed() < PENDING_TIMEOUT);
+		state
+			.requests
+			.retain(|_, request| request.since.elapsed() < PENDING_TIMEOUT);
+
+		let mut released = Vec::new();
+		for (key, sync) in state.files.iter_mut() {
+			if let FileSync::Pending { since, held, .. } = sync {
+				if since.elapsed() >= PENDING_TIMEOUT {
+					eprintln!(
+						"Snapshot: No snapshot received for {}, applying held lines.",
+						key
+					);
+					released.append(held);
+					*sync = FileSync::Synced { base_offset: None };
+				}
+			}
+		}
+
+		// Snapshots whose project path never resolved
+		let SyncState { files, ready, .. } = &mut *state;
+		ready.retain(|key, _| matches!(files.get(key), Some(FileSync::Pending { .. })));
+		released
+	}
+}
+
+/// Publishes a snapshot request for one file (`path` relative to the project root) or for
+/// the whole project, and returns its id.
+pub fn send_request(
+	app_handle: &AppHandle,
+	project_name: &str,
+	path: Option<&str>,
+) -> Result<String, String> {
+	let request_id = new_message_id();
+	let request = SnapshotRequest {
+		request_id: request_id.clone(),
+		path: path.map(str::to_string),
+	};
+	// Emitters only answer requests from keys they trust
+	let mut signed = serde_json::to_value(&request).unwrap();
+	signing::sign_value(&signing_key(app_handle)?, &mut signed);
+	// Tracked first, since the answer may arrive before `publish` returns
+	let sync = app_handle.state::<SnapshotSync>();
+	sync.track_request(&request_id, project_name, path);
+	if let Err(e) = app_handle.state::<MqttService>().publish(
+		&request_topic(project_name),
+		serde_json::to_vec(&signed).unwrap(),
+		QoS::AtLeastOnce,
+	) {
+		sync.untrack_request(&request_id);
+		return Err(e);
+	}
+	println!(
+		"Snapshot: Requested {} of project '{}' ({})",
+		path.unwrap_or("every file"),
+		project_name,
+		request_id
+	);
+	Ok(request_id)
+}
+
+/// Asks the emitter of `project_name` for the full contents of `path`, or of every log file
+/// when no path is given. Incremental lines are held until the snapshot is written.
+#[command]
+pub fn request_project_snapshot(
+	app_handle: AppHandle,
+	project_name: String,
+	path: Option<String>,
+) -> Result<String, String> {
+	if !app_handle
+		.state::<AppModeState>()
+		.0
+		.lock()
+		.unwrap()
+		.is_receiver()
+	{
+		return Err("El modo receptor no está activo".to_string());
+	}
+
+	let sync = app_handle.state::<SnapshotSync>();
+	match &path {
+		Some(path) => sync.begin_file(&format!("{}/{}", project_name, path)),
+		None => sync.begin_project(&project_name),
+	}
+	send_request(&app_handle, &project_name, path.as_deref())
+}
@@ -0,0 +1,256 @@
+use globset::{GlobBuilder, GlobMatcher};
+use rumqttc::QoS;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Manager};
+
+use crate::mqtt::service::MqttService;
+use crate::process::background::RECEIVER_OWNER;
+use crate::settings;
+
+const SUBSCRIPTIONS_FILE: &str = "receiver_subscriptions.json";
+// Used when no subscription is configured: every project
+const ALL_PROJECTS: &str = "project/#";
+
+/// One project (or part of it) the receiver mirrors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiverSubscription {
+	pub project: String,
+	/// Sub-folder of the project, '/'-separated; the whole project when absent
+	#[serde(default)]
+	pub path: Option<String>,
+	/// File glob such as `*.log`, matched against the file name, or against the path
+	/// relative to the project when it contains '/'. MQTT filters cannot express it, so it
+	/// is checked when messages arrive.
+	#[serde(default)]
+	pub pattern: Option<String>,
+}
+
+struct Rule {
+	// Lowercase, as projects are looked up in the database
+	project: String,
+	// "<path>/" so "Bot1" does not match "Bot10/..."
+	prefix: Option<String>,
+	pattern: Option<(GlobMatcher, bool)>,
+}
+
+impl Rule {
+	fn accepts(&self, project: &str, relative_path: &str) -> bool {
+		if self.project != project.trim().to_lowercase() {
+			return false;
+		}
+		if let Some(prefix) = &self.prefix {
+			if !relative_path.starts_with(prefix.as_str()) {
+				return false;
+			}
+		}
+		match &self.pattern {
+			Some((matcher, true)) => matcher.is_match(relative_path),
+			Some((matcher, false)) => {
+				matcher.is_match(relative_path.rsplit('/').next().unwrap_or(relative_path))
+			}
+			None => true,
+		}
+	}
+}
+
+#[derive(Default)]
+struct Active {
+	// Topic filters held while the receiver runs; `None` when it is stopped
+	filters: Option<Vec<String>>,
+	rules: Vec<Rule>,
+}
+
+/// Topic filters and file rules of the running receiver.
+#[derive(Default)]
+pub struct ReceiverSubscriptions(Mutex<Active>);
+
+impl ReceiverSubscriptions {
+	/// Whether a file of `project` is mirrored. Everything is when no subscription is set.
+	pub fn accepts(&self, project: &str, relative_path: &str) -> bool {
+		let active = self.0.lock().unwrap();
+		active.rules.is_empty()
+			|| active
+				.rules
+				.iter()
+				.any(|rule| rule.accepts(project, relative_path))
+	}
+}
+
+fn normalize_path(path: &str) -> Result<Option<String>, String> {
+	let path = path.trim().trim_matches('/');
+	if path.is_empty() {
+		return Ok(None);
+	}
+	for segment in path.split('/') {
+		if segment.is_empty() || segment == "." || segment == ".." {
+			return Err(format!("Ruta de suscripción inválida: '{}'", path));
+		}
+		if segment.contains(['+', '#']) {
+			return Err(format!(
+				"La ruta de suscripción no puede contener comodines MQTT: '{}'",
+				path
+			));
+		}
+	}
+	Ok(Some(path.to_string()))
+}
+
+fn compile(subscription: &ReceiverSubscription) -> Result<Rule, String> {
+	let project = subscription.project.trim();
+	if project.is_empty() || project.contains(['/', '+', '#']) {
+		return Err(format!("Nombre de proyecto inválido: '{}'", project));
+	}
+	let prefix = match subscription.path.as_deref() {
+		Some(path) => normalize_path(path)?.map(|path| format!("{}/", path)),
+		None => None,
+	};
+	let pattern = match subscription.pattern.as_deref().map(str::trim) {
+		Some(pattern) if !pattern.is_empty() => {
+			// '*' stays within one folder; '**' crosses them
+			let glob = GlobBuilder::new(pattern)
+				.literal_separator(true)
+				.build()
+				.map_err(|e| format!("Patrón de archivos inválido '{}': {}", pattern, e))?;
+			Some((glob.compile_matcher(), pattern.contains('/')))
+		}
+		_ => None,
+	};
+	Ok(Rule {
+		project: project.to_lowercase(),
+		prefix,
+		pattern,
+	})
+}
+
+// MQTT filters covering the rules: log topics under the path plus snapshot data. Topics match
+// case-sensitively and project names do not, so any project matches and `accepts` picks them
+fn topic_filters(rules: &[Rule]) -> Vec<String> {
+	if rules.is_empty() {
+		return vec![ALL_PROJECTS.to_string()];
+	}
+	let mut filters = Vec::new();
+	for rule in rules {
+		let logs = match &rule.prefix {
+			Some(prefix) => format!("project/+/logs/{}#", prefix),
+			None => "project/+/logs/#".to_string(),
+		};
+		if !filters.contains(&logs) {
+			filters.push(logs);
+		}
+	}
+	filters.push("project/+/snapshot/data/#".to_string());
+	filters
+}
+
+fn load_subscriptions(app_handle: &AppHandle) -> Vec<ReceiverSubscription> {
+	settings::load_json(app_handle, SUBSCRIPTIONS_FILE).unwrap_or_else(|e| {
+		eprintln!("MQTT Receiver: {}. Subscribing to every project.", e);
+		Vec::new()
+	})
+}
+
+fn compile_all(subscriptions: &[ReceiverSubscription]) -> Result<Vec<Rule>, String> {
+	subscriptions.iter().map(compile).collect()
+}
+
+/// Subscribes the receiver to the saved subscriptions. Called when the receiver starts.
+pub fn activate(app_handle: &AppHandle) {
+	let rules = compile_all(&load_subscriptions(app_handle)).unwrap_or_else(|e| {
+		eprintln!("MQTT Receiver: {}. Subscribing to every project.", e);
+		Vec::new()
+	});
+	let filters = topic_filters(&rules);
+
+	let service = app_handle.state::<MqttService>();
+	for filter in &filters {
+		if let Err(e) = service.subscribe(RECEIVER_OWNER, filter, QoS::AtLeastOnce) {
+			eprintln!("MQTT Receiver: Failed to subscribe to topic: {}", e);
+		}
+	}
+	let state = app_handle.state::<ReceiverSubscriptions>();
+	let mut active = state.0.lock().unwrap();
+	active.filters = Some(filters);
+	active.rules = rules;
+}
+
+/// Forgets the active filters; the MQTT service drops them when the receiver releases it.
+pub fn deactivate(app_handle: &AppHandle) {
+	app_handle
+		.state::<ReceiverSubscriptions>()
+		.0
+		.lock()
+		.unwrap()
+		.filters = None;
+}
+
+#[command]
+pub fn get_receiver_subscriptions(app_handle: AppHandle) -> Vec<ReceiverSubscription> {
+	load_subscriptions(&app_handle)
+}
+
+/// Saves the subscriptions and, if the receiver is running, applies them right away.
+#[command]
+pub fn update_receiver_subscriptions(
+	app_handle: AppHandle,
+	subscriptions: Vec<ReceiverSubscription>,
+) -> Result<(), String> {
+	let rules = compile_all(&subscriptions)?;
+	settings::save_json(&app_handle, SUBSCRIPTIONS_FILE, &subscriptions)?;
+
+	let state = app_handle.state::<ReceiverSubscriptions>();
+	let mut active = state.0.lock().unwrap();
+	let filters = topic_filters(&rules);
+	active.rules = rules;
+	let Some(current) = active.filters.as_mut() else {
+		return Ok(());
+	};
+
+	// Subscribe first so nothing is missed while switching
+	let service = app_handle.state::<MqttService>();
+	for filter in &filters {
+		if !current.contains(filter) {
+			service.subscribe(RECEIVER_OWNER, filter, QoS::AtLeastOnce)?;
+			current.push(filter.clone());
+		}
+	}
+	while let Some(index) = current.iter().position(|f| !filters.contains(f)) {
+		service.unsubscribe(RECEIVER_OWNER, &current[index])?;
+		current.remove(index);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rule(project: &str, path: Option<&str>) -> Rule {
+		compile(&ReceiverSubscription {
+			project: project.to_string(),
+			path: path.map(str::to_string),
+			pattern: None,
+		})
+		.unwrap()
+	}
+
+	#[test]
+	fn projects_match_in_any_case() {
+		let rule = rule("Bot", Some("logs"));
+		for project in ["Bot", "bot", "BOT"] {
+			assert!(rule.accepts(project, "logs/app.log"), "{}", project);
+		}
+		assert!(!rule.accepts("Bot1", "logs/app.log"));
+		assert!(!rule.accepts("bot", "otros/app.log"));
+	}
+
+	#[test]
+	fn filters_leave_the_project_to_the_rules() {
+		assert_eq!(topic_filters(&[]), [ALL_PROJECTS]);
+		let rules = [rule("Bot", Some("logs")), rule("bot", Some("logs/"))];
+		assert_eq!(
+			topic_filters(&rules),
+			["project/+/logs/logs/#", "project/+/snapshot/data/#"]
+		);
+	}
+}
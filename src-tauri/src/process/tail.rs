@@ -0,0 +1,174 @@
+use encoding_rs::{Encoding, UTF_8};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::process::encoding;
+
+// Identity of the file behind a path, used to detect rotation (the path now names a new file)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileId(u64, u64);
+
+#[cfg(unix)]
+fn file_id(file: &File) -> Option<FileId> {
+	use std::os::unix::fs::MetadataExt;
+	let metadata = file.metadata().ok()?;
+	Some(FileId(metadata.dev(), metadata.ino()))
+}
+
+// NTFS tunneling gives a file recreated under the same name the old creation time, so the
+// volume serial number and file index are used instead
+#[cfg(windows)]
+fn file_id(file: &File) -> Option<FileId> {
+	use std::os::windows::io::AsRawHandle;
+	use windows_sys::Win32::Storage::FileSystem::{
+		GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION,
+	};
+
+	let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+	if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+		return None;
+	}
+	let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+	Some(FileId(u64::from(info.dwVolumeSerialNumber), index))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn file_id(_file: &File) -> Option<FileId> {
+	None
+}
+
+pub struct TailLine {
+	pub content: String,
+	/// Byte offset just past this line's newline in the source file
+	pub end_offset: u64,
+	/// What the line ended with in the source file: "\n" or "\r\n"
+	pub newline: &'static str,
+	/// Encoding the line was decoded from
+	pub encoding: &'static Encoding,
+}
+
+struct TailState {
+	offset: u64,
+	id: Option<FileId>,
+	// `None` until the file shows something other than ASCII
+	encoding: Option<&'static Encoding>,
+}
+
+/// Remembers how far each file has been read so every appended line is reported exactly once.
+#[derive(Default)]
+pub struct FileTailer {
+	files: HashMap<PathBuf, TailState>,
+	// Project override; files are detected one by one when absent
+	encoding: Option<&'static Encoding>,
+}
+
+impl FileTailer {
+	pub fn new(encoding: Option<&'static Encoding>) -> Self {
+		FileTailer {
+			files: HashMap::new(),
+			encoding,
+		}
+	}
+
+	/// Starts tracking `path` at its current end, so only lines written from now on are read.
+	pub fn prime(&mut self, path: &Path) {
+		let Ok(file) = File::open(path) else {
+			return;
+		};
+		if let Ok(metadata) = file.metadata() {
+			let encoding = self
+				.encoding
+				.or_else(|| encoding::sniff(path).ok().flatten());
+			self.files.insert(
+				path.to_path_buf(),
+				TailState {
+					offset: metadata.len(),
+					id: file_id(&file),
+					encoding,
+				},
+			);
+		}
+	}
+
+	pub fn forget(&mut self, path: &Path) {
+		self.files.remove(path);
+	}
+
+	/// Returns every complete line appended to `path` since the previous call. A trailing line
+	/// without its newline is left for the next call. Unknown, truncated or rotated files are
+	/// read from the beginning.
+	pub fn read_new_lines(&mut self, path: &Path) -> io::Result<Vec<TailLine>> {
+		let mut file = File::open(path)?;
+		let metadata = file.metadata()?;
+		if !metadata.is_file() {
+			return Ok(Vec::new());
+		}
+
+		let id = file_id(&file);
+		let state = self
+			.files
+			.entry(path.to_path_buf())
+			.or_insert(TailState {
+				offset: 0,
+				id,
+				encoding: self.encoding,
+			});
+		if state.id != id || metadata.len() < state.offset {
+			println!(
+				"File Watcher: {} was truncated or rotated, reading from the start.",
+				path.display()
+			);
+			state.offset = 0;
+			state.id = id;
+			state.encoding = self.encoding;
+		}
+		if metadata.len() == state.offset {
+			return Ok(Vec::new());
+		}
+
+		file.seek(SeekFrom::Start(state.offset))?;
+		let mut buffer = Vec::new();
+		file.take(metadata.len() - state.offset)
+			.read_to_end(&mut buffer)?;
+
+		if state.encoding.is_none() {
+			state.encoding = encoding::detect(&buffer);
+			if let Some(detected) = state.encoding {
+				println!(
+					"File Watcher: {} detected as {}",
+					path.display(),
+					detected.name()
+				);
+			}
+		}
+		// Plain ASCII so far reads the same in any encoding
+		let encoding = state.encoding.unwrap_or(UTF_8);
+
+		let lines = encoding::complete_lines(encoding, &buffer);
+		let start = state.offset;
+		state.offset += lines.iter().map(|line| line.len() as u64).sum::<u64>();
+
+		let newline = encoding::newline(encoding);
+		let carriage_return = encoding::carriage_return(encoding);
+		let mut end_offset = start;
+		Ok(lines
+			.into_iter()
+			.map(|line| {
+				end_offset += line.len() as u64;
+				let line = &line[..line.len() - newline.len()];
+				let (line, newline) = match line.strip_suffix(carriage_return) {
+					Some(line) => (line, "\r\n"),
+					None => (line, "\n"),
+				};
+				TailLine {
+					content: encoding::decode(encoding, line),
+					end_offset,
+					newline,
+					encoding,
+				}
+			})
+			.collect())
+	}
+}
@@ -0,0 +1,223 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+// The layouts the viewer used to recognize, plus ISO 8601 with fractions and offsets
+const DEFAULT_FORMATS: [&str; 8] = [
+	"%d-%m-%Y %H:%M:%S",
+	"%d/%m/%Y %H:%M:%S",
+	"%Y-%m-%d %H:%M:%S",
+	"%Y-%m-%d %H:%M:%S%.f",
+	"%Y-%m-%d %H:%M:%S,%3f",
+	"%Y-%m-%dT%H:%M:%S%.f",
+	"%Y-%m-%d %H:%M:%S%.f %:z",
+	"%d/%m/%Y %H:%M",
+];
+
+/// How the timestamps of a project's logs are read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampSettings {
+	/// chrono format strings tried in order after RFC 3339
+	pub formats: Vec<String>,
+	/// Zone of timestamps written without an offset: "local" (this machine's), "utc" or a
+	/// fixed offset such as "-03:00"
+	pub timezone: String,
+}
+
+impl Default for TimestampSettings {
+	fn default() -> Self {
+		TimestampSettings {
+			formats: DEFAULT_FORMATS.iter().map(|f| f.to_string()).collect(),
+			timezone: "local".to_string(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Zone {
+	Local,
+	Fixed(FixedOffset),
+}
+
+struct Format {
+	pattern: String,
+	// Whether the pattern reads an offset itself
+	has_offset: bool,
+}
+
+/// Turns the timestamps of log records into RFC 3339 dates.
+pub struct TimestampParser {
+	formats: Vec<Format>,
+	zone: Zone,
+}
+
+impl Default for TimestampParser {
+	fn default() -> Self {
+		TimestampParser::new(&TimestampSettings::default()).expect("default timestamp settings")
+	}
+}
+
+impl TimestampParser {
+	pub fn new(settings: &TimestampSettings) -> Result<Self, String> {
+		let zone = match settings.timezone.trim().to_lowercase().as_str() {
+			"" | "local" => Zone::Local,
+			"utc" | "z" => Zone::Fixed(FixedOffset::east_opt(0).unwrap()),
+			offset => Zone::Fixed(
+				offset
+					.parse::<FixedOffset>()
+					.map_err(|_| format!("Zona horaria inválida: '{}'", settings.timezone))?,
+			),
+		};
+		let mut formats = Vec::new();
+		for pattern in &settings.formats {
+			let items: Vec<Item> = StrftimeItems::new(pattern).collect();
+			if pattern.trim().is_empty() || items.contains(&Item::Error) {
+				return Err(format!("Formato de fecha inválido: '{}'", pattern));
+			}
+			formats.push(Format {
+				has_offset: pattern.contains("%z") || pattern.contains("%:z"),
+				pattern: pattern.clone(),
+			});
+		}
+		Ok(TimestampParser { formats, zone })
+	}
+
+	fn assign_zone(&self, naive: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+		match self.zone {
+			// A time skipped by a daylight saving change does not exist and is not read
+			Zone::Local => Local
+				.from_local_datetime(&naive)
+				.earliest()
+				.map(|time| time.fixed_offset()),
+			Zone::Fixed(offset) => offset.from_local_datetime(&naive).single(),
+		}
+	}
+
+	/// The date in `text`, or `None` when no format reads it. Formats without a time read
+	/// the date at midnight.
+	pub fn parse(&self, text: &str) -> Option<DateTime<FixedOffset>> {
+		let text = text.trim();
+		if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+			return Some(time);
+		}
+		self.formats.iter().find_map(|format| {
+			if format.has_offset {
+				DateTime::parse_from_str(text, &format.pattern).ok()
+			} else {
+				NaiveDateTime::parse_from_str(text, &format.pattern)
+					.or_else(|_| {
+						NaiveDate::parse_from_str(text, &format.pattern)
+							.map(|date| date.and_time(Default::default()))
+					})
+					.ok()
+					.and_then(|naive| self.assign_zone(naive))
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parser_for(formats: &[&str], timezone: &str) -> TimestampParser {
+		TimestampParser::new(&TimestampSettings {
+			formats: formats.iter().map(|f| f.to_string()).collect(),
+			timezone: timezone.to_string(),
+		})
+		.unwrap()
+	}
+
+	fn read(parser: &TimestampParser, text: &str) -> Option<String> {
+		parser.parse(text).map(|time| time.to_rfc3339())
+	}
+
+	#[test]
+	fn rfc3339_keeps_its_own_offset() {
+		let parser = parser_for(&[], "utc");
+		assert_eq!(
+			read(&parser, " 2024-05-01T10:00:00+02:00 ").as_deref(),
+			Some("2024-05-01T10:00:00+02:00")
+		);
+	}
+
+	#[test]
+	fn timestamps_without_an_offset_take_the_configured_zone() {
+		let settings = TimestampSettings {
+			timezone: "-03:00".to_string(),
+			..TimestampSettings::default()
+		};
+		let parser = TimestampParser::new(&settings).unwrap();
+		for (text, expected) in [
+			("01-05-2024 10:00:00", "2024-05-01T10:00:00-03:00"),
+			("01/05/2024 10:00:00", "2024-05-01T10:00:00-03:00"),
+			("2024-05-01 10:00:00,250", "2024-05-01T10:00:00.250-03:00"),
+			("2024-05-01T10:00:00.5", "2024-05-01T10:00:00.500-03:00"),
+			("01/05/2024 10:00", "2024-05-01T10:00:00-03:00"),
+		] {
+			assert_eq!(read(&parser, text).as_deref(), Some(expected), "{}", text);
+		}
+
+		let utc = parser_for(&["%Y-%m-%d %H:%M:%S"], "UTC");
+		assert_eq!(
+			read(&utc, "2024-05-01 10:00:00").as_deref(),
+			Some("2024-05-01T10:00:00+00:00")
+		);
+	}
+
+	#[test]
+	fn formats_with_an_offset_keep_it() {
+		let parser = parser_for(&["%Y-%m-%d %H:%M:%S%.f %:z"], "utc");
+		assert_eq!(
+			read(&parser, "2024-05-01 10:00:00.5 +02:00").as_deref(),
+			Some("2024-05-01T10:00:00.500+02:00")
+		);
+	}
+
+	#[test]
+	fn date_only_formats_read_midnight() {
+		let parser = parser_for(&["%d/%m/%Y"], "-03:00");
+		assert_eq!(
+			read(&parser, "18/10/2026").as_deref(),
+			Some("2026-10-18T00:00:00-03:00")
+		);
+	}
+
+	#[test]
+	fn formats_are_tried_in_order() {
+		// 02/03 is the 2nd of March for the first format and the 3rd of February for the other
+		let parser = parser_for(&["%d/%m/%Y %H:%M", "%m/%d/%Y %H:%M"], "utc");
+		assert_eq!(
+			read(&parser, "02/03/2024 08:00").as_deref(),
+			Some("2024-03-02T08:00:00+00:00")
+		);
+		assert_eq!(
+			read(&parser, "02/13/2024 08:00").as_deref(),
+			Some("2024-02-13T08:00:00+00:00")
+		);
+	}
+
+	#[test]
+	fn unreadable_timestamps_are_none() {
+		let parser = TimestampParser::default();
+		assert!(parser.parse("ayer a las diez").is_none());
+		assert!(parser.parse("").is_none());
+	}
+
+	#[test]
+	fn invalid_settings_are_rejected() {
+		let invalid_zone = TimestampSettings {
+			timezone: "Marte".to_string(),
+			..TimestampSettings::default()
+		};
+		assert!(TimestampParser::new(&invalid_zone).is_err());
+		for format in ["%Q", " "] {
+			let settings = TimestampSettings {
+				formats: vec![format.to_string()],
+				..TimestampSettings::default()
+			};
+			assert!(TimestampParser::new(&settings).is_err(), "{:?}", format);
+		}
+	}
+}
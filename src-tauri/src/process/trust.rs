@@ -0,0 +1,313 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::SigningKey;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager};
+
+use crate::db::store::Database;
+use crate::db::trust::{self, TrustList, TrustedEmitter};
+use crate::mqtt::signing;
+use crate::process::keychain;
+use crate::process::project_settings::{trust_policy, TrustPolicy};
+
+// Name of this installation's signing secret in the OS credential store
+const SIGNING_KEY_NAME: &str = "signing-key";
+// Label of signers trusted on first use, so the user can tell them apart
+const FIRST_USE_LABEL: &str = "Primer uso";
+
+/// This installation's signing key once read from the credential store.
+#[derive(Default)]
+pub struct SigningIdentity(Mutex<Option<SigningKey>>);
+
+/// Trust lists read from the database, by list and project.
+#[derive(Default)]
+pub struct TrustLists(Mutex<HashMap<(TrustList, String), HashSet<String>>>);
+
+/// This installation's signing key, created on first use.
+pub fn signing_key(app_handle: &AppHandle) -> Result<SigningKey, String> {
+	let identity = app_handle.state::<SigningIdentity>();
+	// Held while the key is created, so two callers cannot create different ones
+	let mut cached = identity.0.lock().unwrap();
+	if let Some(key) = cached.as_ref() {
+		return Ok(key.clone());
+	}
+
+	let key = match keychain::get(SIGNING_KEY_NAME)? {
+		Some(secret) => signing::key_from_bytes(&secret)?,
+		None => {
+			let key = signing::generate_key();
+			keychain::set(SIGNING_KEY_NAME, &key.to_bytes())?;
+			println!(
+				"Signing: New emitter identity {}",
+				signing::public_key(&key)
+			);
+			key
+		}
+	};
+	*cached = Some(key.clone());
+	Ok(key)
+}
+
+/// Fails unless `signer` is in the emitter trust list of `project`, the emitters a receiver
+/// takes lines from. Under the first-use policy, a project that trusts nobody yet trusts
+/// `signer` from now on.
+pub fn check_trusted(app_handle: &AppHandle, project: &str, signer: &str) -> Result<(), String> {
+	if cached_trust(app_handle, TrustList::Emitters, project, signer) {
+		return Ok(());
+	}
+
+	let first_use = trust_policy(app_handle, project) == TrustPolicy::FirstUse && {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		trust::trust_first(&connection, project, signer, FIRST_USE_LABEL)
+			.map_err(|e| format!("Error guardando el emisor de confianza: {}", e))?
+	};
+	if first_use {
+		println!(
+			"Signing: Emitter {} trusted on first use for project '{}'",
+			signer, project
+		);
+		let _ = app_handle.emit(
+			"emitter-trusted",
+			serde_json::json!({ "projectName": project, "signer": signer }),
+		);
+	}
+	if load_trust(app_handle, TrustList::Emitters, project, signer)? {
+		Ok(())
+	} else {
+		Err(format!(
+			"El emisor {} no es de confianza para el proyecto '{}'",
+			signer, project
+		))
+	}
+}
+
+/// Fails unless `signer` is in the requester trust list of `project`, the receivers an
+/// emitter answers snapshot requests from. Kept apart from the emitters, so trusting a
+/// receiver's requests never lets it publish lines, nor the other way round.
+pub fn check_requester(app_handle: &AppHandle, project: &str, signer: &str) -> Result<(), String> {
+	if cached_trust(app_handle, TrustList::Requesters, project, signer)
+		|| load_trust(app_handle, TrustList::Requesters, project, signer)?
+	{
+		Ok(())
+	} else {
+		Err(format!(
+			"El receptor {} no puede pedir instantáneas del proyecto '{}'",
+			signer, project
+		))
+	}
+}
+
+fn cached_trust(app_handle: &AppHandle, list: TrustList, project: &str, signer: &str) -> bool {
+	app_handle
+		.state::<TrustLists>()
+		.0
+		.lock()
+		.unwrap()
+		.get(&(list, project.to_string()))
+		.is_some_and(|trusted| trusted.contains(signer))
+}
+
+// Reads the list again into the cache and tells whether it holds `signer`
+fn load_trust(
+	app_handle: &AppHandle,
+	list: TrustList,
+	project: &str,
+	signer: &str,
+) -> Result<bool, String> {
+	let trusted = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		trust::trusted(&connection, list, project)
+			.map_err(|e| format!("Error leyendo la lista de confianza: {}", e))?
+	};
+	let trusted: HashSet<String> = trusted.into_iter().map(|entry| entry.public_key).collect();
+	let accepted = trusted.contains(signer);
+	app_handle
+		.state::<TrustLists>()
+		.0
+		.lock()
+		.unwrap()
+		.insert((list, project.to_string()), trusted);
+	Ok(accepted)
+}
+
+// The next check of `project` reads its list again
+fn forget_trust_list(app_handle: &AppHandle, list: TrustList, project: &str) {
+	app_handle
+		.state::<TrustLists>()
+		.0
+		.lock()
+		.unwrap()
+		.remove(&(list, project.to_string()));
+}
+
+fn store_signing_key(app_handle: &AppHandle, key: &SigningKey) -> Result<String, String> {
+	let identity = app_handle.state::<SigningIdentity>();
+	let mut cached = identity.0.lock().unwrap();
+	keychain::set(SIGNING_KEY_NAME, &key.to_bytes())?;
+	*cached = Some(key.clone());
+	Ok(signing::public_key(key))
+}
+
+/// Replaces this installation's identity; receivers must trust the new public key.
+#[command]
+pub fn generate_signing_key(app_handle: AppHandle) -> Result<String, String> {
+	let public_key = store_signing_key(&app_handle, &signing::generate_key())?;
+	println!("Signing: Emitter identity replaced by {}", public_key);
+	Ok(public_key)
+}
+
+/// Public key to add to the receivers' trust lists.
+#[command]
+pub fn get_signing_public_key(app_handle: AppHandle) -> Result<String, String> {
+	Ok(signing::public_key(&signing_key(&app_handle)?))
+}
+
+/// Secret key (base64), to move this identity to another installation.
+#[command]
+pub fn export_signing_key(app_handle: AppHandle) -> Result<String, String> {
+	Ok(BASE64.encode(signing_key(&app_handle)?.to_bytes()))
+}
+
+#[command]
+pub fn import_signing_key(app_handle: AppHandle, secret_key: String) -> Result<String, String> {
+	let bytes = BASE64
+		.decode(secret_key.trim())
+		.map_err(|_| "La clave de firma no es base64 válido".to_string())?;
+	let public_key = store_signing_key(&app_handle, &signing::key_from_bytes(&bytes)?)?;
+	println!("Signing: Emitter identity imported ({})", public_key);
+	Ok(public_key)
+}
+
+fn add_trusted(
+	app_handle: &AppHandle,
+	list: TrustList,
+	project_name: &str,
+	public_key: &str,
+	label: Option<&str>,
+) -> Result<String, String> {
+	if project_name.trim().is_empty() {
+		return Err("El nombre del proyecto no puede estar vacío".to_string());
+	}
+	// Stored in the same canonical form messages carry
+	let public_key = BASE64.encode(signing::parse_public_key(public_key)?.to_bytes());
+
+	{
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		trust::trust(&connection, list, project_name, &public_key, label)
+			.map_err(|e| format!("Error guardando la clave de confianza: {}", e))?;
+	}
+	forget_trust_list(app_handle, list, project_name);
+	Ok(public_key)
+}
+
+fn remove_trusted(
+	app_handle: &AppHandle,
+	list: TrustList,
+	project_name: &str,
+	public_key: &str,
+) -> Result<bool, String> {
+	let removed = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		trust::untrust(&connection, list, project_name, public_key.trim())
+			.map_err(|e| format!("Error eliminando la clave de confianza: {}", e))?
+	};
+	forget_trust_list(app_handle, list, project_name);
+	Ok(removed)
+}
+
+fn list_trusted(
+	app_handle: &AppHandle,
+	list: TrustList,
+	project_name: &str,
+) -> Result<Vec<TrustedEmitter>, String> {
+	let database = app_handle.state::<Database>();
+	let connection = database.0.lock().unwrap();
+	trust::trusted(&connection, list, project_name).map_err(|e| e.to_string())
+}
+
+#[command]
+pub fn trust_emitter(
+	app_handle: AppHandle,
+	project_name: String,
+	public_key: String,
+	label: Option<String>,
+) -> Result<(), String> {
+	let public_key = add_trusted(
+		&app_handle,
+		TrustList::Emitters,
+		&project_name,
+		&public_key,
+		label.as_deref(),
+	)?;
+	println!(
+		"Signing: Emitter {} trusted for project '{}'",
+		public_key, project_name
+	);
+	Ok(())
+}
+
+#[command]
+pub fn untrust_emitter(
+	app_handle: AppHandle,
+	project_name: String,
+	public_key: String,
+) -> Result<bool, String> {
+	remove_trusted(&app_handle, TrustList::Emitters, &project_name, &public_key)
+}
+
+#[command]
+pub fn list_trusted_emitters(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<Vec<TrustedEmitter>, String> {
+	list_trusted(&app_handle, TrustList::Emitters, &project_name)
+}
+
+/// Lets the receiver with `public_key` request snapshots of the project from this emitter.
+#[command]
+pub fn trust_requester(
+	app_handle: AppHandle,
+	project_name: String,
+	public_key: String,
+	label: Option<String>,
+) -> Result<(), String> {
+	let public_key = add_trusted(
+		&app_handle,
+		TrustList::Requesters,
+		&project_name,
+		&public_key,
+		label.as_deref(),
+	)?;
+	println!(
+		"Signing: Receiver {} may request snapshots of project '{}'",
+		public_key, project_name
+	);
+	Ok(())
+}
+
+#[command]
+pub fn untrust_requester(
+	app_handle: AppHandle,
+	project_name: String,
+	public_key: String,
+) -> Result<bool, String> {
+	remove_trusted(
+		&app_handle,
+		TrustList::Requesters,
+		&project_name,
+		&public_key,
+	)
+}
+
+#[command]
+pub fn list_trusted_requesters(
+	app_handle: AppHandle,
+	project_name: String,
+) -> Result<Vec<TrustedEmitter>, String> {
+	list_trusted(&app_handle, TrustList::Requesters, &project_name)
+}
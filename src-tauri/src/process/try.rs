@@ -3,46 +3,62 @@ use std::sync::Arc;
 use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::WindowEvent;
-use tauri::{Emitter, Manager, State};
+use tauri::{Emitter, Manager, State, Wry};
 use tokio::time::{sleep, Duration};
 use walkdir::WalkDir;
 
 use crate::process::mode::{AppMode, AppModeState};
+
+// Tray menu; the first item shows or hides the window depending on `window_visible`
+fn tray_menu(app: &tauri::AppHandle, window_visible: bool) -> tauri::Result<Menu<Wry>> {
+	let show_label = if window_visible {
+		"Ocultar ventana"
+	} else {
+		"Mostrar ventana"
+	};
+	let show = MenuItem::with_id(app, "show", show_label, true, None::<&str>)?;
+	let open_logs = MenuItem::with_id(
+		app,
+		"open_logs",
+		"Abrir carpeta de logs",
+		true,
+		None::<&str>,
+	)?;
+	let set_emitter_mode =
+		MenuItem::with_id(app, "set_emitter_mode", "Modo Emisor", true, None::<&str>)?;
+	let set_receiver_mode = MenuItem::with_id(
+		app,
+		"set_receiver_mode",
+		"Modo Receptor",
+		true,
+		None::<&str>,
+	)?;
+	let set_both_mode = MenuItem::with_id(
+		app,
+		"set_both_mode",
+		"Modo Emisor y Receptor",
+		true,
+		None::<&str>,
+	)?;
+	let quit = MenuItem::with_id(app, "quit", "Salir", true, None::<&str>)?;
+	Menu::with_items(
+		app,
+		&[
+			&show,
+			&open_logs,
+			&set_emitter_mode,
+			&set_receiver_mode,
+			&set_both_mode,
+			&quit,
+		],
+	)
+}
+
 pub fn init_tray(
 	app: &tauri::AppHandle,
 	app_mode_state: State<'_, AppModeState>,
 ) -> tauri::Result<()> {
-	// Initial menu state
-	let initial_menu = {
-		let show = MenuItem::with_id(app, "show", "Ocultar ventana", true, None::<&str>)?;
-		let open_logs = MenuItem::with_id(
-			app,
-			"open_logs",
-			"Abrir carpeta de logs",
-			true,
-			None::<&str>,
-		)?;
-		let set_emitter_mode =
-			MenuItem::with_id(app, "set_emitter_mode", "Modo Emisor", true, None::<&str>)?;
-		let set_receiver_mode = MenuItem::with_id(
-			app,
-			"set_receiver_mode",
-			"Modo Receptor",
-			true,
-			None::<&str>,
-		)?;
-		let quit = MenuItem::with_id(app, "quit", "Salir", true, None::<&str>)?;
-		Menu::with_items(
-			app,
-			&[
-				&show,
-				&open_logs,
-				&set_emitter_mode,
-				&set_receiver_mode,
-				&quit,
-			],
-		)?
-	};
+	let initial_menu = tray_menu(app, true)?;
 
 	let app_mode_state_for_menu_event =
 		Arc::new(std::sync::Mutex::new(*app_mode_state.0.lock().unwrap()));
@@ -79,94 +95,14 @@ pub fn init_tray(
 			let window = app.get_webview_window("main").unwrap();
 			match event.id().as_ref() {
 				"show" => {
-					let new_menu = if window.is_visible().unwrap_or(false) {
-						window.hide().unwrap();
-						// Create a menu for when the window is hidden
-						let show_item =
-							MenuItem::with_id(app, "show", "Mostrar ventana", true, None::<&str>)
-								.unwrap();
-						let open_logs_item = MenuItem::with_id(
-							app,
-							"open_logs",
-							"Abrir carpeta de logs",
-							true,
-							None::<&str>,
-						)
-						.unwrap();
-						let set_emitter_mode_item = MenuItem::with_id(
-							app,
-							"set_emitter_mode",
-							"Modo Emisor",
-							true,
-							None::<&str>,
-						)
-						.unwrap();
-						let set_receiver_mode_item = MenuItem::with_id(
-							app,
-							"set_receiver_mode",
-							"Modo Receptor",
-							true,
-							None::<&str>,
-						)
-						.unwrap();
-						let quit_item =
-							MenuItem::with_id(app, "quit", "Salir", true, None::<&str>).unwrap();
-						Menu::with_items(
-							app,
-							&[
-								&show_item,
-								&open_logs_item,
-								&set_emitter_mode_item,
-								&set_receiver_mode_item,
-								&quit_item,
-							],
-						)
-						.unwrap()
-					} else {
+					let visible = !window.is_visible().unwrap_or(false);
+					if visible {
 						window.show().unwrap();
 						window.set_focus().unwrap();
-						// Create a menu for when the window is visible
-						let show_item =
-							MenuItem::with_id(app, "show", "Ocultar ventana", true, None::<&str>)
-								.unwrap();
-						let open_logs_item = MenuItem::with_id(
-							app,
-							"open_logs",
-							"Abrir carpeta de logs",
-							true,
-							None::<&str>,
-						)
-						.unwrap();
-						let set_emitter_mode_item = MenuItem::with_id(
-							app,
-							"set_emitter_mode",
-							"Modo Emisor",
-							true,
-							None::<&str>,
-						)
-						.unwrap();
-						let set_receiver_mode_item = MenuItem::with_id(
-							app,
-							"set_receiver_mode",
-							"Modo Receptor",
-							true,
-							None::<&str>,
-						)
-						.unwrap();
-						let quit_item =
-							MenuItem::with_id(app, "quit", "Salir", true, None::<&str>).unwrap();
-						Menu::with_items(
-							app,
-							&[
-								&show_item,
-								&open_logs_item,
-								&set_emitter_mode_item,
-								&set_receiver_mode_item,
-								&quit_item,
-							],
-						)
-						.unwrap()
-					};
+					} else {
+						window.hide().unwrap();
+					}
+					let new_menu = tray_menu(app, visible).unwrap();
 					// Set the new menu on the tray
 					app.tray_by_id("main-tray")
 						.unwrap()
@@ -238,6 +174,12 @@ pub fn init_tray(
 					println!("Modo de aplicación cambiado a: {:?}", *app_mode);
 					let _ = app.emit("app_mode_changed", AppMode::Receiver);
 				}
+				"set_both_mode" => {
+					let mut app_mode = app_mode_state_for_menu_event.lock().unwrap();
+					*app_mode = AppMode::Both;
+					println!("Modo de aplicación cambiado a: {:?}", *app_mode);
+					let _ = app.emit("app_mode_changed", AppMode::Both);
+				}
 				"quit" => {
 					println!("quit menu item was clicked");
 					app.exit(0);
@@ -263,46 +205,7 @@ pub fn init_window_event(app: &tauri::AppHandle) {
 				window.hide().unwrap();
 
 				// Rebuild the menu to update the text
-				let show_item =
-					MenuItem::with_id(&app_handle, "show", "Mostrar ventana", true, None::<&str>)
-						.unwrap();
-				let open_logs_item = MenuItem::with_id(
-					&app_handle,
-					"open_logs",
-					"Abrir carpeta de logs",
-					true,
-					None::<&str>,
-				)
-				.unwrap();
-				let set_emitter_mode_item = MenuItem::with_id(
-					&app_handle,
-					"set_emitter_mode",
-					"Modo Emisor",
-					true,
-					None::<&str>,
-				)
-				.unwrap();
-				let set_receiver_mode_item = MenuItem::with_id(
-					&app_handle,
-					"set_receiver_mode",
-					"Modo Receptor",
-					true,
-					None::<&str>,
-				)
-				.unwrap();
-				let quit_item =
-					MenuItem::with_id(&app_handle, "quit", "Salir", true, None::<&str>).unwrap();
-				let menu = Menu::with_items(
-					&app_handle,
-					&[
-						&show_item,
-						&open_logs_item,
-						&set_emitter_mode_item,
-						&set_receiver_mode_item,
-						&quit_item,
-					],
-				)
-				.unwrap();
+				let menu = tray_menu(&app_handle, false).unwrap();
 
 				app_handle
 					.tray_by_id("main-tray")
@@ -1,19 +1,41 @@
 use notify::{RecursiveMode, Watcher};
-use rand::Rng;
-use rumqttc::{Client, MqttOptions, QoS, Transport};
-use rustls;
-use rustls_native_certs;
-use std::fs::File;
+use rumqttc::QoS;
 use std::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::mpsc;
 
+use crate::db::sequences;
+use crate::db::store::Database;
+use crate::mqtt::compression::Compression;
+use crate::mqtt::connection::MAX_PACKET_SIZE;
+use crate::mqtt::envelope::{new_message_id, BatchLine, EventKind, LogEnvelope};
+use crate::mqtt::service::MqttService;
+use crate::mqtt::signing;
+use crate::process::batch::{Batch, Batcher};
+use crate::process::debounce::{Debouncer, FileChange};
+use crate::process::emitter_settings::{host_id, load_emitter_settings};
+use crate::process::file_rules::EmitterFileFilter;
+use crate::process::outbox;
+use crate::process::project_keys::project_key;
+use crate::process::project_settings::{
+	emitter_file_filter, project_encoding, project_log_format,
+};
+use crate::process::records::RecordAssembler;
+use crate::process::snapshot;
+use crate::process::tail::{FileTailer, TailLine};
+use crate::process::trust::signing_key;
 use crate::ActiveProjectPath;
 
-use encoding_rs::WINDOWS_1252;
-use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const EMITTER_OWNER: &str = "emitter";
+// Longest line or record published; like a batch, it leaves room for the envelope, base64
+// and encryption overhead
+const MAX_LINE_BYTES: usize = MAX_PACKET_SIZE / 2;
+// What a publish packet adds to its topic and payload: fixed header, topic length, packet id
+const PUBLISH_OVERHEAD: usize = 16;
 
 // State to hold the sender for stopping the watcher
 pub struct WatcherStopper(pub Mutex<Option<mpsc::Sender<()>>>);
@@ -24,161 +46,347 @@ impl Default for WatcherStopper {
 	}
 }
 
-fn get_last_line(path: &Path) -> Option<String> {
-	let file = File::open(path).ok()?;
-	let mut reader = BufReader::new(file);
-	let file_len = reader.seek(SeekFrom::End(0)).ok()?;
+/// Folder the emitter publishes, while it runs. In Both mode the receiver never writes
+/// into it, or every mirrored line would be published again.
+#[derive(Default)]
+pub struct WatchedRoot(pub Mutex<Option<PathBuf>>);
+
+// Project name and '/'-separated path relative to the project root, as used in MQTT topics
+pub fn project_relative_path(root: &Path, path: &Path) -> (String, String) {
+	let project_name = root.file_name().unwrap().to_string_lossy().to_string();
+	let relative_path = path
+		.strip_prefix(root)
+		.unwrap_or(path)
+		.to_string_lossy()
+		.replace("\\", "/");
+	(project_name, relative_path)
+}
 
-	if file_len == 0 {
-		return None; // Empty file
-	}
+// Terminator sent along with the line; "\n" is what receivers assume
+fn newline(line: &TailLine) -> Option<String> {
+	(line.newline != "\n").then(|| line.newline.to_string())
+}
 
-	let mut buffer = Vec::new();
-	let _bytes_read = 0;
-
-	// Read chunks from the end of the file
-	let mut pos = file_len;
-	while pos > 0 {
-		let read_size = std::cmp::min(pos, 512); // Read in 512-byte chunks
-		pos -= read_size;
-		reader.seek(SeekFrom::Start(pos)).ok()?;
-		buffer.resize(read_size as usize, 0);
-		reader.read_exact(&mut buffer).ok()?;
-
-		// Search for newline in the buffer, from right to left
-		for i in (0..read_size as usize).rev() {
-			if buffer[i] == b'\n' {
-				let last_line_start = i + 1;
-				// If we found a newline, and it's not the very end of the file (meaning there's content after it)
-				// then the line starts after this newline.
-				// If it's the very last byte, it means the file ends with a newline, so we need to look further back.
-				if (pos + last_line_start as u64) < file_len {
-					// Found the start of the last line
-					let line_bytes = &buffer[last_line_start..];
-					return Some(WINDOWS_1252.decode(line_bytes).0.into_owned());
-				}
+// What the messages of one log event share
+struct FileEvent<'a> {
+	app_handle: &'a AppHandle,
+	project_name: String,
+	relative_path: String,
+	kind: EventKind,
+	epoch: String,
+	record: bool,
+	compression: Option<Compression>,
+}
+
+// Stores one log event in the outbox; the drainer publishes it in order. Several lines go
+// out as one batch; no lines means an event without content (a removal). Line offsets let
+// receivers line the lines up with a snapshot
+fn enqueue_file_event(
+	app_handle: &AppHandle,
+	root: &Path,
+	kind: EventKind,
+	path: &Path,
+	lines: &[TailLine],
+	record: bool,
+	compression: Option<Compression>,
+) {
+	let (project_name, relative_path) = project_relative_path(root, path);
+	let stream = format!("{}/{}", project_name, relative_path);
+	let (sequence, epoch) = {
+		let database = app_handle.state::<Database>();
+		let connection = database.0.lock().unwrap();
+		let count = lines.len().max(1) as u64;
+		match sequences::reserve(&connection, &stream, count, &new_message_id()) {
+			Ok(reserved) => reserved,
+			Err(e) => {
+				eprintln!("Error asignando el número de secuencia de {}: {}", stream, e);
+				return;
 			}
 		}
-		// If no newline found in this chunk, continue to the previous chunk
-	}
+	};
+	let event = FileEvent {
+		app_handle,
+		project_name,
+		relative_path,
+		kind,
+		epoch,
+		record: record && !lines.is_empty(),
+		compression,
+	};
+	enqueue_lines(&event, sequence, lines);
+}
 
-	// If we reached the beginning of the file and found no newlines, the whole file is one line
+// Queues `lines`, numbered from `sequence`, as one message. A batch whose message would not
+// fit in an MQTT packet is halved; a single line that does not fit is left out, since it
+// would fail to publish forever and hold back everything queued after it
+fn enqueue_lines(event: &FileEvent, sequence: u64, lines: &[TailLine]) {
+	let stream = format!("{}/{}", event.project_name, event.relative_path);
+	let Some(envelope) = build_envelope(event, sequence, lines) else {
+		return;
+	};
+	let signing_key = match signing_key(event.app_handle) {
+		Ok(key) => key,
+		Err(e) => {
+			eprintln!("Error firmando el mensaje de {}: {}", stream, e);
+			return;
+		}
+	};
+	let mut payload = serde_json::to_value(&envelope).unwrap();
+	signing::sign_value(&signing_key, &mut payload);
+	let payload = serde_json::to_vec(&payload).unwrap();
+
+	let topic = envelope.topic();
+	if topic.len() + payload.len() + PUBLISH_OVERHEAD > MAX_PACKET_SIZE {
+		if lines.len() > 1 {
+			let (first, rest) = lines.split_at(lines.len() / 2);
+			enqueue_lines(event, sequence, first);
+			enqueue_lines(event, sequence + first.len() as u64, rest);
+		} else {
+			eprintln!(
+				"Mensaje {} de {} descartado: ocupa {} bytes y el máximo es {}",
+				sequence,
+				stream,
+				payload.len(),
+				MAX_PACKET_SIZE
+			);
+		}
+		return;
+	}
+	if let Err(e) = outbox::enqueue(event.app_handle, &topic, &payload) {
+		eprintln!("Error encolando mensaje MQTT: {}", e);
+	}
+}
 
-	if file_len > 0 {
-		reader.seek(SeekFrom::Start(0)).ok()?; // Reset cursor to beginning
+// The envelope of `lines`, compressed and encrypted as the project asks
+fn build_envelope(event: &FileEvent, sequence: u64, lines: &[TailLine]) -> Option<LogEnvelope> {
+	let app_handle = event.app_handle;
+	let host_id = host_id(app_handle);
+	let project_name = &event.project_name;
+	let relative_path = &event.relative_path;
+	let kind = event.kind;
+	let mut envelope = match lines {
+		[] => LogEnvelope::new(&host_id, project_name, relative_path, kind, sequence, None, ""),
+		[line] => {
+			let mut envelope = LogEnvelope::new(
+				&host_id,
+				project_name,
+				relative_path,
+				kind,
+				sequence,
+				Some(line.end_offset),
+				&line.content,
+			);
+			envelope.newline = newline(line);
+			envelope
+		}
+		lines => {
+			let lines: Vec<BatchLine> = lines
+				.iter()
+				.map(|line| BatchLine {
+					content: line.content.clone(),
+					offset: line.end_offset,
+					newline: newline(line),
+				})
+				.collect();
+			LogEnvelope::batch(&host_id, project_name, relative_path, kind, sequence, &lines)
+		}
+	};
+	envelope.epoch = Some(event.epoch.clone());
+	envelope.record = event.record;
+	envelope.raise_version();
+	if let Some(line) = lines.first() {
+		envelope.encoding = line.encoding.name().to_string();
+	}
+	if let Some(compression) = event.compression {
+		if let Err(e) = envelope.compress(compression) {
+			eprintln!("{}; se envía sin comprimir.", e);
+		}
+	}
+	match project_key(app_handle, project_name) {
+		Ok(Some(key)) => envelope.encrypt(&key),
+		Ok(None) => {}
+		Err(e) => {
+			// Never fall back to plaintext for a project that may be encrypted
+			eprintln!(
+				"Error cifrando el mensaje de {}: {}",
+				envelope.mqtt_path(),
+				e
+			);
+			return None;
+		}
+	}
+	Some(envelope)
+}
 
-		let mut all_bytes = Vec::new();
+// Turns file changes into outbox messages: new lines → whole records (optional) → batches
+struct Publisher {
+	app_handle: AppHandle,
+	root: PathBuf,
+	tailer: FileTailer,
+	records: Option<RecordAssembler>,
+	batcher: Batcher,
+	compression: Option<Compression>,
+}
 
-		reader.read_to_end(&mut all_bytes).ok()?;
+impl Publisher {
+	fn next_due(&self) -> Option<Instant> {
+		let records = self.records.as_ref().and_then(RecordAssembler::next_due);
+		[records, self.batcher.next_due()].into_iter().flatten().min()
+	}
 
-		return Some(WINDOWS_1252.decode(&all_bytes).0.into_owned());
+	// Every complete line written since the previous burst
+	fn read(&mut self, path: &Path, kind: EventKind) {
+		let lines = match self.tailer.read_new_lines(path) {
+			Ok(lines) => lines,
+			Err(e) => {
+				eprintln!("Error leyendo {}: {}", path.display(), e);
+				return;
+			}
+		};
+		// Dropped before they take a sequence number, so receivers see no gap
+		let lines: Vec<TailLine> = lines
+			.into_iter()
+			.filter(|line| {
+				let fits = line.content.len() <= MAX_LINE_BYTES;
+				if !fits {
+					eprintln!(
+						"Línea de {} bytes de {} descartada: el máximo es {}",
+						line.content.len(),
+						path.display(),
+						MAX_LINE_BYTES
+					);
+				}
+				fits
+			})
+			.collect();
+		match self.records.as_mut() {
+			Some(records) => {
+				for (kind, record) in records.push(path, kind, lines) {
+					self.add(path, kind, vec![record]);
+				}
+			}
+			None => self.add(path, kind, lines),
+		}
 	}
 
-	None
-}
+	fn removed(&mut self, path: &Path) {
+		// Whatever is still waiting goes out before the removal
+		if let Some((kind, record)) = self.records.as_mut().and_then(|r| r.take(path)) {
+			self.add(path, kind, vec![record]);
+		}
+		let waiting = self.batcher.take(path);
+		self.publish(waiting);
+		self.tailer.forget(path);
+		enqueue_file_event(
+			&self.app_handle,
+			&self.root,
+			EventKind::Removed,
+			path,
+			&[],
+			false,
+			None,
+		);
+	}
 
-pub async fn start_watcher(
-	app_handle: AppHandle,
-	mut rx: mpsc::Receiver<()>,
-) -> Result<(), String> {
-	let monitored_project_path = app_handle.state::<ActiveProjectPath>();
-	println!("File Watcher: Initializing...");
+	fn publish_due(&mut self, now: Instant) {
+		let records = self.records.as_mut().map(|r| r.take_due(now));
+		for (path, kind, record) in records.unwrap_or_default() {
+			self.add(&path, kind, vec![record]);
+		}
+		let due = self.batcher.take_due(now);
+		self.publish(due);
+	}
 
-	// MQTT Client Setup
-	println!("MQTT: Setting up client options...");
-
-	// --- TLS and Authentication Setup (using system certificates) ---
-	// let broker_url = "3206f9b95f01467885968e05da78c36c.s1.eu.hivemq.cloud";
-	let broker_url = "b890714b58bc47ab852b24ea4753227b.s1.eu.hivemq.cloud";
-	let broker_port = 8883;
-	// IMPORTANT: Replace with your actual username and password
-	let mqtt_user = "zhent";
-	let mqtt_password = "Zhent1234";
-
-	// Generate a random client ID suffix to prevent collisions
-	let random_suffix: String = rand::thread_rng()
-		.sample_iter(&rand::distributions::Alphanumeric)
-		.take(6)
-		.map(char::from)
-		.collect();
-	let client_id = format!("tauri_emitter_{}", random_suffix);
-
-	let mut mqtt_options = MqttOptions::new(client_id, broker_url, broker_port);
-	mqtt_options.set_credentials(mqtt_user, mqtt_password);
-	mqtt_options.set_keep_alive(Duration::from_secs(5));
-
-	// Configure TLS to use the system's native certificate store
-	// This is how we achieve `usetls: true` without providing certificate files
-	let mut root_cert_store = rustls::RootCertStore::empty();
-	for cert in rustls_native_certs::load_native_certs().expect("could not load platform certs") {
-		root_cert_store.add(&rustls::Certificate(cert.0)).unwrap();
+	// Nothing waiting is lost when the watcher stops
+	fn publish_all(&mut self) {
+		let records = self.records.as_mut().map(RecordAssembler::drain);
+		for (path, kind, record) in records.unwrap_or_default() {
+			self.add(&path, kind, vec![record]);
+		}
+		let waiting = self.batcher.drain();
+		self.publish(waiting);
 	}
 
-	let client_config = rustls::ClientConfig::builder()
-		.with_safe_defaults()
-		.with_root_certificates(root_cert_store)
-		.with_no_client_auth();
+	fn add(&mut self, path: &Path, kind: EventKind, lines: Vec<TailLine>) {
+		let full = self.batcher.push(path, kind, lines);
+		self.publish(full);
+	}
 
-	mqtt_options.set_transport(Transport::tls_with_config(client_config.into()));
-	// --- End of TLS and Authentication Setup ---
+	fn publish(&self, batches: impl IntoIterator<Item = Batch>) {
+		for batch in batches {
+			enqueue_file_event(
+				&self.app_handle,
+				&self.root,
+				batch.kind,
+				&batch.path,
+				&batch.lines,
+				self.records.is_some(),
+				self.compression,
+			);
+		}
+	}
+}
 
-	let (client, mut connection) = Client::new(mqtt_options, 10);
-	println!("MQTT: Client created.");
+// Handles the files whose burst of events ended: one UI notification per file (plus one
+// refresh for the batch) and, in Emitter mode, one read of the new lines
+fn flush_changes(
+	app_handle: &AppHandle,
+	publisher: &mut Publisher,
+	changes: Vec<(PathBuf, FileChange)>,
+) {
+	if changes.is_empty() {
+		return;
+	}
+	for (path, change) in &changes {
+		let event = match change {
+			FileChange::Created | FileChange::Recreated => "file-created",
+			FileChange::Removed => "file-deleted",
+			FileChange::Modified => continue,
+		};
+		if let Err(e) = app_handle.emit(event, path.to_string_lossy().to_string()) {
+			eprintln!("Error emitiendo {}: {}", event, e);
+		}
+	}
+	if let Err(e) = app_handle.emit("directory-changed", ()) {
+		eprintln!("Error emitiendo directory-changed: {}", e);
+	}
 
-	// Channel to send MQTT events from the blocking thread to the async task
-	let (event_tx, mut event_rx) = mpsc::channel(100);
+	let app_mode_state = app_handle.state::<crate::process::mode::AppModeState>();
+	let current_mode = *app_mode_state.0.lock().unwrap();
+	if !current_mode.is_emitter() {
+		return;
+	}
 
-	// Spawn a blocking task to handle the MQTT connection's blocking recv()
-	tauri::async_runtime::spawn_blocking(move || {
-		println!("MQTT Connection Handler (Blocking Thread): Started.");
-		for notification in connection.iter() {
-			// connection.iter() yields blocking events
-			if let Err(e) = event_tx.blocking_send(notification) {
-				eprintln!("MQTT Connection Handler (Blocking Thread): Failed to send event to async task: {}", e);
-				break;
-			}
+	let file_filter = app_handle.state::<EmitterFileFilter>();
+	for (path, change) in changes {
+		if !file_filter
+			.0
+			.lock()
+			.unwrap()
+			.matches_path(&publisher.root, &path)
+		{
+			continue;
 		}
-		println!("MQTT Connection Handler (Blocking Thread): Stopped.");
-	});
+		println!("✅ Cambio detectado en modo Emitter: {:?} ({:?})", path, change);
 
-	// Async task to process MQTT events received from the blocking thread
-	tauri::async_runtime::spawn(async move {
-		println!("MQTT Event Processor (Async Task): Started.");
-		while let Some(notification) = event_rx.recv().await {
-			match notification {
-				Ok(rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(_))) => {
-					println!("MQTT Event Processor: Connected to broker!");
-					// Here you could signal that connection is established if needed
-				}
-				Ok(event) => {
-					match event {
-						rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(_)) => {
-							println!("MQTT Event Processor: Connected to broker!");
-							// Here you could signal that connection is established if needed
-						}
-						rumqttc::Event::Incoming(rumqttc::Packet::PingResp) => {
-							// Ignore PingResp
-						}
-						rumqttc::Event::Outgoing(rumqttc::Outgoing::PingReq) => {
-							// Ignore PingReq
-						}
-						_ => {
-							// Catch all other events
-							println!("MQTT Event Processor: Received event: {:?}", event);
-						}
-					}
-				}
-				Err(e) => {
-					eprintln!("MQTT Event Processor: Connection error: {:?}", e);
-					// Handle connection errors, e.g., try to reconnect
-				}
+		match change {
+			FileChange::Removed => publisher.removed(&path),
+			FileChange::Recreated => {
+				publisher.removed(&path);
+				publisher.read(&path, EventKind::Created);
 			}
+			FileChange::Created => publisher.read(&path, EventKind::Created),
+			FileChange::Modified => publisher.read(&path, EventKind::Modified),
 		}
-		println!("MQTT Event Processor (Async Task): Stopped.");
-	});
+	}
+}
 
-	// No explicit waiting for connection here, rely on the event stream
-	println!("MQTT: Connection handling spawned. Proceeding with watcher setup.");
+pub async fn start_watcher(
+	app_handle: AppHandle,
+	mut rx: mpsc::Receiver<()>,
+) -> Result<(), String> {
+	let monitored_project_path = app_handle.state::<ActiveProjectPath>();
+	println!("File Watcher: Initializing...");
 
 	let root_path = {
 		let path_guard = monitored_project_path.0.lock().unwrap();
@@ -200,126 +408,70 @@ pub async fn start_watcher(
 		));
 	}
 
-	let app_handle_clone = app_handle.clone();
-	let client_clone = client.clone(); // Clone the client for the event handler
-	let root_clone = root.clone(); // Clone root for the event handler
-	let _topic = "file/events".to_string(); // Define the MQTT topic
+	// Publishing goes through the shared MQTT connection
+	let service = app_handle.state::<MqttService>();
+	let snapshot_requests = service.incoming(EMITTER_OWNER);
+	let (project_name, _) = project_relative_path(&root, &root);
+	let connected = service.acquire(&app_handle, EMITTER_OWNER).and_then(|_| {
+		service.subscribe(
+			EMITTER_OWNER,
+			&snapshot::request_topic(&project_name),
+			QoS::AtLeastOnce,
+		)
+	});
+	if let Err(e) = connected {
+		release_emitter(&app_handle);
+		return Err(e);
+	}
+	*app_handle.state::<WatchedRoot>().0.lock().unwrap() = Some(root.clone());
 
-	tauri::async_runtime::spawn(async move {
-		let event_handler = move |res: Result<notify::Event, notify::Error>| {
-			let app_handle_clone_inner = app_handle_clone.clone();
-			let root_clone_inner = root_clone.clone(); // Clone for each event
-
-			if let Ok(event) = res {
-				match event.kind {
-					notify::EventKind::Create(_) => {
-						for path in &event.paths {
-							if let Some(path_str) = path.to_str() {
-								if let Err(e) = app_handle_clone_inner.emit("file-created", path_str) {
-									eprintln!("Error emitiendo file-created: {}", e);
-								}
-							}
-						}
-					}
-					notify::EventKind::Remove(_) => {
-						for path in &event.paths {
-							if let Some(path_str) = path.to_str() {
-								if let Err(e) = app_handle_clone_inner.emit("file-deleted", path_str) {
-									eprintln!("Error emitiendo file-deleted: {}", e);
-								}
-							}
-						}
-					}
-					notify::EventKind::Modify(_) => {
-						if let Err(e) = app_handle_clone_inner.emit("directory-changed", ()) {
-							eprintln!("Error emitiendo directory-changed: {}", e);
-						}
-					}
-					_ => {} // Ignore other event kinds for the main UI refresh
-				}
+	// The project's include/exclude rules decide which files are published
+	let filter = emitter_file_filter(&app_handle, &root);
 
-				// Conditionally handle MQTT publishing based on AppMode
-				let app_mode_state =
-					app_handle_clone_inner.state::<crate::process::mode::AppModeState>();
-				let current_mode = *app_mode_state.0.lock().unwrap();
-
-				if matches!(current_mode, crate::process::mode::AppMode::Emitter) {
-					match event.kind {
-						notify::EventKind::Create(_)
-						| notify::EventKind::Modify(_)
-						| notify::EventKind::Remove(_) => {
-							println!("✅ Cambio detectado en modo Emitter: {:?}", event.paths);
-
-							for path in event.paths {
-								if let Some(extension) = path.extension() {
-									if extension == "log" || extension == "txt" {
-										let event_type = match event.kind {
-											notify::EventKind::Create(_) => "created",
-											notify::EventKind::Modify(_) => "modified",
-											notify::EventKind::Remove(_) => "removed",
-											_ => "unknown",
-										};
-
-										let relative_path = path
-											.strip_prefix(&root_clone_inner)
-											.unwrap_or(&path)
-											.to_string_lossy()
-											.replace("\\", "/");
-
-										let file_content = if event_type != "removed" {
-											get_last_line(&path).unwrap_or_default()
-										} else {
-											"".to_string()
-										};
-
-										let payload = serde_json::json!({
-											"event_type": event_type,
-											"path": format!("{}/{}", root_clone_inner.file_name().unwrap().to_string_lossy(), relative_path),
-											"content": file_content,
-										})
-										.to_string();
-
-										let mut client_for_publish = client_clone.clone();
-										let topic = format!(
-											"project/{}/logs/{}",
-											root_clone_inner.file_name().unwrap().to_string_lossy(),
-											relative_path
-										);
-
-										let topic_clone = topic.clone();
-										let payload_clone = payload.clone();
-
-										tauri::async_runtime::spawn(async move {
-											let publish_result = client_for_publish.publish(
-												topic_clone.clone(),
-												QoS::AtLeastOnce,
-												false,
-												payload_clone.as_bytes(),
-											);
-
-											if let Err(e) = publish_result {
-												eprintln!("Error publicando mensaje MQTT: {}", e);
-											} else {
-												println!(
-													"✅ Publicado MQTT: {} -> {}",
-													topic_clone, payload_clone
-												);
-											}
-										});
-									}
-								}
-							}
-						}
-						_ => {} // Ignorar otros tipos de eventos
-					}
-				}
+	// Existing log files are tailed from their current end, decoded with the project's
+	// encoding or the one detected for each file
+	let mut tailer = FileTailer::new(project_encoding(&app_handle, &root));
+	for entry in WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
+		if entry.file_type().is_file() && filter.matches_path(&root, entry.path()) {
+			tailer.prime(entry.path());
+		}
+	}
+	*app_handle.state::<EmitterFileFilter>().0.lock().unwrap() = filter;
+
+	let emitter_settings = load_emitter_settings(&app_handle);
+	let window = Duration::from_millis(emitter_settings.debounce_ms);
+	// Records are told apart with the project's log format
+	let records = emitter_settings.whole_records.then(|| {
+		RecordAssembler::new(
+			project_log_format(&app_handle, &root, None),
+			Duration::from_millis(emitter_settings.record_idle_ms),
+			MAX_LINE_BYTES,
+		)
+	});
+	let mut publisher = Publisher {
+		app_handle: app_handle.clone(),
+		root: root.clone(),
+		tailer,
+		records,
+		batcher: Batcher::new(&emitter_settings.batch),
+		compression: emitter_settings.batch.compression,
+	};
+
+	tauri::async_runtime::spawn(async move {
+		// Raw notify events are only forwarded; bursts are coalesced below
+		let (event_tx, mut events) = mpsc::unbounded_channel();
+		let event_handler = move |res: Result<notify::Event, notify::Error>| match res {
+			Ok(event) => {
+				let _ = event_tx.send(event);
 			}
+			Err(e) => eprintln!("File Watcher: {}", e),
 		};
 
 		let mut watcher = match notify::recommended_watcher(event_handler) {
 			Ok(w) => w,
 			Err(e) => {
 				eprintln!("Error al crear el file watcher: {}", e);
+				release_emitter(&app_handle);
 				return;
 			}
 		};
@@ -330,13 +482,52 @@ pub async fn start_watcher(
 				root.display(),
 				e
 			);
+			release_emitter(&app_handle);
 			return;
 		}
 
 		println!("✅ Monitorizando cambios en: {}", root.display());
 
-		// Keep the task alive until a stop signal is received
-		let _ = rx.recv().await;
+		let drainer = tauri::async_runtime::spawn(outbox::run_drainer(app_handle.clone()));
+		let snapshots = tauri::async_runtime::spawn(snapshot::serve_requests(
+			app_handle.clone(),
+			root.clone(),
+			snapshot_requests,
+		));
+
+		let mut debouncer = Debouncer::new(window);
+		loop {
+			// Far enough to never fire while nothing is pending
+			let next_due = [debouncer.next_due(), publisher.next_due()]
+				.into_iter()
+				.flatten()
+				.min()
+				.unwrap_or_else(|| Instant::now() + Duration::from_secs(3600));
+			tokio::select! {
+				Some(event) = events.recv() => {
+					let change = match event.kind {
+						notify::EventKind::Create(_) => FileChange::Created,
+						notify::EventKind::Remove(_) => FileChange::Removed,
+						notify::EventKind::Modify(_) => FileChange::Modified,
+						_ => continue, // Access and other events change nothing
+					};
+					for path in event.paths {
+						debouncer.push(path, change);
+					}
+				},
+				_ = tokio::time::sleep_until(next_due.into()) => {
+					let now = Instant::now();
+					flush_changes(&app_handle, &mut publisher, debouncer.take_due(now));
+					publisher.publish_due(now);
+				},
+				// Keep the task alive until a stop signal is received
+				_ = rx.recv() => break,
+			}
+		}
+		publisher.publish_all();
+		drop(watcher);
+		drainer.abort();
+		snapshots.abort();
 		println!("File Watcher: Stop signal received. Shutting down.");
 	});
 
@@ -347,7 +538,16 @@ pub fn stop_watcher(app_handle: &AppHandle) {
 	let stopper_state = app_handle.state::<WatcherStopper>();
 	let mut stopper = stopper_state.0.lock().unwrap();
 	if let Some(tx) = stopper.take() {
-		let _ = tx.send(());
+		let _ = tx.try_send(());
 	}
+	release_emitter(app_handle);
 	println!("File Watcher: Stopping...");
 }
+
+// The shared connection closes once no owner is left
+fn release_emitter(app_handle: &AppHandle) {
+	*app_handle.state::<WatchedRoot>().0.lock().unwrap() = None;
+	app_handle
+		.state::<MqttService>()
+		.release(app_handle, EMITTER_OWNER);
+}
@@ -0,0 +1,44 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+// Small helpers to persist settings as JSON files inside the app config directory
+pub fn config_file_path(app_handle: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
+	let dir = app_handle
+		.path()
+		.app_config_dir()
+		.map_err(|e| format!("No se pudo resolver el directorio de configuración: {}", e))?;
+	Ok(dir.join(file_name))
+}
+
+/// Loads `file_name` from the config directory, falling back to `T::default()` when the
+/// file does not exist yet.
+pub fn load_json<T: DeserializeOwned + Default>(
+	app_handle: &AppHandle,
+	file_name: &str,
+) -> Result<T, String> {
+	let path = config_file_path(app_handle, file_name)?;
+	if !path.exists() {
+		return Ok(T::default());
+	}
+
+	let content = fs::read_to_string(&path)
+		.map_err(|e| format!("Error leyendo {}: {}", path.display(), e))?;
+	serde_json::from_str(&content).map_err(|e| format!("Error en {}: {}", path.display(), e))
+}
+
+pub fn save_json<T: Serialize>(
+	app_handle: &AppHandle,
+	file_name: &str,
+	value: &T,
+) -> Result<(), String> {
+	let path = config_file_path(app_handle, file_name)?;
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+	}
+
+	let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+	fs::write(&path, content).map_err(|e| format!("Error escribiendo {}: {}", path.display(), e))
+}
@@ -1,4 +1,5 @@
 import Database from "@tauri-apps/plugin-sql";
+import { invoke } from "@tauri-apps/api/core";
 
 let db: Database | null = null;
 
@@ -45,6 +46,8 @@ export async function deleteProyect(name: string) {
   const db = await getInstance();
   await db.execute("DELETE FROM proyects WHERE name = ?", [name]);
   await db.execute("DELETE FROM proyects;");
+  // The receiver caches project paths on the Rust side
+  await invoke("invalidate_project_paths");
 }
 
 // deleteProyect("hola");
@@ -95,4 +98,5 @@ export async function InsertProyect(
     "INSERT INTO proyects (name, path, separator, last_selected) VALUES (?, ?, ?, ?)",
     [proyectValue, path, sepValue, 1]
   );
+  await invoke("invalidate_project_paths");
 }
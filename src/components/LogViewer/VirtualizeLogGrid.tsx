@@ -1,9 +1,11 @@
 import type { Virtualizer } from "@tanstack/react-virtual";
 import type React from "react";
+import type { LogFields } from "../../utils/logParser";
 
 interface VirtualizedGridProps {
   rowVirtualizer: Virtualizer<HTMLDivElement, Element>;
   logData: string[][];
+  logFields?: LogFields[];
   headers: string[];
   classes: string[];
   indentLevels: number[];
@@ -18,6 +20,7 @@ interface VirtualizedGridProps {
 export const VirtualizedGrid: React.FC<VirtualizedGridProps> = ({
   rowVirtualizer,
   logData,
+  logFields,
   headers,
   classes,
   indentLevels,
@@ -81,6 +84,7 @@ export const VirtualizedGrid: React.FC<VirtualizedGridProps> = ({
                 const row = logData[i];
                 const rowKey = row.join("_") + i || `row_${i}`;
                 const indent = indentLevels[i] || 0;
+                const fields = logFields?.[i];
 
                 let displayRow = row;
                 if (row.length > 3) {
@@ -103,6 +107,7 @@ export const VirtualizedGrid: React.FC<VirtualizedGridProps> = ({
                     </td>
                     {displayRow.map((item, j) => {
                       const isDescription = j === 2;
+                      const isDate = j === 1;
                       const isTaskMarker =
                         isDescription &&
                         (regexInicio.test(item) || regexFin.test(item));
@@ -115,6 +120,16 @@ export const VirtualizedGrid: React.FC<VirtualizedGridProps> = ({
                         style.backgroundColor = "#3a536e";
                       }
 
+                      // The normalized date, or a warning when no format read it
+                      let title: string | undefined;
+                      if (isDate && fields?.timestamp_error) {
+                        style.color = "#facc15";
+                        title =
+                          "Fecha no reconocida por los formatos del proyecto";
+                      } else if (isDate && fields?.timestamp) {
+                        title = fields.timestamp;
+                      }
+
                       const baseClass = `px-2 py-1 border-b font-normal text-lg break-words whitespace-normal align-top max-w-full min-w-0 ${
                         classes[j + 1]
                       } ${row[0]} cursor-pointer row`;
@@ -125,6 +140,7 @@ export const VirtualizedGrid: React.FC<VirtualizedGridProps> = ({
                           className={baseClass}
                           onContextMenu={handleRightClick}
                           style={style}
+                          title={title}
                           colSpan={j === 1 ? 2 : 8}
                         >
                           {item}
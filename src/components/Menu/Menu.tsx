@@ -2,6 +2,10 @@ import { useNavigate } from "react-router-dom";
 import { useContentPathStore } from "../../store/contentPathStore";
 import BannerForm from "../BannerForm";
 import BannerList from "../BannerList";
+import ModeSelector from "../ModeSelector/ModeSelector";
+import BrokerSettings from "../Settings/BrokerSettings";
+import ReceiverSettings from "../Settings/ReceiverSettings";
+import TrustSettings from "../Settings/TrustSettings";
 import "./Menu.scss";
 
 function Menu() {
@@ -29,6 +33,10 @@ function Menu() {
       >
         Volver
       </button>
+      <ModeSelector />
+      <BrokerSettings />
+      <ReceiverSettings />
+      {projectName && <TrustSettings projectName={projectName} />}
       {/* This container will now grow and handle overflow correctly */}
       <div className="bg-zinc-800 border-2 border-sky-950 rounded-md content flex flex-col flex-grow min-h-0 overflow-y-auto">
         <BannerForm path={path ?? ""} />
@@ -0,0 +1,73 @@
+import { invoke } from "@tauri-apps/api/core";
+import { emit, listen } from "@tauri-apps/api/event";
+import { useEffect, useState } from "react";
+import { toast } from "react-hot-toast";
+
+type AppMode = "None" | "Emitter" | "Receiver" | "Both";
+
+const MODES: { value: AppMode; label: string }[] = [
+	{ value: "None", label: "Detenido" },
+	{ value: "Emitter", label: "Emisor" },
+	{ value: "Receiver", label: "Receptor" },
+	{ value: "Both", label: "Emisor y Receptor" },
+];
+
+function ModeSelector() {
+	const [mode, setMode] = useState<AppMode>("None");
+	// The MQTT connection gave up reconnecting and the mode was stopped
+	const [stopped, setStopped] = useState(false);
+
+	useEffect(() => {
+		invoke<AppMode>("get_app_mode").then(setMode).catch(console.error);
+
+		// The tray menu changes the mode too
+		const unlisten = listen<AppMode>("app_mode_changed", (event) => {
+			setMode(event.payload);
+			setStopped(false);
+		});
+		const unlistenGaveUp = listen<number>("mqtt-gave-up", (event) => {
+			setStopped(true);
+			toast.error(
+				`Conexión MQTT perdida tras ${event.payload} intentos; el modo se detuvo`,
+			);
+		});
+
+		return () => {
+			unlisten.then((f) => f());
+			unlistenGaveUp.then((f) => f());
+		};
+	}, []);
+
+	const handleChange = async (newMode: AppMode) => {
+		setMode(newMode);
+		await emit("app_mode_changed", newMode);
+	};
+
+	return (
+		<label className="flex flex-col gap-1 text-xs text-gray-300 flex-shrink-0">
+			Modo
+			<select
+				className="bg-cyan-950 text-white p-1 rounded-md outline-none"
+				value={mode}
+				onChange={(e) => handleChange(e.target.value as AppMode)}
+			>
+				{MODES.map(({ value, label }) => (
+					<option key={value} value={value}>
+						{label}
+					</option>
+				))}
+			</select>
+			{stopped && (
+				<button
+					type="button"
+					className="back-button rounded-md text-xs"
+					onClick={() => handleChange(mode)}
+				>
+					Reintentar
+				</button>
+			)}
+		</label>
+	);
+}
+
+export default ModeSelector;
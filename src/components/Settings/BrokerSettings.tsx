@@ -0,0 +1,153 @@
+import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
+import { toast } from "react-hot-toast";
+
+// Fields of the Rust `MqttProfile` edited here; the rest are kept as loaded. The password
+// stays in the OS credential store and is only sent when typed
+interface MqttProfile {
+	host: string;
+	port: number;
+	use_tls: boolean;
+	username: string | null;
+	client_id_prefix: string;
+	[key: string]: unknown;
+}
+
+const inputClass = "bg-cyan-950 text-white p-1 rounded-md outline-none w-full";
+
+function BrokerSettings() {
+	const [profile, setProfile] = useState<MqttProfile | null>(null);
+	const [password, setPassword] = useState("");
+	const [open, setOpen] = useState(false);
+	const [testing, setTesting] = useState(false);
+
+	useEffect(() => {
+		invoke<MqttProfile>("get_mqtt_profile")
+			.then((loaded) => {
+				setProfile(loaded);
+				// Nothing can connect until a broker is configured
+				setOpen(loaded.host.trim() === "");
+			})
+			.catch((error) => toast.error(String(error)));
+	}, []);
+
+	if (!profile) {
+		return null;
+	}
+
+	const update = (changes: Partial<MqttProfile>) =>
+		setProfile({ ...profile, ...changes });
+
+	const edited = (): MqttProfile => ({
+		...profile,
+		username: profile.username?.trim() || null,
+	});
+
+	// Left empty, the stored password is kept
+	const typedPassword = () => password || null;
+
+	const handleSave = async () => {
+		try {
+			await invoke("update_mqtt_profile", {
+				profile: edited(),
+				password: typedPassword(),
+			});
+			setPassword("");
+			toast.success("Broker MQTT guardado");
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	const handleTest = async () => {
+		setTesting(true);
+		try {
+			toast.success(
+				await invoke<string>("test_mqtt_connection", {
+					profile: edited(),
+					password: typedPassword(),
+				}),
+			);
+		} catch (error) {
+			toast.error(String(error));
+		} finally {
+			setTesting(false);
+		}
+	};
+
+	return (
+		<details
+			className="text-xs text-gray-300 flex-shrink-0"
+			open={open}
+			onToggle={(e) => setOpen(e.currentTarget.open)}
+		>
+			<summary className="cursor-pointer">Broker MQTT</summary>
+			{profile.host.trim() === "" && (
+				<p className="text-yellow-400 my-1">
+					Configura el broker MQTT para usar los modos Emisor y Receptor.
+				</p>
+			)}
+			<div className="flex flex-col gap-1 mt-1">
+				<input
+					className={inputClass}
+					placeholder="Host"
+					value={profile.host}
+					onChange={(e) => update({ host: e.target.value })}
+				/>
+				<input
+					className={inputClass}
+					type="number"
+					placeholder="Puerto"
+					value={profile.port}
+					onChange={(e) => update({ port: Number(e.target.value) })}
+				/>
+				<label className="flex items-center gap-1">
+					<input
+						type="checkbox"
+						checked={profile.use_tls}
+						onChange={(e) => update({ use_tls: e.target.checked })}
+					/>
+					TLS
+				</label>
+				<input
+					className={inputClass}
+					placeholder="Usuario"
+					value={profile.username ?? ""}
+					onChange={(e) => update({ username: e.target.value })}
+				/>
+				<input
+					className={inputClass}
+					type="password"
+					placeholder="Contraseña (vacía para mantener la guardada)"
+					value={password}
+					onChange={(e) => setPassword(e.target.value)}
+				/>
+				<input
+					className={inputClass}
+					placeholder="Prefijo del client-id"
+					value={profile.client_id_prefix}
+					onChange={(e) => update({ client_id_prefix: e.target.value })}
+				/>
+				<div className="flex gap-1">
+					<button
+						type="button"
+						className="back-button rounded-md text-xs flex-1"
+						onClick={handleSave}
+					>
+						Guardar
+					</button>
+					<button
+						type="button"
+						className="back-button rounded-md text-xs flex-1"
+						onClick={handleTest}
+						disabled={testing}
+					>
+						{testing ? "Probando..." : "Probar"}
+					</button>
+				</div>
+			</div>
+		</details>
+	);
+}
+
+export default BrokerSettings;
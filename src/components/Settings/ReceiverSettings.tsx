@@ -0,0 +1,123 @@
+import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
+import { toast } from "react-hot-toast";
+
+interface ReceiverSettingsData {
+	auto_create_projects: boolean;
+	receiver_root: string | null;
+	default_separator: string;
+}
+
+const inputClass = "bg-cyan-950 text-white p-1 rounded-md outline-none w-full";
+
+function ReceiverSettings() {
+	const [settings, setSettings] = useState<ReceiverSettingsData | null>(null);
+	// Emitter allowed to create a project not registered here yet
+	const [newProject, setNewProject] = useState("");
+	const [newKey, setNewKey] = useState("");
+
+	useEffect(() => {
+		invoke<ReceiverSettingsData>("get_receiver_settings")
+			.then(setSettings)
+			.catch((error) => toast.error(String(error)));
+	}, []);
+
+	if (!settings) {
+		return null;
+	}
+
+	const update = (changes: Partial<ReceiverSettingsData>) =>
+		setSettings({ ...settings, ...changes });
+
+	const handleSave = async () => {
+		try {
+			await invoke("update_receiver_settings", {
+				newSettings: {
+					...settings,
+					receiver_root: settings.receiver_root?.trim() || null,
+				},
+			});
+			toast.success("Configuración del receptor guardada");
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	const handleAuthorize = async () => {
+		try {
+			await invoke("trust_emitter", {
+				projectName: newProject.trim(),
+				publicKey: newKey,
+				label: null,
+			});
+			toast.success(`Emisor autorizado para crear "${newProject.trim()}"`);
+			setNewProject("");
+			setNewKey("");
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	return (
+		<details className="text-xs text-gray-300 flex-shrink-0">
+			<summary className="cursor-pointer">Receptor</summary>
+			<div className="flex flex-col gap-1 mt-1">
+				<label className="flex items-center gap-1">
+					<input
+						type="checkbox"
+						checked={settings.auto_create_projects}
+						onChange={(e) => update({ auto_create_projects: e.target.checked })}
+					/>
+					Crear proyectos desconocidos automáticamente
+				</label>
+				<input
+					className={inputClass}
+					placeholder="Carpeta raíz del receptor (ruta absoluta)"
+					value={settings.receiver_root ?? ""}
+					onChange={(e) => update({ receiver_root: e.target.value })}
+				/>
+				<input
+					className={inputClass}
+					placeholder="Separador por defecto"
+					value={settings.default_separator}
+					onChange={(e) => update({ default_separator: e.target.value })}
+				/>
+				{settings.auto_create_projects && !settings.receiver_root?.trim() && (
+					<p className="text-yellow-400">
+						Indica la carpeta donde se crearán los proyectos nuevos.
+					</p>
+				)}
+				<button
+					type="button"
+					className="back-button rounded-md text-xs"
+					onClick={handleSave}
+				>
+					Guardar
+				</button>
+				<span>Solo los emisores agregados al proyecto pueden crearlo</span>
+				<input
+					className={inputClass}
+					placeholder="Nombre del proyecto"
+					value={newProject}
+					onChange={(e) => setNewProject(e.target.value)}
+				/>
+				<input
+					className={inputClass}
+					placeholder="Clave pública del emisor"
+					value={newKey}
+					onChange={(e) => setNewKey(e.target.value)}
+				/>
+				<button
+					type="button"
+					className="back-button rounded-md text-xs"
+					onClick={handleAuthorize}
+					disabled={newProject.trim() === "" || newKey.trim() === ""}
+				>
+					Autorizar emisor
+				</button>
+			</div>
+		</details>
+	);
+}
+
+export default ReceiverSettings;
@@ -0,0 +1,280 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { useCallback, useEffect, useState } from "react";
+import { toast } from "react-hot-toast";
+
+type TrustPolicy = "first_use" | "strict";
+
+interface TrustedEmitter {
+	public_key: string;
+	label: string | null;
+	added_at: number;
+	first_use: boolean;
+}
+
+interface RejectedMessage {
+	projectName: string;
+	signer: string | null;
+	reason: string;
+}
+
+const inputClass = "bg-cyan-950 text-white p-1 rounded-md outline-none w-full";
+
+function TrustSettings({ projectName }: { projectName: string }) {
+	const [publicKey, setPublicKey] = useState("");
+	const [policy, setPolicy] = useState<TrustPolicy>("strict");
+	const [trusted, setTrusted] = useState<TrustedEmitter[]>([]);
+	// Signers whose messages were rejected, so they can be trusted from here
+	const [pending, setPending] = useState<RejectedMessage[]>([]);
+	const [newKey, setNewKey] = useState("");
+	const [newLabel, setNewLabel] = useState("");
+	// Receivers allowed to request snapshots; a separate list from the emitters
+	const [requesters, setRequesters] = useState<TrustedEmitter[]>([]);
+	const [newRequester, setNewRequester] = useState("");
+
+	const loadTrusted = useCallback(() => {
+		invoke<TrustedEmitter[]>("list_trusted_emitters", { projectName })
+			.then(setTrusted)
+			.catch((error) => toast.error(String(error)));
+	}, [projectName]);
+
+	const loadRequesters = useCallback(() => {
+		invoke<TrustedEmitter[]>("list_trusted_requesters", { projectName })
+			.then(setRequesters)
+			.catch((error) => toast.error(String(error)));
+	}, [projectName]);
+
+	useEffect(() => {
+		invoke<string>("get_signing_public_key")
+			.then(setPublicKey)
+			.catch((error) => toast.error(String(error)));
+		invoke<TrustPolicy>("get_project_trust_policy", { projectName })
+			.then(setPolicy)
+			.catch(console.error);
+		loadTrusted();
+		loadRequesters();
+		setPending([]);
+
+		const unlistenRejected = listen<RejectedMessage>("message-rejected", (event) => {
+			const { payload } = event;
+			if (payload.projectName !== projectName || !payload.signer) {
+				return;
+			}
+			setPending((current) =>
+				current.some((entry) => entry.signer === payload.signer)
+					? current
+					: [...current, payload],
+			);
+		});
+		const unlistenTrusted = listen<{ projectName: string; signer: string }>(
+			"emitter-trusted",
+			(event) => {
+				if (event.payload.projectName !== projectName) {
+					return;
+				}
+				toast.success(`Emisor ${event.payload.signer} aceptado en su primer uso`);
+				loadTrusted();
+			},
+		);
+
+		return () => {
+			unlistenRejected.then((f) => f());
+			unlistenTrusted.then((f) => f());
+		};
+	}, [projectName, loadTrusted, loadRequesters]);
+
+	const handlePolicy = async (newPolicy: TrustPolicy) => {
+		try {
+			await invoke("set_project_trust_policy", { projectName, policy: newPolicy });
+			setPolicy(newPolicy);
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	const handleTrust = async (key: string, label: string | null) => {
+		try {
+			await invoke("trust_emitter", { projectName, publicKey: key, label });
+			setPending((current) => current.filter((entry) => entry.signer !== key));
+			setNewKey("");
+			setNewLabel("");
+			loadTrusted();
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	const handleUntrust = async (key: string) => {
+		try {
+			await invoke("untrust_emitter", { projectName, publicKey: key });
+			loadTrusted();
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	const handleTrustRequester = async () => {
+		try {
+			await invoke("trust_requester", {
+				projectName,
+				publicKey: newRequester,
+				label: null,
+			});
+			setNewRequester("");
+			loadRequesters();
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	const handleUntrustRequester = async (key: string) => {
+		try {
+			await invoke("untrust_requester", { projectName, publicKey: key });
+			loadRequesters();
+		} catch (error) {
+			toast.error(String(error));
+		}
+	};
+
+	const handleCopy = async () => {
+		await navigator.clipboard.writeText(publicKey);
+		toast.success("Clave pública copiada");
+	};
+
+	return (
+		<details className="text-xs text-gray-300 flex-shrink-0">
+			<summary className="cursor-pointer">Emisores de confianza</summary>
+			<div className="flex flex-col gap-1 mt-1">
+				<span>Clave pública de este equipo</span>
+				<div className="flex gap-1">
+					<input className={inputClass} value={publicKey} readOnly />
+					<button
+						type="button"
+						className="back-button rounded-md text-xs"
+						onClick={handleCopy}
+					>
+						Copiar
+					</button>
+				</div>
+				<label className="flex flex-col gap-1">
+					Política
+					<select
+						className={inputClass}
+						value={policy}
+						onChange={(e) => handlePolicy(e.target.value as TrustPolicy)}
+					>
+						<option value="strict">Solo emisores agregados</option>
+						<option value="first_use">Fijar el primer emisor</option>
+					</select>
+				</label>
+				{trusted.length === 0 && (
+					<p className="text-yellow-400">
+						{policy === "first_use"
+							? "El primer emisor con firma válida quedará fijado. Cualquier cliente del broker puede ser ese emisor."
+							: "No se acepta ningún mensaje hasta agregar un emisor."}
+					</p>
+				)}
+				{trusted.map((emitter) => (
+					<div key={emitter.public_key} className="flex gap-1 items-center">
+						<span
+							className={`truncate flex-1 ${emitter.first_use ? "text-yellow-400" : ""}`}
+							title={emitter.public_key}
+						>
+							{emitter.first_use
+								? "Fijado en el primer uso: "
+								: emitter.label
+									? `${emitter.label}: `
+									: ""}
+							{emitter.public_key}
+						</span>
+						{emitter.first_use && (
+							<button
+								type="button"
+								className="back-button rounded-md text-xs"
+								onClick={() => handleTrust(emitter.public_key, null)}
+							>
+								Confirmar
+							</button>
+						)}
+						<button
+							type="button"
+							className="back-button rounded-md text-xs"
+							onClick={() => handleUntrust(emitter.public_key)}
+						>
+							Quitar
+						</button>
+					</div>
+				))}
+				{pending.map((entry) => (
+					<div key={entry.signer} className="flex gap-1 items-center">
+						<span
+							className="truncate flex-1 text-red-400"
+							title={`${entry.signer}\n${entry.reason}`}
+						>
+							Rechazado: {entry.signer}
+						</span>
+						<button
+							type="button"
+							className="back-button rounded-md text-xs"
+							onClick={() => entry.signer && handleTrust(entry.signer, null)}
+						>
+							Confiar
+						</button>
+					</div>
+				))}
+				<input
+					className={inputClass}
+					placeholder="Clave pública del emisor"
+					value={newKey}
+					onChange={(e) => setNewKey(e.target.value)}
+				/>
+				<input
+					className={inputClass}
+					placeholder="Etiqueta (opcional)"
+					value={newLabel}
+					onChange={(e) => setNewLabel(e.target.value)}
+				/>
+				<button
+					type="button"
+					className="back-button rounded-md text-xs"
+					onClick={() => handleTrust(newKey, newLabel.trim() || null)}
+					disabled={newKey.trim() === ""}
+				>
+					Agregar emisor
+				</button>
+				<span className="mt-1">Receptores que pueden pedir instantáneas</span>
+				{requesters.map((requester) => (
+					<div key={requester.public_key} className="flex gap-1 items-center">
+						<span className="truncate flex-1" title={requester.public_key}>
+							{requester.label ? `${requester.label}: ` : ""}
+							{requester.public_key}
+						</span>
+						<button
+							type="button"
+							className="back-button rounded-md text-xs"
+							onClick={() => handleUntrustRequester(requester.public_key)}
+						>
+							Quitar
+						</button>
+					</div>
+				))}
+				<input
+					className={inputClass}
+					placeholder="Clave pública del receptor"
+					value={newRequester}
+					onChange={(e) => setNewRequester(e.target.value)}
+				/>
+				<button
+					type="button"
+					className="back-button rounded-md text-xs"
+					onClick={handleTrustRequester}
+					disabled={newRequester.trim() === ""}
+				>
+					Agregar receptor
+				</button>
+			</div>
+		</details>
+	);
+}
+
+export default TrustSettings;
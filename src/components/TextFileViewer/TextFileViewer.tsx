@@ -5,7 +5,7 @@ import { useVirtualizer } from "@tanstack/react-virtual";
 import { listen } from "@tauri-apps/api/event";
 import toast from "react-hot-toast";
 import { ResizeTable } from "../../config/resizeTable";
-import type { LogEntries } from "../../utils/logParser";
+import type { LogEntries, LogFields } from "../../utils/logParser";
 import { parseLogEntries } from "../../utils/logParser";
 import LogAnalysisViewer from "../LogViewer/LogAnalysisViewer";
 import { VirtualizedGrid } from "../LogViewer/VirtualizeLogGrid";
@@ -27,6 +27,65 @@ interface TaskInfo {
   lineNumber: number;
 }
 
+// Rows asked for at a time; the Rust side returns at most 5000
+const PAGE_ROWS = 5000;
+
+interface LogPage {
+  rows: string[][];
+  fields: LogFields[];
+  total_rows: number;
+  complete: boolean;
+  maquina: string | null;
+}
+
+// Reads every row of the file through a log session, page by page while the
+// Rust side indexes it; `onRows` gets the rows read so far after each page
+const readLogRows = async (
+  path: string,
+  delimiter: string,
+  onRows: (
+    rows: string[][],
+    fields: LogFields[],
+    maquina: string | null
+  ) => void,
+  cancelled: () => boolean
+) => {
+  const { session_id: sessionId } = await invoke<{ session_id: number }>(
+    "open_log_session",
+    { path, delimiter }
+  );
+  const rows: string[][] = [];
+  const fields: LogFields[] = [];
+  let maquina: string | null = null;
+  try {
+    while (!cancelled()) {
+      const page = await invoke<LogPage>("get_log_rows", {
+        sessionId,
+        start: rows.length,
+        count: PAGE_ROWS,
+      });
+      rows.push(...page.rows);
+      fields.push(...page.fields);
+      maquina = page.maquina;
+      if (page.rows.length > 0) {
+        onRows([...rows], [...fields], maquina);
+      }
+      if (page.complete && rows.length >= page.total_rows) {
+        break;
+      }
+      if (page.rows.length === 0) {
+        // Wait for the index to grow
+        await new Promise((resolve) => setTimeout(resolve, 200));
+      }
+    }
+    if (!cancelled() && rows.length === 0) {
+      onRows(rows, fields, maquina);
+    }
+  } finally {
+    await invoke("close_log_session", { sessionId });
+  }
+};
+
 const analyzeAndGetIndentations = (data: string[][]) => {
   const levels: number[] = [];
   const taskStack: TaskInfo[] = [];
@@ -63,6 +122,7 @@ const analyzeAndGetIndentations = (data: string[][]) => {
 
 function TextFileViewer({ path, delimiter }: TextFileViewerProps) {
   const [logData, setLogData] = useState<string[][]>([]);
+  const [logFields, setLogFields] = useState<LogFields[]>([]);
   const [logEntries, setLogEntries] = useState<LogEntries>({
     errors: [],
     warnings: [],
@@ -83,6 +143,8 @@ function TextFileViewer({ path, delimiter }: TextFileViewerProps) {
   const classes = ["show", "hidden", "show", "show"];
   const timeoutRef = useRef<NodeJS.Timeout>();
   const unlistenRef = useRef<(() => void) | null>(null);
+  // Bumped by every read, so an older one still running stops
+  const readIdRef = useRef(0);
 
   const rowVirtualizer = useVirtualizer({
     count: logData.length,
@@ -91,29 +153,45 @@ function TextFileViewer({ path, delimiter }: TextFileViewerProps) {
     overscan: 5,
   });
 
+  const showRows = useCallback(
+    (rows: string[][], fields: LogFields[], maquinaValue: string | null) => {
+      setLogData(rows);
+      setLogFields(fields);
+      setMaquina(maquinaValue ?? null);
+
+      // Actualizar logEntries con el contenido parseado
+      const linesComplete = rows.map((row) => `${row[2]}`);
+      const linesType = rows.map((row, i) => fields[i]?.level ?? row[0]);
+
+      const parsedEntries = parseLogEntries(linesType, linesComplete);
+      setLogEntries(parsedEntries);
+    },
+    []
+  );
+
+  const readFile = useCallback(async () => {
+    const readId = ++readIdRef.current;
+    await readLogRows(
+      path,
+      delimiter,
+      (rows, fields, maquinaValue) => {
+        showRows(rows, fields, maquinaValue);
+        setLoading(false);
+      },
+      () => readIdRef.current !== readId
+    );
+  }, [path, delimiter, showRows]);
+
   const debouncedReload = useCallback(() => {
     if (timeoutRef.current) clearTimeout(timeoutRef.current);
     timeoutRef.current = setTimeout(async () => {
       try {
-        const [rows, maquinaValue]: [string[][], string | null] = await invoke(
-          "process_log_file",
-          { path, delimiter }
-        );
-
-        setLogData(rows);
-        setMaquina(maquinaValue ?? null);
-
-        // Actualizar logEntries con el contenido parseado
-        const linesComplete = rows.map((row) => `${row[2]}`);
-        const linesType = rows.map((row) => row[0]);
-
-        const parsedEntries = parseLogEntries(linesType, linesComplete);
-        setLogEntries(parsedEntries);
+        await readFile();
       } catch (error) {
         console.error("Error reloading file:", error);
       }
     }, 500);
-  }, [path, delimiter]);
+  }, [readFile]);
 
   useEffect(() => {
     const setupWatcher = async () => {
@@ -129,19 +207,7 @@ function TextFileViewer({ path, delimiter }: TextFileViewerProps) {
   useEffect(() => {
     const loadData = async () => {
       try {
-        const [data, maquinaValue]: [string[][], string | null] = await invoke(
-          "process_log_file",
-          { path, delimiter }
-        );
-        if (data.length > 0) {
-          setLogData(data);
-          const linesComplete = data.map((row) => `${row[2]}`);
-          const linesType = data.map((row) => row[0]);
-
-          const parsedEntries = parseLogEntries(linesType, linesComplete);
-          setLogEntries(parsedEntries);
-        }
-        setMaquina(maquinaValue ?? null);
+        await readFile();
         unlistenRef.current = await listen("file-change", () => {
           debouncedReload();
         });
@@ -163,8 +229,10 @@ function TextFileViewer({ path, delimiter }: TextFileViewerProps) {
         unlistenRef.current = null; // Clear ref after unlistening
       }
       if (timeoutRef.current) clearTimeout(timeoutRef.current);
+      // Stops a read still in progress, which closes its session
+      readIdRef.current += 1;
     };
-  }, [path, delimiter, debouncedReload]);
+  }, [readFile, debouncedReload]);
 
   console.log(logData);
 
@@ -404,6 +472,7 @@ function TextFileViewer({ path, delimiter }: TextFileViewerProps) {
         headers={headers}
         classes={classes}
         logData={logData}
+        logFields={logFields}
         rowVirtualizer={rowVirtualizer}
         indentLevels={indentLevels}
         getIndentStyle={getIndentStyle}
@@ -130,6 +130,21 @@ export function extractDateFromFilename(filename: string): string | null {
 	return match ? match[0] : null;
 }
 
+/** Fields the Rust side extracts from each record (`LogFields`). */
+export interface LogFields {
+	/** RFC 3339, read with the project's timestamp formats */
+	timestamp: string | null;
+	/** The timestamp as written in the log */
+	raw_timestamp: string | null;
+	/** The record has a timestamp none of the formats could read */
+	timestamp_error: boolean;
+	level: string | null;
+	bot: string | null;
+	machine: string | null;
+	process: string | null;
+	message: string | null;
+}
+
 export interface LogEntry {
 	line: string;
 	lineNumber: number;